@@ -0,0 +1,59 @@
+// 比较 Vec<Opcode> 枚举指令流与按字节编码的指令流的内存占用与解码速度
+// 运行: cargo bench --bench dispatch
+use std::{hint::black_box, mem::size_of, time::Instant};
+use tlang::{chunk::{chunk::Chunk, op::Opcode, value::Value}, vm::vm::VM};
+
+fn build(n: usize) -> (Vec<Opcode>, Chunk) {
+    let mut ops = Vec::new();
+    let mut chunk = Chunk::new();
+    let index = chunk.push_val(Value::Double(1.0));
+    for i in 0..n {
+        let op = match i % 4 {
+            0 => Opcode::OP_CONST(index),
+            1 => Opcode::OP_GET_LOCAL(0),
+            2 => Opcode::OP_ADD,
+            _ => Opcode::OP_POP,
+        };
+        ops.push(op);
+        chunk.push_op(op, 1);
+    }
+    (ops, chunk)
+}
+
+fn operand(op: Opcode) -> usize {
+    match op {
+        Opcode::OP_CONST(x) | Opcode::OP_GET_LOCAL(x) => x + 1,
+        _ => 1,
+    }
+}
+
+fn main() {
+    let n = 4_000_000;
+    let (ops, chunk) = build(n);
+    println!("{} instructions", n);
+    println!("enum  stream: {:>10} bytes", ops.len() * size_of::<Opcode>());
+    println!("byte  stream: {:>10} bytes", chunk.op_len());
+
+    let start = Instant::now();
+    let mut sum = 0;
+    for op in ops.iter() {
+        sum += operand(*op);
+    }
+    black_box(sum);
+    println!("enum  decode: {:>10.2?}", start.elapsed());
+
+    let start = Instant::now();
+    let mut sum = 0;
+    let mut offset = 0;
+    while let Some((op, size)) = chunk.get_op(offset) {
+        sum += operand(op);
+        offset += size;
+    }
+    black_box(sum);
+    println!("byte  decode: {:>10.2?}", start.elapsed());
+
+    let mut vm = VM::from(Chunk::new());
+    let start = Instant::now();
+    vm.interpret("let i = 0; let s = 0; while i < 1000000 { s = s + i * 2; i = i + 1; }");
+    println!("vm loop 1e6:  {:>10.2?}", start.elapsed());
+}
@@ -0,0 +1,61 @@
+// 比较一次性生成全部词素与按需拉取词素的耗时和内存峰值
+// 运行: cargo bench --bench scanner
+use std::{alloc::{GlobalAlloc, Layout, System}, sync::atomic::{AtomicUsize, Ordering}, hint::black_box, time::Instant};
+use tlang::lexical_analysis::scanner::Scanner;
+
+#[path = "scanner/baseline.rs"]
+mod baseline;
+
+struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let now = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        PEAK.fetch_max(now, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+fn measure<F: FnOnce() -> usize>(name: &str, f: F) {
+    let base = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(base, Ordering::SeqCst);
+
+    let start = Instant::now();
+    let count = f();
+    let time = start.elapsed();
+
+    let peak = PEAK.load(Ordering::SeqCst) - base;
+    println!("{:<12} {:>9} tokens {:>10.2?} peak {:>10.1} KiB", name, count, time, peak as f64 / 1024.0);
+}
+
+fn main() {
+    let snippet = "let 变量 = 1.5 * (2 + 3) / 4;\nwhile 变量 < 100 { 变量 = 变量 + 1; print \"loop\"; } // 注释\n";
+    let code = snippet.repeat(8 * 1024 * 1024 / snippet.len());
+    println!("input: {:.2} MiB", code.len() as f64 / (1024.0 * 1024.0));
+
+    // 旧实现：源码复制为 Vec<char>，再生成完整的词素列表
+    // 旧的 Scanner::new 取得源码的所有权，复制不计入测量
+    let owned = code.clone();
+    measure("materialized", move || {
+        let mut scanner = baseline::Scanner::new(owned);
+        match scanner.scann() {
+            Ok(tokens) => black_box(tokens).len(),
+            Err(_) => 0,
+        }
+    });
+
+    measure("streaming", || {
+        Scanner::new(&code).count()
+    });
+}
@@ -1,59 +1,105 @@
+use std::ops::Range;
 use super::{op::*, value::*,};
 
 pub struct Chunk {
-    ops: Vec<Opcode>,
+    code: Vec<u8>, // 按字节编码的指令流，见 Opcode::encode
     vals: Vec<Value>,
     line: Vec<(usize, u32)>,
+    dense_line: Option<Vec<u32>>, // 每个字节对应的行号，见 set_dense_lines
+    resumes: Vec<usize>, // 每条 OP_FINALLY_JUMP 之后的偏移，即 finally 结束后可以继续执行的位置
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chunk {
     pub fn new() -> Self {
         Self {
-            ops: Vec::new(),
+            code: Vec::new(),
             vals: Vec::new(),
             line: Vec::new(),
+            dense_line: None,
+            resumes: Vec::new(),
         }
     }
 
-    pub fn get_line(&self, op_index: usize) -> Option<u32> {
-        let mut start = 0;
-        let mut end = self.line.len() - 1;
+    // 返回字节偏移 offset 处指令所在的行，offset 越界时返回 None
+    pub fn get_line(&self, offset: usize) -> Option<u32> {
+        if offset >= self.code.len() {
+            return None;
+        }
+        if let Some(dense) = &self.dense_line {
+            return dense.get(offset).copied();
+        }
 
-        let get_index = |x| {
-            let (index, _) = self.line[x];
-            index
-        };
+        self.get_line_span(offset).map(|(_, line)| line)
+    }
+
+    // 返回 offset 所在的同一行指令的字节范围及行号
+    pub fn get_line_span(&self, offset: usize) -> Option<(Range<usize>, u32)> {
+        if offset >= self.code.len() {
+            return None;
+        }
 
-        let get_line = | x | {
-            let (_, line) = self.line[x];
-            line
+        // 第一个起始偏移大于 offset 的项之前即为所在的项
+        let run = self.line.partition_point(|&(start, _)| start <= offset).checked_sub(1)?;
+        let (start, line) = self.line[run];
+        let end = match self.line.get(run + 1) {
+            Some(&(next, _)) => next,
+            None => self.code.len(),
         };
+        Some((start..end, line))
+    }
 
-        loop {
-            let mid = (start + end) / 2;
-            if op_index < get_index(mid) {
-                end = mid - 1;
-            }else if mid == self.line.len()-1 || op_index < get_index(mid+1) {
-                return Some(get_line(mid));
-            }else {
-                start = mid + 1;
+    // 为每个字节记录行号，以内存换取 O(1) 的查询，供调试器和性能分析器逐步查询
+    pub fn set_dense_lines(&mut self, dense: bool) {
+        self.dense_line = if dense {
+            let mut lines = Vec::with_capacity(self.code.len());
+            for (i, &(start, line)) in self.line.iter().enumerate() {
+                let end = self.line.get(i + 1).map_or(self.code.len(), |&(next, _)| next);
+                lines.resize(lines.len() + (end - start), line);
             }
-        }
+            Some(lines)
+        }else {
+            None
+        };
     }
 
-    pub fn get_op(&self, index: usize) -> Option<&Opcode> {
-        self.ops.get(index)
+    // 解码 offset 处的指令，返回指令及其字节数
+    pub fn get_op(&self, offset: usize) -> Option<(Opcode, usize)> {
+        Opcode::decode(&self.code, offset)
+    }
+
+    // 回填跳转指令的偏移量，新指令须与原指令等长
+    pub fn set_op(&mut self, offset: usize, op: Opcode) {
+        let mut buf = Vec::new();
+        op.encode(&mut buf);
+        if let Some(old) = self.code.get_mut(offset..offset + buf.len()) {
+            old.copy_from_slice(&buf);
+        }
     }
 
     pub fn push_op(&mut self, op: Opcode, line: u32) {
-        self.ops.push(op);
+        let offset = self.code.len();
+        op.encode(&mut self.code);
 
         if let Some(&(_, old_line)) = self.line.last() {
             if old_line != line {
-                self.line.push((self.ops.len()-1, line));
+                self.line.push((offset, line));
             }
         }else {
-            self.line.push((self.ops.len()-1, line));
+            self.line.push((offset, line));
+        }
+
+        if let Some(dense) = &mut self.dense_line {
+            dense.resize(self.code.len(), line);
+        }
+        // 编译与反序列化都经过这里，记录的位置只来自实际存在的指令
+        if let Opcode::OP_FINALLY_JUMP = op {
+            self.resumes.push(self.code.len());
         }
     }
 
@@ -69,8 +115,86 @@ impl Chunk {
         }
     }
 
+    // 指令流的字节数
     pub fn op_len(&self) -> usize {
-        self.ops.len()
+        self.code.len()
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    pub fn val_len(&self) -> usize {
+        self.vals.len()
+    }
+
+    // break 或 continue 经过 finally 后只能回到 OP_FINALLY_JUMP 之后的位置
+    pub fn is_resume(&self, offset: usize) -> bool {
+        self.resumes.binary_search(&offset).is_ok()
+    }
+
+    // 行号表：(该行第一条指令的字节偏移, 行号)
+    pub fn lines(&self) -> &[(usize, u32)] {
+        &self.line
     }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn get_line_test() {
+        let mut chunk = Chunk::new();
+        assert_eq!(chunk.get_line(0), None);
+
+        chunk.push_op(Opcode::OP_NULL, 3);
+        chunk.push_op(Opcode::OP_CONST(0), 3);
+        chunk.push_op(Opcode::OP_RETURN, 5);
+        assert_eq!(chunk.get_line(0), Some(3));
+        assert_eq!(chunk.get_line(2), Some(3));
+        assert_eq!(chunk.get_line(3), Some(5));
+        assert_eq!(chunk.get_line(4), None);
+        assert_eq!(chunk.get_line_span(1), Some((0..3, 3)));
+        assert_eq!(chunk.get_line_span(3), Some((3..4, 5)));
+    }
+
+    proptest! {
+        // 与逐条指令记录行号的朴素实现比较，并检查 dense 模式结果一致
+        #[test]
+        fn get_line_prop(steps in prop::collection::vec((0usize..4, 0u32..3), 0..64), probe in 0usize..256) {
+            let mut chunk = Chunk::new();
+            let mut naive = Vec::new();
+            let mut line = 1;
+            for (kind, step) in steps {
+                line += step;
+                let op = match kind {
+                    0 => Opcode::OP_NULL,
+                    1 => Opcode::OP_CONST(1),
+                    2 => Opcode::OP_JUMP(2),
+                    _ => Opcode::OP_GET_GLOBAL(3),
+                };
+                chunk.push_op(op, line);
+                naive.resize(chunk.op_len(), line);
+            }
+
+            for offset in 0..chunk.op_len() + 2 {
+                prop_assert_eq!(chunk.get_line(offset), naive.get(offset).copied());
+            }
+            prop_assert_eq!(chunk.get_line(probe), naive.get(probe).copied());
+            if let Some((span, line)) = chunk.get_line_span(probe) {
+                prop_assert!(span.contains(&probe));
+                prop_assert!(span.clone().all(|i| naive[i] == line));
+            }
+
+            chunk.set_dense_lines(true);
+            chunk.push_op(Opcode::OP_RETURN, line + 1);
+            naive.resize(chunk.op_len(), line + 1);
+            for offset in 0..chunk.op_len() + 2 {
+                prop_assert_eq!(chunk.get_line(offset), naive.get(offset).copied());
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::{collections::{BTreeMap, HashMap}, fmt::Display};
+use crate::interpreter_error::catalog::*;
+use super::value::*;
+
+// Rust 类型与 Value 之间的转换，供本地函数与宿主程序使用
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+pub trait FromValue: Sized {
+    fn from_value(v: &Value) -> Result<Self, ConvertError>;
+}
+
+// 转换失败时记录期望的类型与实际的类型
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertError {
+    pub expected: String,
+    pub actual: &'static str,
+}
+
+impl ConvertError {
+    pub fn new(expected: impl Into<String>, actual: &Value) -> Self {
+        Self { expected: expected.into(), actual: actual.type_name() }
+    }
+}
+
+impl Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", trf("expected {} but got {}.", &[&self.expected, &self.actual]))
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl Value {
+    // 如 `let n: i64 = v.get()?;`
+    pub fn get<T: FromValue>(&self) -> Result<T, ConvertError> {
+        T::from_value(self)
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::Double(self)
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Value::Double(self as f64)
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Null
+    }
+}
+
+// None 对应 null
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        match self {
+            Some(v) => v.into_value(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::list(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<T: IntoValue> IntoValue for HashMap<String, T> {
+    fn into_value(self) -> Value {
+        Value::map(self.into_iter().map(|(k, v)| (k, v.into_value())).collect())
+    }
+}
+
+impl<T: IntoValue> IntoValue for BTreeMap<String, T> {
+    fn into_value(self) -> Value {
+        Value::map(self.into_iter().map(|(k, v)| (k, v.into_value())).collect())
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        Ok(v.clone())
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match v {
+            Value::Double(d) => Ok(*d),
+            _ => Err(ConvertError::new("number", v)),
+        }
+    }
+}
+
+// 带小数部分或超出 i64 范围的数字视为类型不符
+impl FromValue for i64 {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match v {
+            Value::Double(d) if d.fract() == 0.0 && *d >= i64::MIN as f64 && *d < i64::MAX as f64 => Ok(*d as i64),
+            Value::Double(_) => Err(ConvertError { expected: "integer".to_string(), actual: "number" }),
+            _ => Err(ConvertError::new("integer", v)),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match v {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(ConvertError::new("bool", v)),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match v {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(ConvertError::new("string", v)),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match v {
+            Value::Null => Ok(None),
+            _ => match T::from_value(v) {
+                Ok(x) => Ok(Some(x)),
+                Err(e) => Err(ConvertError { expected: format!("{} or null", e.expected), actual: e.actual }),
+            },
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match v {
+            Value::List(l) => l.borrow().iter().map(T::from_value).collect(),
+            _ => Err(ConvertError::new("list", v)),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match v {
+            Value::Map(m) => m.borrow().iter().map(|(k, v)| Ok((k.clone(), T::from_value(v)?))).collect(),
+            _ => Err(ConvertError::new("map", v)),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for BTreeMap<String, T> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match v {
+            Value::Map(m) => m.borrow().iter().map(|(k, v)| Ok((k.clone(), T::from_value(v)?))).collect(),
+            _ => Err(ConvertError::new("map", v)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scalar_test() {
+        assert_eq!(1.5.into_value(), Value::Double(1.5));
+        assert_eq!(3i64.into_value(), Value::Double(3.0));
+        assert_eq!("a".into_value(), Value::String("a".to_string()));
+        assert_eq!(None::<bool>.into_value(), Value::Null);
+
+        assert_eq!(Value::Double(2.0).get::<i64>(), Ok(2));
+        assert_eq!(Value::Bool(true).get::<bool>(), Ok(true));
+        assert_eq!(Value::Null.get::<Option<String>>(), Ok(None));
+        assert_eq!(Value::Double(2.5).get::<i64>().unwrap_err().to_string(), "expected integer but got number.");
+        assert_eq!(Value::Bool(true).get::<Option<f64>>().unwrap_err().to_string(), "expected number or null but got bool.");
+    }
+
+    #[test]
+    fn collection_test() {
+        let v = vec![vec![1i64, 2], vec![]].into_value();
+        assert_eq!(v.to_string(), "[[1, 2], []]");
+        assert_eq!(v.get::<Vec<Vec<i64>>>(), Ok(vec![vec![1, 2], vec![]]));
+        assert_eq!(v.get::<Vec<String>>().unwrap_err(), ConvertError { expected: "string".to_string(), actual: "list" });
+
+        let mut m = HashMap::new();
+        m.insert("a".to_string(), Some("x"));
+        m.insert("b".to_string(), None);
+        let v = m.into_value();
+        assert_eq!(v.to_string(), "{\"a\": \"x\", \"b\": null}");
+        let back: HashMap<String, Option<String>> = v.get().unwrap();
+        assert_eq!(back["a"], Some("x".to_string()));
+        assert_eq!(Value::Null.get::<HashMap<String, f64>>().unwrap_err().to_string(), "expected map but got null.");
+    }
+
+    #[test]
+    fn cycle_test() {
+        let v = Value::list(vec![Value::Null]);
+        if let Value::List(l) = &v {
+            l.borrow_mut().push(v.clone());
+        }
+        assert_eq!(v.to_string(), "[null, [...]]");
+
+        // 含有自身的列表按身份比较，与脚本中的 == 一致
+        assert_eq!(v, v.clone());
+        assert_ne!(Value::list(vec![Value::Null]), Value::list(vec![Value::Null]));
+    }
+}
@@ -1,12 +1,44 @@
 #[allow(non_camel_case_types)]
 pub enum Opcode {
     OP_RETURN,
-    OP_CONST(usize), // val_index
+    OP_CONST(usize), // val_index < 256
+    OP_CONST_LONG(usize), // val_index
+    OP_NULL,
+    OP_TRUE,
+    OP_FALSE,
     OP_NEGATE,
+    OP_NOT,
     OP_ADD,
     OP_SUB,
     OP_MULTIPLY,
     OP_DIVIDE,
+    OP_EQUAL,
+    OP_GREATER,
+    OP_LESS,
+    OP_PRINT,
+    OP_POP,
+    OP_DEFINE_GLOBAL(usize), // val_index of name
+    OP_GET_GLOBAL(usize), // val_index of name
+    OP_SET_GLOBAL(usize), // val_index of name
+    OP_GET_LOCAL(usize), // stack slot
+    OP_SET_LOCAL(usize), // stack slot
+    OP_JUMP(usize), // forward offset
+    OP_JUMP_IF_FALSE(usize), // forward offset
+    OP_LOOP(usize), // backward offset
+    OP_CALL(usize), // argument count
+    OP_GET_PROPERTY(usize), // val_index of name
+    OP_IMPORT(usize), // val_index of module path or name
+    OP_TRY(usize), // forward offset of catch
+    OP_TRY_FINALLY(usize), // forward offset of finally
+    OP_END_TRY,
+    OP_THROW,
+    OP_END_FINALLY,
+    OP_ITER,
+    OP_FOR_ITER(usize), // forward offset of loop exit
+    OP_FOR_ENTRY(usize), // forward offset of loop exit
+    OP_RANGE,
+    OP_RANGE_INCLUSIVE,
+    OP_FINALLY_JUMP,
 }
 
 impl Copy for Opcode {}
@@ -16,3 +48,176 @@ impl Clone for Opcode {
         *self
     }
 }
+
+// 指令在字节流中的编码：1 字节操作码，随后为定长小端序操作数
+//   OP_CONST, OP_GET_LOCAL, OP_SET_LOCAL, OP_CALL: u8
+//   OP_JUMP, OP_JUMP_IF_FALSE, OP_LOOP, OP_TRY, OP_TRY_FINALLY, OP_FOR_ITER, OP_FOR_ENTRY: u16
+//   OP_CONST_LONG, OP_*_GLOBAL, OP_GET_PROPERTY, OP_IMPORT: u24
+pub const MAX_SHORT_CONST: usize = u8::MAX as usize;
+pub const MAX_CONSTS: usize = 1 << 24;
+pub const MAX_LOCALS: usize = u8::MAX as usize + 1;
+pub const MAX_JUMP: usize = u16::MAX as usize;
+pub const MAX_ARGS: usize = u8::MAX as usize;
+
+impl Opcode {
+    fn code(&self) -> u8 {
+        match self {
+            Self::OP_RETURN => 0,
+            Self::OP_CONST(_) => 1,
+            Self::OP_CONST_LONG(_) => 2,
+            Self::OP_NULL => 3,
+            Self::OP_TRUE => 4,
+            Self::OP_FALSE => 5,
+            Self::OP_NEGATE => 6,
+            Self::OP_NOT => 7,
+            Self::OP_ADD => 8,
+            Self::OP_SUB => 9,
+            Self::OP_MULTIPLY => 10,
+            Self::OP_DIVIDE => 11,
+            Self::OP_EQUAL => 12,
+            Self::OP_GREATER => 13,
+            Self::OP_LESS => 14,
+            Self::OP_PRINT => 15,
+            Self::OP_POP => 16,
+            Self::OP_DEFINE_GLOBAL(_) => 17,
+            Self::OP_GET_GLOBAL(_) => 18,
+            Self::OP_SET_GLOBAL(_) => 19,
+            Self::OP_GET_LOCAL(_) => 20,
+            Self::OP_SET_LOCAL(_) => 21,
+            Self::OP_JUMP(_) => 22,
+            Self::OP_JUMP_IF_FALSE(_) => 23,
+            Self::OP_LOOP(_) => 24,
+            Self::OP_CALL(_) => 25,
+            Self::OP_GET_PROPERTY(_) => 26,
+            Self::OP_IMPORT(_) => 27,
+            Self::OP_TRY(_) => 28,
+            Self::OP_TRY_FINALLY(_) => 29,
+            Self::OP_END_TRY => 30,
+            Self::OP_THROW => 31,
+            Self::OP_END_FINALLY => 32,
+            Self::OP_ITER => 33,
+            Self::OP_FOR_ITER(_) => 34,
+            Self::OP_FOR_ENTRY(_) => 35,
+            Self::OP_RANGE => 36,
+            Self::OP_RANGE_INCLUSIVE => 37,
+            Self::OP_FINALLY_JUMP => 38,
+        }
+    }
+
+    // (操作数, 操作数字节数)
+    fn operand(&self) -> (usize, usize) {
+        match *self {
+            Self::OP_CONST(x) | Self::OP_GET_LOCAL(x) | Self::OP_SET_LOCAL(x) | Self::OP_CALL(x) => (x, 1),
+            Self::OP_JUMP(x) | Self::OP_JUMP_IF_FALSE(x) | Self::OP_LOOP(x)
+            | Self::OP_TRY(x) | Self::OP_TRY_FINALLY(x) | Self::OP_FOR_ITER(x) | Self::OP_FOR_ENTRY(x) => (x, 2),
+            Self::OP_CONST_LONG(x) | Self::OP_DEFINE_GLOBAL(x)
+            | Self::OP_GET_GLOBAL(x) | Self::OP_SET_GLOBAL(x) | Self::OP_GET_PROPERTY(x) | Self::OP_IMPORT(x) => (x, 3),
+            _ => (0, 0),
+        }
+    }
+
+    // 编码后的字节数
+    pub fn size(&self) -> usize {
+        1 + self.operand().1
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.code());
+        let (x, width) = self.operand();
+        out.extend_from_slice(&(x as u32).to_le_bytes()[..width]);
+    }
+
+    // 操作码对应的操作数字节数，未知操作码返回 None
+    pub fn operand_width(code: u8) -> Option<usize> {
+        match code {
+            1 | 20 | 21 | 25 => Some(1),
+            22..=24 | 28 | 29 | 34 | 35 => Some(2),
+            2 | 17..=19 | 26 | 27 => Some(3),
+            0..=38 => Some(0),
+            _ => None,
+        }
+    }
+
+    // 解码 offset 处的指令，返回指令及其字节数；越界或未知操作码时返回 None
+    #[inline]
+    pub fn decode(code: &[u8], offset: usize) -> Option<(Opcode, usize)> {
+        let u8_at = || code.get(offset + 1).map(|&b| b as usize);
+        let u16_at = || code.get(offset + 1..offset + 3).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize);
+        let u24_at = || code.get(offset + 1..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], 0]) as usize);
+
+        Some(match *code.get(offset)? {
+            0 => (Self::OP_RETURN, 1),
+            1 => (Self::OP_CONST(u8_at()?), 2),
+            2 => (Self::OP_CONST_LONG(u24_at()?), 4),
+            3 => (Self::OP_NULL, 1),
+            4 => (Self::OP_TRUE, 1),
+            5 => (Self::OP_FALSE, 1),
+            6 => (Self::OP_NEGATE, 1),
+            7 => (Self::OP_NOT, 1),
+            8 => (Self::OP_ADD, 1),
+            9 => (Self::OP_SUB, 1),
+            10 => (Self::OP_MULTIPLY, 1),
+            11 => (Self::OP_DIVIDE, 1),
+            12 => (Self::OP_EQUAL, 1),
+            13 => (Self::OP_GREATER, 1),
+            14 => (Self::OP_LESS, 1),
+            15 => (Self::OP_PRINT, 1),
+            16 => (Self::OP_POP, 1),
+            17 => (Self::OP_DEFINE_GLOBAL(u24_at()?), 4),
+            18 => (Self::OP_GET_GLOBAL(u24_at()?), 4),
+            19 => (Self::OP_SET_GLOBAL(u24_at()?), 4),
+            20 => (Self::OP_GET_LOCAL(u8_at()?), 2),
+            21 => (Self::OP_SET_LOCAL(u8_at()?), 2),
+            22 => (Self::OP_JUMP(u16_at()?), 3),
+            23 => (Self::OP_JUMP_IF_FALSE(u16_at()?), 3),
+            24 => (Self::OP_LOOP(u16_at()?), 3),
+            25 => (Self::OP_CALL(u8_at()?), 2),
+            26 => (Self::OP_GET_PROPERTY(u24_at()?), 4),
+            27 => (Self::OP_IMPORT(u24_at()?), 4),
+            28 => (Self::OP_TRY(u16_at()?), 3),
+            29 => (Self::OP_TRY_FINALLY(u16_at()?), 3),
+            30 => (Self::OP_END_TRY, 1),
+            31 => (Self::OP_THROW, 1),
+            32 => (Self::OP_END_FINALLY, 1),
+            33 => (Self::OP_ITER, 1),
+            34 => (Self::OP_FOR_ITER(u16_at()?), 3),
+            35 => (Self::OP_FOR_ENTRY(u16_at()?), 3),
+            36 => (Self::OP_RANGE, 1),
+            37 => (Self::OP_RANGE_INCLUSIVE, 1),
+            38 => (Self::OP_FINALLY_JUMP, 1),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encode_decode_test() {
+        let ops = [Opcode::OP_RETURN, Opcode::OP_CONST(255), Opcode::OP_CONST_LONG(70000),
+            Opcode::OP_GET_LOCAL(3), Opcode::OP_JUMP(65535), Opcode::OP_SET_GLOBAL(1 << 20)];
+        let mut code = Vec::new();
+        for op in ops.iter() {
+            op.encode(&mut code);
+        }
+        assert_eq!(code.len(), 1 + 2 + 4 + 2 + 3 + 4);
+
+        let mut offset = 0;
+        for op in ops.iter() {
+            let (back, size) = Opcode::decode(&code, offset).unwrap();
+            assert_eq!(back.code(), op.code());
+            assert_eq!(back.operand(), op.operand());
+            assert_eq!(size, op.size());
+            offset += size;
+        }
+    }
+
+    #[test]
+    fn decode_invalid_test() {
+        assert!(Opcode::decode(&[200], 0).is_none());
+        assert!(Opcode::decode(&[2, 0], 0).is_none()); // 操作数不完整
+        assert!(Opcode::decode(&[0], 1).is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use std::{cell::RefCell, collections::BTreeMap, fmt, rc::Rc};
+use serde::{de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor}, ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer}};
+use crate::interpreter_error::catalog::*;
+use super::value::*;
+
+// 数字、字符串、布尔值、null、列表与映射可以序列化；函数与含环的列表或映射会报错
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Nested { value: self, seen: &RefCell::new(Vec::new()) }.serialize(serializer)
+    }
+}
+
+// 记录从根到当前位置经过的列表与映射，用于发现环
+struct Nested<'a> {
+    value: &'a Value,
+    seen: &'a RefCell<Vec<*const ()>>,
+}
+
+impl Nested<'_> {
+    fn enter<E: ser::Error>(&self, ptr: *const ()) -> Result<(), E> {
+        if self.seen.borrow().contains(&ptr) {
+            return Err(E::custom(tr("a list or map contains itself and can't be serialized.")));
+        }
+        self.seen.borrow_mut().push(ptr);
+        Ok(())
+    }
+}
+
+impl Serialize for Nested<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            Value::Double(d) => serializer.serialize_f64(*d),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Null => serializer.serialize_unit(),
+            Value::List(l) => {
+                self.enter(Rc::as_ptr(l) as *const ())?;
+                let items = l.borrow();
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for v in items.iter() {
+                    seq.serialize_element(&Nested { value: v, seen: self.seen })?;
+                }
+                self.seen.borrow_mut().pop();
+                seq.end()
+            },
+            Value::Map(m) => {
+                self.enter(Rc::as_ptr(m) as *const ())?;
+                let entries = m.borrow();
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries.iter() {
+                    map.serialize_entry(k, &Nested { value: v, seen: self.seen })?;
+                }
+                self.seen.borrow_mut().pop();
+                map.end()
+            },
+            v @ (Value::Native(_) | Value::Function(_) | Value::Bound(_) | Value::Regex(_) | Value::Range(_) | Value::Iterator(_)) => Err(ser::Error::custom(trf("{} can't be serialized.", &[v]))),
+        }
+    }
+}
+
+// 整数转为数字，序列转为列表，键必须是字符串
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number, string, bool, null, list or map")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Double(v as f64))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Double(v as f64))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Double(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(v) = seq.next_element()? {
+            items.push(v);
+        }
+        Ok(Value::list(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = BTreeMap::new();
+        while let Some((k, v)) = map.next_entry::<String, Value>()? {
+            entries.insert(k, v);
+        }
+        Ok(Value::map(entries))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chunk::convert::IntoValue;
+
+    #[test]
+    fn round_trip_test() {
+        let v: Value = serde_json::from_str(r#"{"name": "tlang", "tags": ["a", 1, 2.5, true, null], "nested": {"x": -3}}"#).unwrap();
+        assert_eq!(v.to_string(), r#"{"name": "tlang", "nested": {"x": -3}, "tags": ["a", 1, 2.5, true, null]}"#);
+        assert_eq!(serde_json::to_string(&v).unwrap(), r#"{"name":"tlang","nested":{"x":-3.0},"tags":["a",1.0,2.5,true,null]}"#);
+    }
+
+    #[test]
+    fn error_test() {
+        let shared = vec![1i64].into_value();
+        // 同一个列表出现两次不是环
+        let v = vec![shared.clone(), shared].into_value();
+        assert!(serde_json::to_string(&v).is_ok());
+
+        if let Value::List(l) = &v {
+            l.borrow_mut().push(v.clone());
+        }
+        assert_eq!(serde_json::to_string(&v).unwrap_err().to_string(), "a list or map contains itself and can't be serialized.");
+
+        let native = Value::Native(Native { name: "f".to_string(), arity: None, fun: |_, _| Ok(Value::Null) });
+        assert_eq!(serde_json::to_string(&vec![native].into_value()).unwrap_err().to_string(), "<native fn f> can't be serialized.");
+    }
+}
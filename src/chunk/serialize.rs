@@ -0,0 +1,292 @@
+use std::{fmt::Display, rc::Rc};
+use super::{chunk::*, op::*, value::*};
+
+// .tlc 文件格式（整数均为小端序）：
+//   magic "TLC\0" | version: u16 | chunk
+// chunk:
+//   常量池: count: u32, 每项 tag: u8 + 数据；函数为 名字长度: u32 + 名字 + arity: u32 + chunk
+//   指令流: 字节数: u32, 随后为 Opcode::encode 编码的字节
+//   行号表: count: u32, 每项 (字节偏移: u32, line: u32)
+pub const MAGIC: [u8; 4] = *b"TLC\0";
+pub const FORMAT_VERSION: u16 = 3;
+
+// 函数嵌套的最大层数，防止构造的文件耗尽调用栈
+const MAX_NESTING: usize = 64;
+
+const TAG_DOUBLE: u8 = 0;
+const TAG_STRING: u8 = 1;
+const TAG_BOOL: u8 = 2;
+const TAG_NULL: u8 = 3;
+const TAG_FUNCTION: u8 = 4;
+
+#[derive(Debug, PartialEq)]
+pub enum DeserializeError {
+    BadMagic,
+    VersionMismatch { found: u16, expected: u16 },
+    Truncated,
+    TrailingBytes,
+    UnknownValueTag(u8),
+    UnknownOpcode(u8),
+    InvalidString,
+    BadConstIndex { offset: usize, val_index: usize },
+    BadLineTable,
+    TooDeep,
+}
+
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a tlang bytecode file"),
+            Self::VersionMismatch { found, expected } => write!(f, "bytecode version {} is not supported, expected {}", found, expected),
+            Self::Truncated => write!(f, "bytecode file is truncated"),
+            Self::TrailingBytes => write!(f, "unexpected bytes after the end of bytecode"),
+            Self::UnknownValueTag(t) => write!(f, "unknown constant tag {}", t),
+            Self::UnknownOpcode(o) => write!(f, "unknown opcode {}", o),
+            Self::InvalidString => write!(f, "constant string is not valid utf-8"),
+            Self::BadConstIndex { offset, val_index } => write!(f, "instruction at {} refers to missing constant {}", offset, val_index),
+            Self::BadLineTable => write!(f, "line table is malformed"),
+            Self::TooDeep => write!(f, "functions are nested too deeply"),
+        }
+    }
+}
+
+impl Chunk {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        self.write_to(&mut out);
+        out
+    }
+
+    fn write_to(&self, out: &mut Vec<u8>) {
+        write_u32(out, self.val_len());
+        for i in 0..self.val_len() {
+            match self.get_val(i) {
+                Value::Double(d) => {
+                    out.push(TAG_DOUBLE);
+                    out.extend_from_slice(&d.to_le_bytes());
+                },
+                Value::String(s) => {
+                    out.push(TAG_STRING);
+                    write_u32(out, s.len());
+                    out.extend_from_slice(s.as_bytes());
+                },
+                Value::Bool(b) => {
+                    out.push(TAG_BOOL);
+                    out.push(*b as u8);
+                },
+                Value::Null => out.push(TAG_NULL),
+                // 本地函数、列表、映射、正则表达式、区间与迭代器只在运行时存在，不会出现在常量池中
+                Value::Native(_) | Value::Bound(_) | Value::List(_) | Value::Map(_) | Value::Regex(_) | Value::Range(_) | Value::Iterator(_) => out.push(TAG_NULL),
+                Value::Function(f) => {
+                    out.push(TAG_FUNCTION);
+                    write_u32(out, f.name.len());
+                    out.extend_from_slice(f.name.as_bytes());
+                    write_u32(out, f.arity);
+                    f.chunk.write_to(out);
+                },
+            }
+        }
+
+        write_u32(out, self.op_len());
+        out.extend_from_slice(self.code());
+
+        write_u32(out, self.lines().len());
+        for &(index, line) in self.lines() {
+            write_u32(out, index);
+            out.extend_from_slice(&line.to_le_bytes());
+        }
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Chunk, DeserializeError> {
+        let mut r = Reader { bytes, pos: 0 };
+
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err(DeserializeError::BadMagic);
+        }
+        let version = u16::from_le_bytes([r.u8()?, r.u8()?]);
+        if version != FORMAT_VERSION {
+            return Err(DeserializeError::VersionMismatch { found: version, expected: FORMAT_VERSION });
+        }
+
+        let chunk = Chunk::read_from(&mut r, 0)?;
+        if r.pos != bytes.len() {
+            return Err(DeserializeError::TrailingBytes);
+        }
+        Ok(chunk)
+    }
+
+    fn read_from(r: &mut Reader, nesting: usize) -> Result<Chunk, DeserializeError> {
+        if nesting > MAX_NESTING {
+            return Err(DeserializeError::TooDeep);
+        }
+
+        let mut chunk = Chunk::new();
+        let val_count = r.u32()?;
+        for _ in 0..val_count {
+            let val = match r.u8()? {
+                TAG_DOUBLE => {
+                    let b = r.take(8)?;
+                    Value::Double(f64::from_le_bytes(b.try_into().unwrap()))
+                },
+                TAG_STRING => Value::String(r.string()?),
+                TAG_BOOL => Value::Bool(r.u8()? != 0),
+                TAG_NULL => Value::Null,
+                TAG_FUNCTION => {
+                    let name = r.string()?;
+                    let arity = r.u32()?;
+                    let body = Chunk::read_from(r, nesting + 1)?;
+                    Value::Function(Rc::new(Function::new(&name, arity, body)))
+                },
+                t => return Err(DeserializeError::UnknownValueTag(t)),
+            };
+            chunk.push_val(val);
+        }
+
+        let code_len = r.u32()?;
+        let code = r.take(code_len)?;
+        let mut ops = Vec::new();
+        let mut offset = 0;
+        while offset < code.len() {
+            let (op, size) = match Opcode::decode(code, offset) {
+                Some(v) => v,
+                None => {
+                    return Err(match Opcode::operand_width(code[offset]) {
+                        Some(_) => DeserializeError::Truncated,
+                        None => DeserializeError::UnknownOpcode(code[offset]),
+                    });
+                },
+            };
+            match op {
+                Opcode::OP_CONST(i) | Opcode::OP_CONST_LONG(i) | Opcode::OP_DEFINE_GLOBAL(i)
+                | Opcode::OP_GET_GLOBAL(i) | Opcode::OP_SET_GLOBAL(i) | Opcode::OP_GET_PROPERTY(i) | Opcode::OP_IMPORT(i) if i >= chunk.val_len() => {
+                    return Err(DeserializeError::BadConstIndex { offset, val_index: i });
+                },
+                _ => {},
+            }
+            ops.push((offset, op));
+            offset += size;
+        }
+
+        // 行号表必须从第 0 条指令开始、严格递增且指向指令的起始位置
+        let line_count = r.u32()?;
+        let mut lines = Vec::new();
+        for _ in 0..line_count {
+            let index = r.u32()?;
+            let line = u32::from_le_bytes(r.take(4)?.try_into().unwrap());
+            lines.push((index, line));
+        }
+        let sorted = lines.windows(2).all(|w| w[0].0 < w[1].0);
+        let starts = match lines.first() {
+            Some(&(first, _)) => first == 0,
+            None => ops.is_empty(),
+        };
+        let aligned = lines.iter().all(|&(index, _)| ops.binary_search_by_key(&index, |&(o, _)| o).is_ok());
+        if !sorted || !starts || !aligned {
+            return Err(DeserializeError::BadLineTable);
+        }
+
+        let mut run = 0;
+        for (offset, op) in ops {
+            if run + 1 < lines.len() && lines[run + 1].0 == offset {
+                run += 1;
+            }
+            chunk.push_op(op, lines[run].1);
+        }
+
+        Ok(chunk)
+    }
+}
+
+fn write_u32(out: &mut Vec<u8>, x: usize) {
+    out.extend_from_slice(&(x as u32).to_le_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DeserializeError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(DeserializeError::Truncated);
+        }
+        let ret = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(ret)
+    }
+
+    fn u8(&mut self) -> Result<u8, DeserializeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, DeserializeError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes(b.try_into().unwrap()) as usize)
+    }
+
+    fn string(&mut self) -> Result<String, DeserializeError> {
+        let len = self.u32()?;
+        match std::str::from_utf8(self.take(len)?) {
+            Ok(s) => Ok(s.to_string()),
+            Err(_) => Err(DeserializeError::InvalidString),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compiler::compiler::compile;
+
+    fn sample() -> Chunk {
+        compile("let a = \"变量\";\nif a == \"变量\" {\n print 1.5; }\nwhile false { print null; }\nfn f(x) { return x; }").unwrap()
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let chunk = sample();
+        let bytes = chunk.serialize();
+        let back = Chunk::deserialize(&bytes).unwrap();
+
+        assert_eq!(back.op_len(), chunk.op_len());
+        assert_eq!(back.val_len(), chunk.val_len());
+        assert_eq!(back.lines(), chunk.lines());
+        assert_eq!(back.serialize(), bytes);
+    }
+
+    #[test]
+    fn truncated_test() {
+        let bytes = sample().serialize();
+        for len in 0..bytes.len() {
+            assert!(Chunk::deserialize(&bytes[..len]).is_err(), "prefix of {} bytes accepted", len);
+        }
+
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(Chunk::deserialize(&longer).err(), Some(DeserializeError::TrailingBytes));
+    }
+
+    #[test]
+    fn version_test() {
+        let mut bytes = sample().serialize();
+        bytes[4] = 9;
+        assert_eq!(Chunk::deserialize(&bytes).err(), Some(DeserializeError::VersionMismatch { found: 9, expected: FORMAT_VERSION }));
+
+        bytes[0] = b'X';
+        assert_eq!(Chunk::deserialize(&bytes).err(), Some(DeserializeError::BadMagic));
+    }
+
+    #[test]
+    fn bad_const_index_test() {
+        let mut chunk = Chunk::new();
+        chunk.push_val(Value::Double(1.0));
+        chunk.push_op(Opcode::OP_CONST(0), 1);
+        chunk.push_op(Opcode::OP_CONST(3), 1);
+        chunk.push_op(Opcode::OP_RETURN, 2);
+
+        assert_eq!(Chunk::deserialize(&chunk.serialize()).err(), Some(DeserializeError::BadConstIndex { offset: 2, val_index: 3 }));
+    }
+}
@@ -1,16 +1,166 @@
-#[derive(Debug)]
+use std::{cell::{Cell, RefCell}, collections::BTreeMap, fmt::{Debug, Display}, rc::Rc};
+use crate::{stdlib::re::Regex, vm::{iterator::Iter, vm::VM, runtime_error::RuntimeError}};
+use super::chunk::Chunk;
+
 pub enum Value {
     Double(f64),
     String(String),
+    Bool(bool),
     Null,
+    Native(Native),
+    Function(Rc<Function>),
+    Bound(Rc<Bound>),
+    // 列表与映射按引用共享，可能形成环
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<BTreeMap<String, Value>>>),
+    // 编译好的正则表达式，见 re.compile
+    Regex(Rc<Regex>),
+    // 数值区间，如 0..10、0..=10
+    Range(Range),
+    // for 循环的迭代状态，只存放在循环的隐藏局部变量中
+    Iterator(Rc<RefCell<Iter>>),
 }
 
-impl Value {
-    pub fn to_string(&self) -> String {
+// 由宿主实现的函数，见 VM::define_native
+pub type NativeFn = fn(&mut VM, &[Value]) -> Result<Value, RuntimeError>;
+
+#[derive(Clone)]
+pub struct Native {
+    pub name: String,
+    pub arity: Option<usize>, // None 表示接受任意个参数
+    pub fun: NativeFn,
+}
+
+impl Debug for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl PartialEq for Native {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.arity == other.arity && std::ptr::fn_addr_eq(self.fun, other.fun)
+    }
+}
+
+// 绑定了接收者的本地方法，如 "abc".upper；调用时接收者作为第一个参数
+#[derive(Debug, PartialEq)]
+pub struct Bound {
+    pub receiver: Value,
+    pub method: Native, // arity 不含接收者
+}
+
+// start..end 不含 end，start..=end 包含 end；第 i 项为 start + i * step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: f64,
+    pub end: f64,
+    pub step: f64,
+    pub inclusive: bool,
+}
+
+impl Range {
+    pub fn new(start: f64, end: f64, inclusive: bool) -> Self {
+        Self { start, end, step: 1.0, inclusive }
+    }
+
+    // 第 i 项，越过 end 时为 None
+    pub fn get(&self, i: usize) -> Option<f64> {
+        let v = self.start + self.step * i as f64;
+        let inside = if self.step > 0.0 { v < self.end }else { v > self.end };
+        (inside || self.inclusive && v == self.end).then_some(v)
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = if self.inclusive { "..=" }else { ".." };
+        if self.step == 1.0 {
+            write!(f, "{}{}{}", self.start, op, self.end)
+        }else {
+            write!(f, "({}{}{}).step({})", self.start, op, self.end, self.step)
+        }
+    }
+}
+
+// 脚本中定义的函数，编译时生成并存放在外层 chunk 的常量池中
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub chunk: Chunk,
+    pub max_stack: Cell<Option<usize>>, // 通过 verifier 检查后记录所需的栈深度
+    pub module: Cell<usize>, // 定义所在的模块，决定全局变量的查找范围；0 为主脚本
+}
+
+impl Function {
+    pub fn new(name: &str, arity: usize, chunk: Chunk) -> Self {
+        Self { name: name.to_string(), arity, chunk, max_stack: Cell::new(None), module: Cell::new(0) }
+    }
+
+    // 连同常量池中嵌套的函数一起归入模块
+    pub fn set_module(&self, module: usize) {
+        self.module.set(module);
+        for i in 0..self.chunk.val_len() {
+            if let Value::Function(f) = self.chunk.get_val(i) {
+                f.set_module(module);
+            }
+        }
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.name)
+    }
+}
+
+// 函数按身份比较
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// 与脚本中的 == 一致：列表、映射与迭代器按身份比较，不会在含有自身的列表上无限递归
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.equal(other)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Value::Double(d) => d.to_string(),
-            Value::Null => String::from("null"),
-            Value::String(s) => s.clone(),
+            Value::Double(d) => write!(f, "{}", d),
+            Value::Null => write!(f, "null"),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Native(n) => write!(f, "{:?}", n),
+            Value::Function(fun) => write!(f, "{:?}", fun),
+            Value::Bound(b) => write!(f, "{:?}", b.method),
+            Value::List(_) | Value::Map(_) => self.write_nested(f, &mut Vec::new()),
+            Value::Regex(r) => write!(f, "<regex {:?}>", r.pattern),
+            Value::Range(r) => write!(f, "{}", r),
+            Value::Iterator(_) => write!(f, "<iterator>"),
+        }
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Double(d) => write!(f, "Double({:?})", d),
+            Value::String(s) => write!(f, "String({:?})", s),
+            Value::Bool(b) => write!(f, "Bool({})", b),
+            Value::Null => write!(f, "Null"),
+            Value::Native(n) => write!(f, "Native({:?})", n),
+            Value::Function(fun) => write!(f, "Function({:?})", fun),
+            Value::Bound(b) => write!(f, "Bound({:?})", b.method),
+            Value::List(_) => write!(f, "List({})", self),
+            Value::Map(_) => write!(f, "Map({})", self),
+            Value::Regex(r) => write!(f, "Regex({:?})", r.pattern),
+            Value::Range(r) => write!(f, "Range({})", r),
+            Value::Iterator(_) => write!(f, "Iterator"),
         }
     }
 }
@@ -24,6 +174,7 @@ impl Value {
                 },
                 Value::String(s) => Value::String(format!("{}{}", s, right.get_string_val().unwrap())),
                 Value::Null => Value::Null,
+                _ => Value::Null,
             }
         }else {
             Value::Null
@@ -38,6 +189,7 @@ impl Value {
                 },
                 Value::Null => Value::Null,
                 Value::String(_) => Value::Null,
+                _ => Value::Null,
             }
         }else {
             Value::Null
@@ -52,6 +204,7 @@ impl Value {
                 },
                 Value::Null => Value::Null,
                 Value::String(_) => Value::Null,
+                _ => Value::Null,
             }
         }else {
             Value::Null
@@ -71,12 +224,85 @@ impl Value {
                 },
                 Value::Null => Value::Null,
                 Value::String(_) => Value::Null,
+                _ => Value::Null,
             }
         }else {
             Value::Null
         }
     }
 
+    pub fn equal(&self, right: &Value) -> bool {
+        match (self, right) {
+            (Value::Double(a), Value::Double(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            (Value::Native(a), Value::Native(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Bound(a), Value::Bound(b)) => a.method == b.method && a.receiver.equal(&b.receiver),
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Regex(a), Value::Regex(b)) => a.pattern == b.pattern,
+            (Value::Range(a), Value::Range(b)) => a == b,
+            (Value::Iterator(a), Value::Iterator(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn map(entries: BTreeMap<String, Value>) -> Value {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    // 列表与映射中的字符串带引号；已在外层出现的列表或映射显示为 [...] 或 {...}
+    fn write_nested(&self, f: &mut std::fmt::Formatter<'_>, seen: &mut Vec<*const ()>) -> std::fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::List(l) => {
+                let ptr = Rc::as_ptr(l) as *const ();
+                if seen.contains(&ptr) {
+                    return write!(f, "[...]");
+                }
+                seen.push(ptr);
+                write!(f, "[")?;
+                for (i, v) in l.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    v.write_nested(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "]")
+            },
+            Value::Map(m) => {
+                let ptr = Rc::as_ptr(m) as *const ();
+                if seen.contains(&ptr) {
+                    return write!(f, "{{...}}");
+                }
+                seen.push(ptr);
+                write!(f, "{{")?;
+                for (i, (k, v)) in m.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: ", k)?;
+                    v.write_nested(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "}}")
+            },
+            v => write!(f, "{}", v),
+        }
+    }
+
+    // null 与 false 为假，其余皆为真
+    pub fn is_falsey(&self) -> bool {
+        matches!(self, Value::Null | Value::Bool(false))
+    }
+
     fn get_double_val(&self) -> Option<f64> {
         match self {
             Value::Double(v) => Some(*v),
@@ -91,6 +317,23 @@ impl Value {
         }
     }
 
+    // 类型名，用于错误信息
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Double(_) => "number",
+            Value::String(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::Null => "null",
+            Value::Native(_) | Value::Bound(_) => "native fn",
+            Value::Function(_) => "fn",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Regex(_) => "regex",
+            Value::Range(_) => "range",
+            Value::Iterator(_) => "iterator",
+        }
+    }
+
     pub fn type_eq(&self, other: &Value) -> bool {
         self.to_u32() == other.to_u32()
     }
@@ -100,6 +343,15 @@ impl Value {
             Value::Double(_) => 0,
             Value::Null => 1,
             Value::String(_) => 2,
+            Value::Bool(_) => 3,
+            Value::Native(_) => 4,
+            Value::Function(_) => 5,
+            Value::Bound(_) => 8,
+            Value::List(_) => 6,
+            Value::Map(_) => 7,
+            Value::Regex(_) => 9,
+            Value::Range(_) => 10,
+            Value::Iterator(_) => 11,
         }
     }
 }
@@ -109,7 +361,16 @@ impl Clone for Value {
         match self {
             Value::Double(d) => Value::Double(*d),
             Value::String(s) => Value::String(s.clone()),
+            Value::Bool(b) => Value::Bool(*b),
             Value::Null => Value::Null,
+            Value::Native(n) => Value::Native(n.clone()),
+            Value::Function(f) => Value::Function(f.clone()),
+            Value::Bound(b) => Value::Bound(b.clone()),
+            Value::List(l) => Value::List(l.clone()),
+            Value::Map(m) => Value::Map(m.clone()),
+            Value::Regex(r) => Value::Regex(r.clone()),
+            Value::Range(r) => Value::Range(*r),
+            Value::Iterator(i) => Value::Iterator(i.clone()),
         }
     }
 }
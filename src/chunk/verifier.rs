@@ -0,0 +1,409 @@
+use std::fmt::Display;
+use super::{chunk::*, op::*, value::*};
+
+// 执行前对 Chunk 做静态检查：常量下标、跳转目标、每条指令处的栈深度与 try 处理器以及以 OP_RETURN 结束。
+// 通过检查后栈的最大深度已知，VM 可以省去逐条指令的栈上限检查。
+#[derive(Debug, PartialEq)]
+pub enum VerifyError {
+    BadInstruction { offset: usize },
+    BadConstIndex { offset: usize, val_index: usize },
+    BadGlobalName { offset: usize, val_index: usize },
+    BadLocalSlot { offset: usize, slot: usize },
+    BadJumpTarget { offset: usize },
+    StackUnderflow { offset: usize },
+    StackMismatch { offset: usize, expected: usize, found: usize },
+    HandlerMismatch { offset: usize },
+    BadHandler { offset: usize },
+    BadFinally { offset: usize },
+    MissingReturn { offset: usize },
+    StackOverflow { needed: usize, max: usize },
+    InFunction { name: String, error: Box<VerifyError> },
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadInstruction { offset } => write!(f, "{:04}: invalid instruction", offset),
+            Self::BadConstIndex { offset, val_index } => write!(f, "{:04}: constant {} does not exist", offset, val_index),
+            Self::BadGlobalName { offset, val_index } => write!(f, "{:04}: constant {} is not a global name", offset, val_index),
+            Self::BadLocalSlot { offset, slot } => write!(f, "{:04}: local slot {} is out of the stack", offset, slot),
+            Self::BadJumpTarget { offset } => write!(f, "{:04}: jump target is not an instruction", offset),
+            Self::StackUnderflow { offset } => write!(f, "{:04}: stack underflow", offset),
+            Self::StackMismatch { offset, expected, found } => write!(f, "{:04}: stack depth {} differs from {} on another path", offset, found, expected),
+            Self::HandlerMismatch { offset } => write!(f, "{:04}: try handlers differ from another path", offset),
+            Self::BadHandler { offset } => write!(f, "{:04}: no matching try handler", offset),
+            Self::BadFinally { offset } => write!(f, "{:04}: completion of finally is not set up by a try", offset),
+            Self::MissingReturn { offset } => write!(f, "{:04}: execution falls off the end without OP_RETURN", offset),
+            Self::StackOverflow { needed, max } => write!(f, "needs a stack of {} but the maximum is {}", needed, max),
+            Self::InFunction { name, error } => write!(f, "in fn {}: {}", name, error),
+        }
+    }
+}
+
+// 返回执行所需的最大栈深度，常量池中的函数一并检查
+pub fn verify(chunk: &Chunk) -> Result<usize, VerifyError> {
+    verify_chunk(chunk, 0)
+}
+
+// 函数开始执行时栈上已有被调用者与参数；结果记录在 max_stack 中
+pub fn verify_function(fun: &Function) -> Result<usize, VerifyError> {
+    if let Some(max) = fun.max_stack.get() {
+        return Ok(max);
+    }
+    let max = verify_chunk(&fun.chunk, fun.arity + 1)
+        .map_err(|e| VerifyError::InFunction { name: fun.name.clone(), error: Box::new(e) })?;
+    fun.max_stack.set(Some(max));
+    Ok(max)
+}
+
+fn verify_chunk(chunk: &Chunk, base: usize) -> Result<usize, VerifyError> {
+    let len = chunk.op_len();
+    for i in 0..chunk.val_len() {
+        if let Value::Function(f) = chunk.get_val(i) {
+            verify_function(f)?;
+        }
+    }
+
+    // 先按顺序解码全部指令，记录指令起始位置
+    let mut starts = vec![false; len + 1];
+    let mut offset = 0;
+    while offset < len {
+        let (op, size) = match chunk.get_op(offset) {
+            Some(v) => v,
+            None => return Err(VerifyError::BadInstruction { offset }),
+        };
+        starts[offset] = true;
+
+        match op {
+            Opcode::OP_CONST(i) | Opcode::OP_CONST_LONG(i) if i >= chunk.val_len() => {
+                return Err(VerifyError::BadConstIndex { offset, val_index: i });
+            },
+            Opcode::OP_DEFINE_GLOBAL(i) | Opcode::OP_GET_GLOBAL(i) | Opcode::OP_SET_GLOBAL(i) | Opcode::OP_GET_PROPERTY(i) | Opcode::OP_IMPORT(i) => {
+                if i >= chunk.val_len() {
+                    return Err(VerifyError::BadConstIndex { offset, val_index: i });
+                }
+                if !matches!(chunk.get_val(i), Value::String(_)) {
+                    return Err(VerifyError::BadGlobalName { offset, val_index: i });
+                }
+            },
+            _ => {},
+        }
+        offset += size;
+    }
+
+    // 跳转与处理器的目标；finally 的入口另外记录
+    let mut targets = vec![false; len + 1];
+    let mut finally_targets = vec![false; len + 1];
+    let mut offset = 0;
+    while let Some((op, size)) = chunk.get_op(offset) {
+        let next = offset + size;
+        let target = match op {
+            Opcode::OP_JUMP(jump) | Opcode::OP_JUMP_IF_FALSE(jump) | Opcode::OP_FOR_ITER(jump) | Opcode::OP_FOR_ENTRY(jump) => Some(next + jump),
+            Opcode::OP_TRY(jump) | Opcode::OP_TRY_FINALLY(jump) if jump > 0 => Some(next + jump),
+            Opcode::OP_LOOP(jump) => next.checked_sub(jump),
+            _ => None,
+        };
+        if let Some(t) = target.filter(|&t| t <= len) {
+            targets[t] = true;
+            if let Opcode::OP_TRY_FINALLY(_) = op {
+                finally_targets[t] = true;
+            }
+        }
+        offset = next;
+    }
+
+    // 沿控制流传播栈深度、处理器与 finally 的完成方式，同一位置在不同路径上必须一致
+    let mut state_at: Vec<Option<State>> = vec![None; len];
+    let mut work = vec![(0, State { depth: base, handlers: Vec::new(), completions: Vec::new() })];
+    let mut max = base;
+    // (finally 入口, 跳出后继续执行的位置) 与 (finally 入口, OP_END_FINALLY 之后的状态)
+    let mut resumes = Vec::new();
+    let mut ends = Vec::new();
+    while let Some((offset, mut state)) = work.pop() {
+        if offset >= len {
+            return Err(VerifyError::MissingReturn { offset });
+        }
+        if !starts[offset] {
+            return Err(VerifyError::BadJumpTarget { offset });
+        }
+        match &state_at[offset] {
+            Some(expected) if expected.depth != state.depth => {
+                return Err(VerifyError::StackMismatch { offset, expected: expected.depth, found: state.depth });
+            },
+            Some(expected) if *expected != state => return Err(VerifyError::HandlerMismatch { offset }),
+            Some(_) => continue,
+            None => state_at[offset] = Some(state.clone()),
+        }
+
+        let (op, size) = chunk.get_op(offset).unwrap();
+        let next = offset + size;
+        let depth = state.depth;
+        let (pops, pushes) = stack_effect(op);
+        if depth < pops {
+            return Err(VerifyError::StackUnderflow { offset });
+        }
+        // 处理器记录了 try 开始时的栈深度，try 中不能弹出更早的值
+        let floor = state.handlers.last().map_or(0, |h| h.depth);
+        if depth - pops < floor && !matches!(op, Opcode::OP_RETURN | Opcode::OP_THROW) {
+            return Err(VerifyError::StackUnderflow { offset });
+        }
+        let after = depth - pops + pushes;
+        max = max.max(depth).max(after);
+
+        // 完成方式及其值只能由 OP_END_FINALLY 使用，被弹出后不再跟踪
+        let mut finished = None;
+        if let Opcode::OP_END_FINALLY = op {
+            match state.completions.pop() {
+                Some((slot, target)) if slot + 2 == depth => finished = Some(target),
+                _ => return Err(VerifyError::BadFinally { offset }),
+            }
+        }
+        if let Opcode::OP_SET_LOCAL(slot) = op {
+            if state.completions.iter().any(|&(s, _)| slot == s || slot == s + 1) {
+                return Err(VerifyError::BadFinally { offset });
+            }
+        }
+        state.completions.retain(|&(slot, _)| slot + 2 <= depth - pops);
+        state.depth = after;
+
+        match op {
+            Opcode::OP_RETURN | Opcode::OP_THROW => {},
+            Opcode::OP_GET_LOCAL(slot) | Opcode::OP_SET_LOCAL(slot) if slot >= depth => {
+                return Err(VerifyError::BadLocalSlot { offset, slot });
+            },
+            Opcode::OP_JUMP(jump) => work.push((next + jump, state)),
+            Opcode::OP_JUMP_IF_FALSE(jump) => {
+                work.push((next + jump, state.clone()));
+                work.push((next, state));
+            },
+            // 抛出异常时栈回到 try 开始时的深度，再压入异常（finally 还要压入完成方式）。
+            // 偏移为 0 表示没有对应的子句，处理器只占位
+            Opcode::OP_TRY(jump) => {
+                let target = (jump > 0).then_some(next + jump);
+                if let Some(target) = target {
+                    work.push((target, State { depth: after + 1, ..state.clone() }));
+                }
+                state.handlers.push(HandlerState { finally: false, depth: after, target });
+                work.push((next, state));
+            },
+            Opcode::OP_TRY_FINALLY(jump) => {
+                let target = (jump > 0).then_some(next + jump);
+                if let Some(target) = target {
+                    work.push((target, state.enter_finally(target)));
+                }
+                state.handlers.push(HandlerState { finally: true, depth: after, target });
+                work.push((next, state));
+            },
+            Opcode::OP_END_TRY => {
+                if state.handlers.pop().is_none() {
+                    return Err(VerifyError::BadHandler { offset });
+                }
+                work.push((next, state));
+            },
+            // 跳出 try 时栈已回到 try 开始时的深度，finally 结束后回到下一条指令
+            Opcode::OP_FINALLY_JUMP => {
+                let handler = match state.handlers.pop() {
+                    Some(h) if h.finally => h,
+                    _ => return Err(VerifyError::BadHandler { offset }),
+                };
+                if depth != handler.depth {
+                    return Err(VerifyError::StackMismatch { offset, expected: handler.depth, found: depth });
+                }
+                if let Some(target) = handler.target {
+                    work.push((target, state.enter_finally(target)));
+                    resumes.push((target, next));
+                }
+                work.push((next, state));
+            },
+            Opcode::OP_END_FINALLY => {
+                if let Some(target) = finished {
+                    ends.push((target, state.clone()));
+                }
+                work.push((next, state));
+            },
+            // 正常执行到 finally 时由紧邻的两条 OP_NULL 压入完成方式及其值
+            Opcode::OP_NULL if finally_targets[next] && !targets[offset] && offset > 0
+                && starts[offset - 1] && matches!(chunk.get_op(offset - 1), Some((Opcode::OP_NULL, 1))) => {
+                state.completions.push((depth - 1, next));
+                work.push((next, state));
+            },
+            // 取得下一项时压入值（或键与值），迭代结束时跳出循环
+            Opcode::OP_FOR_ITER(jump) => {
+                work.push((next + jump, state.clone()));
+                work.push((next, State { depth: after + 1, ..state }));
+            },
+            Opcode::OP_FOR_ENTRY(jump) => {
+                work.push((next + jump, state.clone()));
+                work.push((next, State { depth: after + 2, ..state }));
+            },
+            Opcode::OP_LOOP(jump) => match next.checked_sub(jump) {
+                Some(target) => work.push((target, state)),
+                None => return Err(VerifyError::BadJumpTarget { offset }),
+            },
+            _ => work.push((next, state)),
+        }
+    }
+
+    // finally 因 break 或 continue 而执行时，结束后回到跳出的位置，那里的状态须与正常结束时一致
+    for (target, end) in &ends {
+        for &(_, resume) in resumes.iter().filter(|(t, _)| t == target) {
+            match &state_at[resume] {
+                Some(s) if s.depth != end.depth => {
+                    return Err(VerifyError::StackMismatch { offset: resume, expected: s.depth, found: end.depth });
+                },
+                Some(s) if s != end => return Err(VerifyError::HandlerMismatch { offset: resume }),
+                _ => {},
+            }
+        }
+    }
+
+    Ok(max)
+}
+
+// try 处理器：try 开始时的栈深度与处理代码的位置
+#[derive(Clone, PartialEq)]
+struct HandlerState {
+    finally: bool,
+    depth: usize,
+    target: Option<usize>,
+}
+
+#[derive(Clone, PartialEq)]
+struct State {
+    depth: usize,
+    handlers: Vec<HandlerState>,
+    completions: Vec<(usize, usize)>, // finally 中完成方式所在的栈位置及 finally 入口
+}
+
+impl State {
+    // 进入 finally 时栈回到 try 开始时的深度，再压入完成方式及其值
+    fn enter_finally(&self, target: usize) -> State {
+        let mut state = self.clone();
+        let depth = state.depth;
+        state.completions.push((depth, target));
+        state.depth = depth + 2;
+        state
+    }
+}
+
+// (弹出数, 压入数)
+fn stack_effect(op: Opcode) -> (usize, usize) {
+    match op {
+        Opcode::OP_JUMP(_) | Opcode::OP_LOOP(_) | Opcode::OP_TRY(_) | Opcode::OP_TRY_FINALLY(_) | Opcode::OP_END_TRY
+        | Opcode::OP_FINALLY_JUMP => (0, 0),
+        // 迭代器留在栈上，作为循环的隐藏局部变量
+        Opcode::OP_FOR_ITER(_) | Opcode::OP_FOR_ENTRY(_) => (1, 1),
+        Opcode::OP_CONST(_) | Opcode::OP_CONST_LONG(_) | Opcode::OP_NULL
+        | Opcode::OP_TRUE | Opcode::OP_FALSE | Opcode::OP_GET_GLOBAL(_) | Opcode::OP_GET_LOCAL(_) | Opcode::OP_IMPORT(_) => (0, 1),
+        Opcode::OP_NEGATE | Opcode::OP_NOT | Opcode::OP_SET_GLOBAL(_) | Opcode::OP_GET_PROPERTY(_)
+        | Opcode::OP_SET_LOCAL(_) | Opcode::OP_JUMP_IF_FALSE(_) | Opcode::OP_ITER => (1, 1),
+        Opcode::OP_ADD | Opcode::OP_SUB | Opcode::OP_MULTIPLY | Opcode::OP_DIVIDE
+        | Opcode::OP_EQUAL | Opcode::OP_GREATER | Opcode::OP_LESS | Opcode::OP_RANGE | Opcode::OP_RANGE_INCLUSIVE => (2, 1),
+        // 返回值出栈，交给调用者
+        Opcode::OP_RETURN | Opcode::OP_THROW | Opcode::OP_PRINT | Opcode::OP_POP | Opcode::OP_DEFINE_GLOBAL(_) => (1, 0),
+        // finally 结束时弹出完成方式及其值
+        Opcode::OP_END_FINALLY => (2, 0),
+        // 被调用者与参数出栈，返回值入栈
+        Opcode::OP_CALL(argc) => (argc + 1, 1),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compiler::compiler::compile;
+
+    fn chunk_of(ops: &[Opcode]) -> Chunk {
+        let mut chunk = Chunk::new();
+        chunk.push_val(Value::Double(1.0));
+        chunk.push_val(Value::String("a".to_string()));
+        for op in ops {
+            chunk.push_op(*op, 1);
+        }
+        chunk
+    }
+
+    #[test]
+    fn compiled_test() {
+        let chunk = compile("let a = 1; { let b = 2; let c = a + b * (b - 1); print c; } while a < 3 { a = a + 1; }").unwrap();
+        assert_eq!(verify(&chunk), Ok(5));
+
+        let chunk = compile("if true && !false { print 1; } else if null { print 2; }").unwrap();
+        assert!(verify(&chunk).is_ok());
+
+        // 进入 catch 与 finally 时栈上多出异常及完成方式
+        let chunk = compile("{ let a = 1; try { let b = 2; } catch (e) { print e; } finally { print a; } }").unwrap();
+        assert_eq!(verify(&chunk), Ok(4));
+
+        // 迭代器、键与值，以及跳出 finally 时压入的完成方式
+        let chunk = compile("for k, v in 0..3 { try { let x = k; continue; } finally { break; } }").unwrap();
+        assert_eq!(verify(&chunk), Ok(5));
+
+        let chunk = compile("'a: while true { try { for i in 0..3 { try { break 'a; } finally { print i; } } } finally { print 1; } }").unwrap();
+        assert!(verify(&chunk).is_ok());
+    }
+
+    #[test]
+    fn const_test() {
+        assert_eq!(verify(&chunk_of(&[Opcode::OP_CONST(7), Opcode::OP_RETURN])), Err(VerifyError::BadConstIndex { offset: 0, val_index: 7 }));
+        assert_eq!(verify(&chunk_of(&[Opcode::OP_GET_GLOBAL(0), Opcode::OP_RETURN])), Err(VerifyError::BadGlobalName { offset: 0, val_index: 0 }));
+    }
+
+    #[test]
+    fn stack_test() {
+        assert_eq!(verify(&chunk_of(&[Opcode::OP_CONST(0), Opcode::OP_ADD, Opcode::OP_RETURN])), Err(VerifyError::StackUnderflow { offset: 2 }));
+        assert_eq!(verify(&chunk_of(&[Opcode::OP_GET_LOCAL(0), Opcode::OP_RETURN])), Err(VerifyError::BadLocalSlot { offset: 0, slot: 0 }));
+
+        // 两条路径到达同一位置时栈深度不同
+        let chunk = chunk_of(&[Opcode::OP_TRUE, Opcode::OP_JUMP_IF_FALSE(2), Opcode::OP_CONST(0), Opcode::OP_RETURN]);
+        assert_eq!(verify(&chunk), Err(VerifyError::StackMismatch { offset: 6, expected: 2, found: 1 }));
+    }
+
+    #[test]
+    fn finally_test() {
+        // 完成方式不是由 try 压入的，OP_END_FINALLY 不能使用
+        let chunk = chunk_of(&[Opcode::OP_CONST(0), Opcode::OP_CONST(0), Opcode::OP_END_FINALLY, Opcode::OP_NULL, Opcode::OP_NULL, Opcode::OP_ADD, Opcode::OP_RETURN]);
+        assert_eq!(verify(&chunk), Err(VerifyError::BadFinally { offset: 4 }));
+
+        // 跳出 try 时栈须已回到 try 开始时的深度
+        let chunk = chunk_of(&[Opcode::OP_TRY_FINALLY(0), Opcode::OP_NULL, Opcode::OP_FINALLY_JUMP, Opcode::OP_RETURN]);
+        assert_eq!(verify(&chunk), Err(VerifyError::StackMismatch { offset: 4, expected: 0, found: 1 }));
+
+        // 正常进入 finally 之前只能是两条 OP_NULL
+        let chunk = chunk_of(&[Opcode::OP_TRY_FINALLY(3), Opcode::OP_END_TRY, Opcode::OP_TRUE, Opcode::OP_NULL, Opcode::OP_END_FINALLY, Opcode::OP_NULL, Opcode::OP_RETURN]);
+        assert_eq!(verify(&chunk), Err(VerifyError::BadFinally { offset: 6 }));
+        let chunk = chunk_of(&[Opcode::OP_TRY_FINALLY(3), Opcode::OP_END_TRY, Opcode::OP_NULL, Opcode::OP_NULL, Opcode::OP_END_FINALLY, Opcode::OP_NULL, Opcode::OP_RETURN]);
+        assert_eq!(verify(&chunk), Ok(2));
+
+        assert_eq!(verify(&chunk_of(&[Opcode::OP_END_TRY, Opcode::OP_NULL, Opcode::OP_RETURN])), Err(VerifyError::BadHandler { offset: 0 }));
+        let chunk = chunk_of(&[Opcode::OP_TRY(0), Opcode::OP_FINALLY_JUMP, Opcode::OP_NULL, Opcode::OP_RETURN]);
+        assert_eq!(verify(&chunk), Err(VerifyError::BadHandler { offset: 3 }));
+    }
+
+    #[test]
+    fn function_test() {
+        let chunk = compile("fn f(a, b) { let c = a + b; return c * 2; }\nprint f(1, 2);").unwrap();
+        assert!(verify(&chunk).is_ok());
+        let fun = match chunk.get_val(1) {
+            Value::Function(f) => f.clone(),
+            v => panic!("{:?}", v),
+        };
+        // 被调用者、两个参数、c 以及 c * 2 的两个操作数
+        assert_eq!(fun.max_stack.get(), Some(6));
+
+        let mut bad = Chunk::new();
+        bad.push_op(Opcode::OP_GET_LOCAL(3), 1);
+        bad.push_op(Opcode::OP_RETURN, 1);
+        let mut chunk = chunk_of(&[Opcode::OP_NULL, Opcode::OP_RETURN]);
+        chunk.push_val(Value::Function(std::rc::Rc::new(Function::new("g", 2, bad))));
+        assert_eq!(verify(&chunk).unwrap_err().to_string(), "in fn g: 0000: local slot 3 is out of the stack");
+    }
+
+    #[test]
+    fn jump_test() {
+        assert_eq!(verify(&chunk_of(&[Opcode::OP_JUMP(1), Opcode::OP_CONST(0), Opcode::OP_RETURN])), Err(VerifyError::BadJumpTarget { offset: 4 }));
+        assert_eq!(verify(&chunk_of(&[Opcode::OP_LOOP(9), Opcode::OP_RETURN])), Err(VerifyError::BadJumpTarget { offset: 0 }));
+        assert_eq!(verify(&chunk_of(&[Opcode::OP_NULL, Opcode::OP_POP])), Err(VerifyError::MissingReturn { offset: 2 }));
+        assert_eq!(verify(&Chunk::new()), Err(VerifyError::MissingReturn { offset: 0 }));
+    }
+}
@@ -0,0 +1,465 @@
+use std::{env, fs, io::{self, Read, Write}};
+use tlang::{chunk::{chunk::Chunk, serialize::MAGIC}, compiler::{compiler::Compiler, compile_error::CompileError}, debug_tools::disassemble::Listing,
+    formatter::formatter, interpreter_error::catalog::*, lexical_analysis::{scanner::Scanner, token_type::TokenType},
+    vm::{interpret_result::InterpretResult, vm::VM}};
+use crate::repl;
+
+// 退出码沿用 sysexits
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+const EX_SOFTWARE: i32 = 70;
+const EX_CANTCREAT: i32 = 73;
+const EX_IOERR: i32 = 74;
+
+const USAGE: &str = "\
+Usage: tlang [options] [command] [file | -] [script args...]
+
+Commands:
+    run <file>                  compile and run a script or a .tlc file (default)
+    repl                        start the interactive shell (default without a file)
+    tokens <file>               print the tokens of a script
+    disasm <file>               print the bytecode of a script
+    compile <file> -o <out>     write the bytecode to a .tlc file
+    check <file>                report errors without running
+    fmt <file>                  print the formatted script
+
+Options:
+    -e <code>                   use <code> instead of a file
+    -                           read the script from stdin
+    --trace                     print the stack and each instruction while running
+    --stack-max <n>             limit the VM stack to <n> values
+    --format <text|json|source> output format of disasm
+    -o <file>                   output file of compile
+    -w, --write                 let fmt rewrite the file in place
+    -I <dir>                    also look for imported modules in <dir>
+    -h, --help                  print this help";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Run,
+    Repl,
+    Tokens,
+    Disasm,
+    Compile,
+    Check,
+    Fmt,
+    Help,
+}
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "run" => Some(Command::Run),
+            "repl" => Some(Command::Repl),
+            "tokens" => Some(Command::Tokens),
+            "disasm" => Some(Command::Disasm),
+            "compile" => Some(Command::Compile),
+            "check" => Some(Command::Check),
+            "fmt" => Some(Command::Fmt),
+            "help" => Some(Command::Help),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    File(String),
+    Stdin,
+    Inline(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub input: Option<Input>,
+    pub trace: bool,
+    pub stack_max: Option<usize>,
+    pub output: Option<String>,
+    pub format: String,
+    pub write: bool,
+    pub search_paths: Vec<String>, // -I 给出的模块查找目录
+    pub script_args: Vec<String>, // run 时脚本之后的参数，原样交给脚本
+}
+
+// 命令可以省略：给出文件或 -e 时为 run，否则为 repl
+pub fn parse(args: &[String]) -> Result<Options, String> {
+    let mut command = None;
+    let mut opts = Options {
+        command: Command::Repl,
+        input: None,
+        trace: false,
+        stack_max: None,
+        output: None,
+        format: "text".to_string(),
+        write: false,
+        search_paths: Vec::new(),
+        script_args: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if opts.input.is_some() && command.is_none_or(|c| c == Command::Run) {
+            opts.script_args.push(arg.clone());
+            continue;
+        }
+
+        let mut value = |name: &str| match args.next() {
+            Some(v) => Ok(v.clone()),
+            None => Err(trf("option ' {} ' needs a value!", &[&name])),
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => command = Some(Command::Help),
+            "--trace" => opts.trace = true,
+            "-w" | "--write" => opts.write = true,
+            "--stack-max" => {
+                let v = value(arg)?;
+                match v.parse::<usize>() {
+                    Ok(n) => opts.stack_max = Some(n),
+                    Err(_) => return Err(trf("invalid number ' {} '!", &[&v])),
+                }
+            },
+            "--format" => opts.format = value(arg)?,
+            "-o" => opts.output = Some(value(arg)?),
+            "-I" => opts.search_paths.push(value(arg)?),
+            "-e" => set_input(&mut opts, Input::Inline(value(arg)?))?,
+            "-" => set_input(&mut opts, Input::Stdin)?,
+            // 旧的写法，等同于 disasm
+            a if a.starts_with("--dump-bytecode") => {
+                command = Some(Command::Disasm);
+                match &a["--dump-bytecode".len()..] {
+                    "" => {},
+                    f if f.starts_with('=') => opts.format = f[1..].to_string(),
+                    _ => return Err(trf("unknown option ' {} '!", &[&a])),
+                }
+            },
+            a if a.starts_with('-') => return Err(trf("unknown option ' {} '!", &[&a])),
+            a => match Command::from_name(a) {
+                Some(c) if command.is_none() && opts.input.is_none() => command = Some(c),
+                _ => set_input(&mut opts, Input::File(a.to_string()))?,
+            },
+        }
+    }
+
+    opts.command = match command {
+        Some(c) => c,
+        None if opts.input.is_some() => Command::Run,
+        None => Command::Repl,
+    };
+    let needs_input = !matches!(opts.command, Command::Repl | Command::Help);
+    if needs_input && opts.input.is_none() {
+        return Err(tr("no input file!").to_string());
+    }
+    if opts.command == Command::Compile && opts.output.is_none() {
+        return Err(tr("compile needs an output file (-o)!").to_string());
+    }
+    if opts.command == Command::Disasm && !matches!(opts.format.as_str(), "text" | "json" | "source") {
+        return Err(trf("unknown bytecode format ' {} '!", &[&opts.format]));
+    }
+    Ok(opts)
+}
+
+fn set_input(opts: &mut Options, input: Input) -> Result<(), String> {
+    if opts.input.is_some() {
+        let name = match input {
+            Input::File(f) => f,
+            Input::Stdin => "-".to_string(),
+            Input::Inline(_) => "-e".to_string(),
+        };
+        return Err(trf("unexpected argument ' {} '!", &[&name]));
+    }
+    opts.input = Some(input);
+    Ok(())
+}
+
+// 执行命令，返回进程退出码
+pub fn main(args: &[String]) -> i32 {
+    let opts = match parse(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            error(&e);
+            eprintln!("{}", tr(USAGE));
+            return EX_USAGE;
+        },
+    };
+
+    let mut vm = VM::from(Chunk::new());
+    vm.set_output(Box::new(io::stdout())).set_input(Box::new(io::BufReader::new(io::stdin())));
+    vm.set_debug(opts.trace);
+    if let Some(n) = opts.stack_max {
+        vm.set_stack_max(n);
+    }
+    vm.set_args(opts.script_args.clone());
+    // -I 之后查找 TLANG_PATH 中的目录
+    for dir in &opts.search_paths {
+        vm.add_search_path(dir);
+    }
+    if let Some(paths) = env::var_os("TLANG_PATH") {
+        for dir in env::split_paths(&paths) {
+            vm.add_search_path(dir);
+        }
+    }
+    if let Some(Input::File(path)) = &opts.input {
+        vm.set_script_path(path);
+    }
+
+    if opts.command == Command::Help {
+        println!("{}", tr(USAGE));
+        return 0;
+    }
+    if opts.command == Command::Repl {
+        return repl::tshlle(vm);
+    }
+
+    let bytes = match read_input(opts.input.as_ref().unwrap()) {
+        Ok(b) => b,
+        Err(name) => {
+            error(&trf("can't read file ' {} '!", &[&name]));
+            return EX_NOINPUT;
+        },
+    };
+    if opts.command == Command::Run && bytes.starts_with(&MAGIC) {
+        return run_bytecode(&mut vm, &bytes);
+    }
+    let code = match String::from_utf8(bytes) {
+        Ok(code) => code,
+        Err(_) => {
+            error(tr("script is not valid utf-8!"));
+            return EX_DATAERR;
+        },
+    };
+
+    match opts.command {
+        Command::Run => {
+            let result = vm.interpret(&code);
+            report_warnings(vm.warnings());
+            report_result(result)
+        },
+        Command::Tokens => tokens(&code),
+        Command::Disasm => {
+            let chunk = match compile_reported(&code) {
+                Some(chunk) => chunk,
+                None => return EX_DATAERR,
+            };
+            let listing = Listing::from(&chunk, &input_name(opts.input.as_ref().unwrap()));
+            let mut out = io::stdout();
+            let result = match opts.format.as_str() {
+                "json" => listing.write_json(&mut out),
+                "source" => listing.write_side_by_side(&code, &mut out),
+                _ => listing.write_text(&mut out),
+            };
+            match result {
+                Ok(_) => 0,
+                Err(_) => {
+                    error(tr("output error!"));
+                    EX_IOERR
+                },
+            }
+        },
+        Command::Compile => {
+            let chunk = match compile_reported(&code) {
+                Some(chunk) => chunk,
+                None => return EX_DATAERR,
+            };
+            let out = opts.output.unwrap();
+            match fs::write(&out, chunk.serialize()) {
+                Ok(_) => 0,
+                Err(_) => {
+                    error(&trf("can't write file ' {} '!", &[&out]));
+                    EX_CANTCREAT
+                },
+            }
+        },
+        Command::Check => match compile_reported(&code) {
+            Some(_) => 0,
+            None => EX_DATAERR,
+        },
+        Command::Fmt => {
+            let formatted = match formatter::format(&code) {
+                Ok(f) => f,
+                Err(errors) => {
+                    report_errors(&errors);
+                    return EX_DATAERR;
+                },
+            };
+            match (&opts.input, opts.write) {
+                (Some(Input::File(path)), true) => match fs::write(path, formatted) {
+                    Ok(_) => 0,
+                    Err(_) => {
+                        error(&trf("can't write file ' {} '!", &[&path]));
+                        EX_CANTCREAT
+                    },
+                },
+                _ => {
+                    print!("{}", formatted);
+                    match io::stdout().flush() {
+                        Ok(_) => 0,
+                        Err(_) => EX_IOERR,
+                    }
+                },
+            }
+        },
+        Command::Repl | Command::Help => unreachable!(),
+    }
+}
+
+pub fn error(massege: &str) {
+    eprintln!("{}", trf("interpreter error!\nhere:\n{}", &[&massege]));
+}
+
+pub fn warning(massege: &str) {
+    eprintln!("{}", trf("interpreter warning!\nhere:\n{}", &[&massege]));
+}
+
+// 输出源码的全部词素，存在词法错误时只报告错误
+fn tokens(code: &str) -> i32 {
+    let mut scanner = Scanner::new(code);
+    let tokens: Vec<_> = scanner.by_ref().collect();
+    report_warnings(scanner.warnings());
+
+    let errors: Vec<_> = tokens.iter().filter(|t| t.token_type() == TokenType::ERROR).collect();
+    if !errors.is_empty() {
+        for t in &errors {
+            error(&trf("{}\n in line={}", &[&t.lexeme_string(), &t.line()]));
+        }
+        error(&trf("lexical analysis errror!total {} error.", &[&errors.len()]));
+        return EX_DATAERR;
+    }
+
+    for t in &tokens {
+        println!("{:?}", t);
+    }
+    0
+}
+
+fn read_input(input: &Input) -> Result<Vec<u8>, String> {
+    match input {
+        Input::File(path) => fs::read(path).map_err(|_| path.clone()),
+        Input::Stdin => {
+            let mut buf = Vec::new();
+            match io::stdin().read_to_end(&mut buf) {
+                Ok(_) => Ok(buf),
+                Err(_) => Err("-".to_string()),
+            }
+        },
+        Input::Inline(code) => Ok(code.as_bytes().to_vec()),
+    }
+}
+
+fn input_name(input: &Input) -> String {
+    match input {
+        Input::File(path) => path.clone(),
+        Input::Stdin => "<stdin>".to_string(),
+        Input::Inline(_) => "<-e>".to_string(),
+    }
+}
+
+fn run_bytecode(vm: &mut VM, bytes: &[u8]) -> i32 {
+    let chunk = match Chunk::deserialize(bytes) {
+        Ok(chunk) => chunk,
+        Err(e) => {
+            error(&trf("invalid bytecode file: {}", &[&e]));
+            return EX_DATAERR;
+        },
+    };
+    if let Err(e) = vm.set_verified_chunk(chunk) {
+        error(&trf("invalid bytecode file: {}", &[&e]));
+        return EX_DATAERR;
+    }
+    report_result(vm.run())
+}
+
+// 编译并报告警告与错误，失败时返回 None
+fn compile_reported(code: &str) -> Option<Chunk> {
+    let mut scanner = Scanner::new(code);
+    let result = Compiler::new(&mut scanner).compile();
+    report_warnings(scanner.warnings());
+    match result {
+        Ok(chunk) => Some(chunk),
+        Err(errors) => {
+            report_errors(&errors);
+            None
+        },
+    }
+}
+
+fn report_warnings(warnings: &[CompileError]) {
+    for w in warnings {
+        warning(&w.to_string());
+    }
+}
+
+fn report_errors(errors: &[CompileError]) {
+    for e in errors {
+        error(&e.to_string());
+    }
+}
+
+fn report_result(result: InterpretResult) -> i32 {
+    match result {
+        InterpretResult::Ok => 0,
+        InterpretResult::COMPLE_ERROR(errors) => {
+            report_errors(&errors);
+            EX_DATAERR
+        },
+        InterpretResult::RUNTIME_ERROR(e) => {
+            error(&e.to_string());
+            EX_SOFTWARE
+        },
+        InterpretResult::EXIT(code) => code,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_test() {
+        let o = parse(&args("a.tl")).unwrap();
+        assert_eq!((o.command, o.input), (Command::Run, Some(Input::File("a.tl".to_string()))));
+        assert_eq!(parse(&[]).unwrap().command, Command::Repl);
+
+        let o = parse(&args("--trace --stack-max 16 run -")).unwrap();
+        assert_eq!((o.command, o.input, o.trace, o.stack_max), (Command::Run, Some(Input::Stdin), true, Some(16)));
+
+        let o = parse(&["-e".to_string(), "print 1;".to_string()]).unwrap();
+        assert_eq!((o.command, o.input), (Command::Run, Some(Input::Inline("print 1;".to_string()))));
+
+        let o = parse(&args("compile a.tl -o a.tlc")).unwrap();
+        assert_eq!((o.command, o.output), (Command::Compile, Some("a.tlc".to_string())));
+
+        let o = parse(&args("-I lib -I vendor a.tl")).unwrap();
+        assert_eq!(o.search_paths, vec!["lib".to_string(), "vendor".to_string()]);
+
+        let o = parse(&args("--dump-bytecode=json a.tl")).unwrap();
+        assert_eq!((o.command, o.format.as_str()), (Command::Disasm, "json"));
+
+        // 脚本之后的参数都交给脚本，包括形如选项的参数
+        let o = parse(&args("--trace a.tl x --stack-max")).unwrap();
+        assert_eq!((o.trace, o.stack_max, o.script_args), (true, None, vec!["x".to_string(), "--stack-max".to_string()]));
+        let o = parse(&["-e".to_string(), "print arg(0);".to_string(), "y".to_string()]).unwrap();
+        assert_eq!(o.script_args, vec!["y".to_string()]);
+
+        // 文件名与命令同名时，第二个位置参数视为文件
+        let o = parse(&args("tokens run")).unwrap();
+        assert_eq!((o.command, o.input), (Command::Tokens, Some(Input::File("run".to_string()))));
+    }
+
+    #[test]
+    fn parse_error_test() {
+        assert!(parse(&args("--stack-max")).is_err());
+        assert!(parse(&args("--stack-max x a.tl")).is_err());
+        assert!(parse(&args("--nope a.tl")).is_err());
+        assert!(parse(&args("disasm")).is_err());
+        assert!(parse(&args("compile a.tl")).is_err());
+        assert!(parse(&args("disasm --format xml a.tl")).is_err());
+        assert!(parse(&args("tokens a.tl b.tl")).is_err());
+    }
+}
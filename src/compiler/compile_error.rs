@@ -0,0 +1,19 @@
+use std::fmt::Display;
+use crate::interpreter_error::catalog::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileError {
+    pub line: u32,
+    pub at: String, // 出错位置的词素，到达文件末尾时为 "end"
+    pub massege: String,
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.at.is_empty() {
+            write!(f, "{}", trf("[line {}] error: {}", &[&self.line, &self.massege]))
+        }else {
+            write!(f, "{}", trf("[line {}] error at {}: {}", &[&self.line, &self.at, &self.massege]))
+        }
+    }
+}
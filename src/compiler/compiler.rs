@@ -0,0 +1,1230 @@
+use std::{mem, path::Path, rc::Rc};
+use crate::{chunk::{chunk::*, op::*, value::*}, lexical_analysis::{scanner::*, token::*, token_type::*}};
+use crate::interpreter_error::catalog::*;
+use super::compile_error::*;
+
+// 表达式与代码块的最大嵌套层数，避免递归下降耗尽栈
+const MAX_NESTING: usize = 256;
+
+#[derive(PartialEq, PartialOrd, Clone, Copy)]
+enum Precedence {
+    None,
+    Assignment, // =
+    Or, // ||
+    And, // &&
+    Equality, // == !=
+    Comparison, // < > <= >=
+    Range, // .. ..=
+    Term, // + -
+    Factor, // * /
+    Unary, // ! -
+    Call, // () .
+    Primary,
+}
+
+impl Precedence {
+    fn next(self) -> Self {
+        match self {
+            Self::None => Self::Assignment,
+            Self::Assignment => Self::Or,
+            Self::Or => Self::And,
+            Self::And => Self::Equality,
+            Self::Equality => Self::Comparison,
+            Self::Comparison => Self::Range,
+            Self::Range => Self::Term,
+            Self::Term => Self::Factor,
+            Self::Factor => Self::Unary,
+            Self::Unary => Self::Call,
+            Self::Call => Self::Primary,
+            Self::Primary => Self::Primary,
+        }
+    }
+
+    fn of(t: TokenType) -> Self {
+        match t {
+            TokenType::OR_OR => Self::Or,
+            TokenType::AND_AND => Self::And,
+            TokenType::EQUAL_EQUAL | TokenType::BANG_EQUAL => Self::Equality,
+            TokenType::LESS | TokenType::LESS_EQUAL
+            | TokenType::GREATER | TokenType::GREATER_EQUAL => Self::Comparison,
+            TokenType::DOT_DOT | TokenType::DOT_DOT_EQUAL => Self::Range,
+            TokenType::PLUS | TokenType::MINUS => Self::Term,
+            TokenType::STAR | TokenType::SLASH => Self::Factor,
+            TokenType::LEFT_PAREN | TokenType::DOT => Self::Call,
+            _ => Self::None,
+        }
+    }
+}
+
+struct Local {
+    name: String,
+    depth: Option<usize>, // None 表示已声明但尚未初始化
+}
+
+// 正在编译的循环，break 与 continue 据此跳转
+struct Loop {
+    label: Option<String>, // 'outer: while ... 中的标签，含开头的 '
+    start: usize, // continue 跳回的位置
+    locals: usize, // 循环体外的局部变量个数，跳转前弹出其余的
+    tries: usize, // 循环外的 try 语句个数，跳转前结束其余的
+    breaks: Vec<usize>, // 待回填到循环出口的跳转
+}
+
+// 正在编译的 try 语句，跳出时要移除它的处理器
+struct Try {
+    locals: usize, // 安装处理器时的局部变量个数
+    in_body: bool, // 位于 try 块中时 catch 的处理器尚未移除
+}
+
+// 编译函数体时保存的外层状态
+struct Enclosing {
+    chunk: Chunk,
+    locals: Vec<Local>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<Try>,
+}
+
+pub struct Compiler<'a, I: Iterator<Item = Token<'a>>> {
+    tokens: I,
+    current: Token<'a>,
+    previous: Token<'a>,
+
+    chunk: Chunk,
+    locals: Vec<Local>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<Try>,
+    enclosing: Vec<Enclosing>, // 为空时位于顶层代码
+    has_result: bool, // 顶层代码以不带 ';' 的表达式结束，其值作为执行结果
+    exports: Vec<String>, // export 声明的名字，作为模块载入时对外可见
+    nesting: usize, // 当前所在的表达式与代码块的层数
+
+    errors: Vec<CompileError>,
+    panic_mode: bool, // 处于恐慌模式时不再报告错误，直到同步点
+}
+
+// 编译器按需从扫描器拉取词素，不会一次性生成全部词素
+pub fn compile(code: &str) -> Result<Chunk, Vec<CompileError>> {
+    Compiler::new(Scanner::new(code)).compile()
+}
+
+impl<'a, I: Iterator<Item = Token<'a>>> Compiler<'a, I> {
+    pub fn new(tokens: I) -> Self {
+        let mut compiler = Self {
+            tokens,
+            current: Token::new("", TokenType::EOF, None, 1),
+            previous: Token::new("", TokenType::EOF, None, 1),
+            chunk: Chunk::new(),
+            locals: Vec::new(),
+            scope_depth: 0,
+            loops: Vec::new(),
+            tries: Vec::new(),
+            enclosing: Vec::new(),
+            has_result: false,
+            exports: Vec::new(),
+            nesting: 0,
+            errors: Vec::new(),
+            panic_mode: false,
+        };
+        compiler.advance();
+        compiler
+    }
+
+    pub fn compile(self) -> Result<Chunk, Vec<CompileError>> {
+        self.compile_module().map(|(chunk, _)| chunk)
+    }
+
+    // 作为模块编译时同时返回导出的名字
+    pub fn compile_module(mut self) -> Result<(Chunk, Vec<String>), Vec<CompileError>> {
+        while !self.match_token(TokenType::EOF) {
+            self.declaration();
+        }
+        if !self.has_result {
+            self.emit(Opcode::OP_NULL);
+        }
+        self.emit(Opcode::OP_RETURN);
+
+        if self.errors.is_empty() {
+            Ok((self.chunk, self.exports))
+        }else {
+            Err(self.errors)
+        }
+    }
+
+    fn declaration(&mut self) {
+        if self.match_token(TokenType::LET) {
+            self.let_declaration();
+        }else if self.match_token(TokenType::FN) {
+            self.fn_declaration();
+        }else if self.match_token(TokenType::IMPORT) {
+            self.import_declaration();
+        }else if self.match_token(TokenType::EXPORT) {
+            self.export_declaration();
+        }else {
+            self.statement();
+        }
+
+        if self.panic_mode {
+            self.synchronize();
+        }
+    }
+
+    fn let_declaration(&mut self) {
+        let global = self.parse_variable(tr("Expect variable name."));
+
+        if self.match_token(TokenType::EQUAL) {
+            self.expression();
+        }else {
+            self.emit(Opcode::OP_NULL);
+        }
+        self.consume(TokenType::SEMICOLON, tr("Expect ';' after variable declaration."));
+
+        self.define_variable(global);
+    }
+
+    fn fn_declaration(&mut self) {
+        let global = self.parse_variable(tr("Expect function name."));
+        // 函数体内可以递归引用自身
+        if self.scope_depth > 0 {
+            if let Some(local) = self.locals.last_mut() {
+                local.depth = Some(self.scope_depth);
+            }
+        }
+        let name = self.previous.lexeme_string();
+        self.function(&name);
+        self.define_variable(global);
+    }
+
+    // import "lib/util.tl"; import util; import util as u;
+    // 模块以路径或名字查找，绑定到 as 之后的名字，省略时为文件名去掉扩展名
+    fn import_declaration(&mut self) {
+        let spec = if self.match_token(TokenType::STRING) {
+            self.previous.lexeme_string()
+        }else {
+            self.consume(TokenType::IDENTIFIER, tr("Expect module name or path after 'import'."));
+            self.previous.lexeme_string()
+        };
+        let name = if self.match_token(TokenType::AS) {
+            self.consume(TokenType::IDENTIFIER, tr("Expect name after 'as'."));
+            self.previous.lexeme_string()
+        }else {
+            Path::new(&spec).file_stem().map_or(spec.clone(), |s| s.to_string_lossy().into_owned())
+        };
+        self.consume(TokenType::SEMICOLON, tr("Expect ';' after import."));
+
+        let index = self.make_constant(Value::String(spec));
+        self.emit(Opcode::OP_IMPORT(index));
+        self.declare_name(name.chars().collect());
+        let global = if self.scope_depth > 0 { 0 }else { self.make_constant(Value::String(name)) };
+        self.define_variable(global);
+    }
+
+    // export let x = 1; export fn f() { } export a, b;
+    fn export_declaration(&mut self) {
+        if !self.enclosing.is_empty() || self.scope_depth > 0 {
+            self.error(tr("Can only export top-level declarations."));
+        }
+
+        if self.match_token(TokenType::LET) {
+            self.export_current();
+            self.let_declaration();
+            return;
+        }
+        if self.match_token(TokenType::FN) {
+            self.export_current();
+            self.fn_declaration();
+            return;
+        }
+
+        loop {
+            self.consume(TokenType::IDENTIFIER, tr("Expect name after 'export'."));
+            self.exports.push(self.previous.lexeme_string());
+            if !self.match_token(TokenType::COMMA) {
+                break;
+            }
+        }
+        self.consume(TokenType::SEMICOLON, tr("Expect ';' after exported names."));
+    }
+
+    // 记录即将声明的名字，名字缺失的错误由声明本身报告
+    fn export_current(&mut self) {
+        if self.check(TokenType::IDENTIFIER) {
+            self.exports.push(self.current.lexeme_string());
+        }
+    }
+
+    // 函数体编译到新的 chunk 中，第 0 个槽位留给被调用的函数本身
+    fn function(&mut self, name: &str) {
+        self.enclosing.push(Enclosing {
+            chunk: mem::take(&mut self.chunk),
+            locals: mem::replace(&mut self.locals, vec![Local { name: String::new(), depth: Some(0) }]),
+            scope_depth: mem::replace(&mut self.scope_depth, 1),
+            loops: mem::take(&mut self.loops),
+            tries: mem::take(&mut self.tries),
+        });
+
+        self.consume(TokenType::LEFT_PAREN, tr("Expect '(' after function name."));
+        let mut arity = 0;
+        if !self.check(TokenType::RIGHT_PAREN) {
+            loop {
+                if arity == MAX_ARGS {
+                    self.error_at_current(tr("Can't have more than 255 parameters."));
+                }
+                arity += 1;
+                let slot = self.parse_variable(tr("Expect parameter name."));
+                self.define_variable(slot);
+                if !self.match_token(TokenType::COMMA) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RIGHT_PAREN, tr("Expect ')' after parameters."));
+        self.consume(TokenType::LEFT_BRACE, tr("Expect '{' before function body."));
+        self.block();
+        self.emit(Opcode::OP_NULL);
+        self.emit(Opcode::OP_RETURN);
+
+        let outer = self.enclosing.pop().unwrap();
+        let chunk = mem::replace(&mut self.chunk, outer.chunk);
+        self.locals = outer.locals;
+        self.scope_depth = outer.scope_depth;
+        self.loops = outer.loops;
+        self.tries = outer.tries;
+
+        let fun = Function::new(name, arity.min(MAX_ARGS), chunk);
+        self.emit_constant(Value::Function(Rc::new(fun)));
+    }
+
+    fn statement(&mut self) {
+        match self.current.token_type() {
+            TokenType::PRINT => {
+                self.advance();
+                self.print_statement();
+            },
+            TokenType::IF => {
+                self.advance();
+                self.if_statement();
+            },
+            TokenType::WHILE => {
+                self.advance();
+                self.while_statement(None);
+            },
+            TokenType::FOR => {
+                self.advance();
+                self.for_statement(None);
+            },
+            TokenType::LABEL => {
+                self.advance();
+                self.labeled_statement();
+            },
+            TokenType::BREAK | TokenType::CONTINUE => {
+                self.advance();
+                self.jump_statement();
+            },
+            TokenType::LEFT_BRACE => {
+                self.advance();
+                self.begin_scope();
+                self.block();
+                self.end_scope();
+            },
+            TokenType::RETURN => {
+                self.advance();
+                self.return_statement();
+            },
+            TokenType::TRY => {
+                self.advance();
+                self.try_statement();
+            },
+            TokenType::THROW => {
+                self.advance();
+                self.throw_statement();
+            },
+            TokenType::CLASS => {
+                self.advance();
+                self.unsupported();
+            },
+            _ => self.expression_statement(),
+        }
+    }
+
+    fn print_statement(&mut self) {
+        self.expression();
+        self.consume(TokenType::SEMICOLON, tr("Expect ';' after value."));
+        self.emit(Opcode::OP_PRINT);
+    }
+
+    fn return_statement(&mut self) {
+        if self.enclosing.is_empty() {
+            self.error(tr("Can't return from top-level code."));
+        }
+
+        if self.match_token(TokenType::SEMICOLON) {
+            self.emit(Opcode::OP_NULL);
+        }else {
+            self.expression();
+            self.consume(TokenType::SEMICOLON, tr("Expect ';' after return value."));
+        }
+        self.emit(Opcode::OP_RETURN);
+    }
+
+    // try { } catch (e) { } finally { }，catch 与 finally 至少有一个。
+    // 两个处理器总在 try 之前安装，编译完后面的子句才知道是否存在，不存在的偏移保持为 0，
+    // 这样 break 与 continue 跳出时总能按相同的方式移除它们
+    fn try_statement(&mut self) {
+        self.consume(TokenType::LEFT_BRACE, tr("Expect '{' after 'try'."));
+        let finally_try = self.emit_jump(Opcode::OP_TRY_FINALLY(0));
+        let catch_try = self.emit_jump(Opcode::OP_TRY(0));
+        self.tries.push(Try { locals: self.locals.len(), in_body: true });
+        self.begin_scope();
+        self.block();
+        self.end_scope();
+        self.emit(Opcode::OP_END_TRY);
+        if let Some(t) = self.tries.last_mut() {
+            t.in_body = false;
+        }
+
+        let has_catch = self.match_token(TokenType::CATCH);
+        if has_catch {
+            let end_jump = self.emit_jump(Opcode::OP_JUMP(0));
+            self.patch_jump(catch_try);
+
+            // 异常已在栈上，作为 catch 的局部变量
+            self.begin_scope();
+            if self.match_token(TokenType::LEFT_PAREN) {
+                self.consume(TokenType::IDENTIFIER, tr("Expect exception variable name."));
+                self.declare_variable();
+                self.define_variable(0);
+                self.consume(TokenType::RIGHT_PAREN, tr("Expect ')' after exception variable."));
+            }else {
+                self.add_hidden_local();
+            }
+            self.consume(TokenType::LEFT_BRACE, tr("Expect '{' after 'catch'."));
+            self.begin_scope();
+            self.block();
+            self.end_scope();
+            self.end_scope();
+            self.patch_jump(end_jump);
+        }
+        self.tries.pop();
+        self.emit(Opcode::OP_END_TRY);
+
+        if self.match_token(TokenType::FINALLY) {
+            // 正常执行到 finally 时完成方式及其值均为 null
+            self.emit(Opcode::OP_NULL);
+            self.emit(Opcode::OP_NULL);
+            self.patch_jump(finally_try);
+
+            self.begin_scope();
+            self.add_hidden_local();
+            self.add_hidden_local();
+            self.consume(TokenType::LEFT_BRACE, tr("Expect '{' after 'finally'."));
+            self.begin_scope();
+            self.block();
+            self.end_scope();
+            // OP_END_FINALLY 弹出完成方式及其值，之后继续执行、重新抛出或返回
+            self.emit(Opcode::OP_END_FINALLY);
+            self.locals.truncate(self.locals.len().saturating_sub(2));
+            self.scope_depth -= 1;
+        }else if !has_catch {
+            self.error_at_current(tr("Expect 'catch' or 'finally' after try block."));
+        }
+    }
+
+    fn throw_statement(&mut self) {
+        self.expression();
+        self.consume(TokenType::SEMICOLON, tr("Expect ';' after thrown value."));
+        self.emit(Opcode::OP_THROW);
+    }
+
+    fn expression_statement(&mut self) {
+        self.expression();
+        if self.enclosing.is_empty() && self.scope_depth == 0 && self.check(TokenType::EOF) && !self.panic_mode {
+            self.has_result = true;
+            return;
+        }
+        self.consume(TokenType::SEMICOLON, tr("Expect ';' after expression."));
+        self.emit(Opcode::OP_POP);
+    }
+
+    fn if_statement(&mut self) {
+        self.expression();
+        self.consume(TokenType::LEFT_BRACE, tr("Expect '{' after condition."));
+
+        let then_jump = self.emit_jump(Opcode::OP_JUMP_IF_FALSE(0));
+        self.emit(Opcode::OP_POP);
+        self.begin_scope();
+        self.block();
+        self.end_scope();
+
+        let else_jump = self.emit_jump(Opcode::OP_JUMP(0));
+        self.patch_jump(then_jump);
+        self.emit(Opcode::OP_POP);
+
+        if self.match_token(TokenType::ELSE) {
+            if self.match_token(TokenType::IF) {
+                self.if_statement();
+            }else {
+                self.consume(TokenType::LEFT_BRACE, tr("Expect '{' after 'else'."));
+                self.begin_scope();
+                self.block();
+                self.end_scope();
+            }
+        }
+        self.patch_jump(else_jump);
+    }
+
+    // 'outer: while ... { }，标签只能用于循环，break 'outer; 与 continue 'outer; 据此找到循环
+    fn labeled_statement(&mut self) {
+        let label = self.previous.lexeme().to_string();
+        if self.loops.iter().any(|l| l.label.as_ref() == Some(&label)) {
+            let massege = trf("Label {} is already in use by an enclosing loop.", &[&self.previous.lexeme_string()]);
+            self.error(&massege);
+        }
+        self.consume(TokenType::COLON, tr("Expect ':' after loop label."));
+
+        if self.match_token(TokenType::WHILE) {
+            self.while_statement(Some(label));
+        }else if self.match_token(TokenType::FOR) {
+            self.for_statement(Some(label));
+        }else {
+            self.error_at_current(tr("Expect 'while' or 'for' after loop label."));
+        }
+    }
+
+    fn while_statement(&mut self, label: Option<String>) {
+        let loop_start = self.chunk.op_len();
+        self.expression();
+        self.consume(TokenType::LEFT_BRACE, tr("Expect '{' after condition."));
+
+        let exit_jump = self.emit_jump(Opcode::OP_JUMP_IF_FALSE(0));
+        self.emit(Opcode::OP_POP);
+        self.begin_loop(label, loop_start);
+        self.begin_scope();
+        self.block();
+        self.end_scope();
+        self.emit_loop(loop_start);
+
+        self.patch_jump(exit_jump);
+        self.emit(Opcode::OP_POP);
+        self.end_loop();
+    }
+
+    // for x in list { }，for k, v in map { }，for i in 0..10 { }
+    // 迭代器作为隐藏的局部变量留在栈上，每次迭代压入的值（或键与值）即循环变量
+    fn for_statement(&mut self, label: Option<String>) {
+        self.begin_scope();
+        self.consume(TokenType::IDENTIFIER, tr("Expect loop variable name."));
+        let mut names = vec![self.previous.lexeme().to_string()];
+        if self.match_token(TokenType::COMMA) {
+            self.consume(TokenType::IDENTIFIER, tr("Expect loop variable name."));
+            names.push(self.previous.lexeme().to_string());
+        }
+        self.consume(TokenType::IN, tr("Expect 'in' after loop variable."));
+        self.expression();
+        self.consume(TokenType::LEFT_BRACE, tr("Expect '{' after for clause."));
+
+        self.emit(Opcode::OP_ITER);
+        self.add_hidden_local();
+        let loop_start = self.chunk.op_len();
+        let exit_jump = self.emit_jump(if names.len() == 2 { Opcode::OP_FOR_ENTRY(0) }else { Opcode::OP_FOR_ITER(0) });
+        self.begin_loop(label, loop_start);
+
+        self.begin_scope();
+        for name in names {
+            self.declare_name(name);
+            self.define_variable(0);
+        }
+        self.begin_scope();
+        self.block();
+        self.end_scope();
+        self.end_scope();
+        self.emit_loop(loop_start);
+
+        self.patch_jump(exit_jump);
+        self.end_loop();
+        self.end_scope();
+    }
+
+    fn begin_loop(&mut self, label: Option<String>, start: usize) {
+        self.loops.push(Loop { label, start, locals: self.locals.len(), tries: self.tries.len(), breaks: Vec::new() });
+    }
+
+    // break 跳到循环出口
+    fn end_loop(&mut self) {
+        if let Some(l) = self.loops.pop() {
+            for jump in l.breaks {
+                self.patch_jump(jump);
+            }
+        }
+    }
+
+    // break; continue; break 'outer; 先弹出循环体中的局部变量、移除循环体中 try 的处理器
+    // （有 finally 时先执行它），再跳到循环出口或下一次迭代。不带标签时作用于最内层的循环
+    fn jump_statement(&mut self) {
+        let keyword = self.previous.clone();
+        if self.loops.is_empty() {
+            let massege = trf("Can't use '{}' outside of a loop.", &[&keyword.lexeme_string()]);
+            self.error(&massege);
+            return;
+        }
+        let target = if self.match_token(TokenType::LABEL) {
+            let label = self.previous.lexeme();
+            match self.loops.iter().rposition(|l| l.label.as_deref() == Some(label)) {
+                Some(i) => i,
+                None => {
+                    let massege = trf("Unknown loop label {}.", &[&self.previous.lexeme_string()]);
+                    self.error(&massege);
+                    return;
+                },
+            }
+        }else {
+            self.loops.len() - 1
+        };
+        let (start, locals, tries) = (self.loops[target].start, self.loops[target].locals, self.loops[target].tries);
+        let massege = trf("Expect ';' after '{}'.", &[&keyword.lexeme_string()]);
+        self.consume(TokenType::SEMICOLON, &massege);
+
+        let mut level = self.locals.len();
+        for i in (tries..self.tries.len()).rev() {
+            let (t_locals, in_body) = (self.tries[i].locals, self.tries[i].in_body);
+            self.emit_pops(level - t_locals);
+            level = t_locals;
+            if in_body {
+                self.emit(Opcode::OP_END_TRY);
+            }
+            self.emit(Opcode::OP_FINALLY_JUMP);
+        }
+        self.emit_pops(level - locals);
+
+        if keyword.token_type() == TokenType::BREAK {
+            let jump = self.emit_jump(Opcode::OP_JUMP(0));
+            self.loops[target].breaks.push(jump);
+        }else {
+            self.emit_loop(start);
+        }
+    }
+
+    fn block(&mut self) {
+        if self.nesting == MAX_NESTING {
+            self.error(tr("Block nests too deeply."));
+            return;
+        }
+        self.nesting += 1;
+        while !self.check(TokenType::RIGHT_BRACE) && !self.check(TokenType::EOF) {
+            self.declaration();
+        }
+        self.consume(TokenType::RIGHT_BRACE, tr("Expect '}' after block."));
+        self.nesting -= 1;
+    }
+
+    // 尚未实现的语句：报告一次错误后整体跳过，避免其内部产生连锁错误
+    fn unsupported(&mut self) {
+        let massege = trf("'{}' is not supported yet.", &[&self.previous.lexeme_string()]);
+        self.error(&massege);
+
+        let mut paren = 0;
+        let mut brace = 0;
+        loop {
+            match self.current.token_type() {
+                TokenType::EOF => break,
+                TokenType::SEMICOLON if paren == 0 && brace == 0 => {
+                    self.advance();
+                    break;
+                },
+                TokenType::LEFT_PAREN => paren += 1,
+                TokenType::RIGHT_PAREN => paren -= 1,
+                TokenType::LEFT_BRACE => brace += 1,
+                TokenType::RIGHT_BRACE => {
+                    if brace == 0 {
+                        break;
+                    }
+                    brace -= 1;
+                    if brace == 0 {
+                        self.advance();
+                        break;
+                    }
+                },
+                _ => {},
+            }
+            self.advance();
+        }
+
+        self.panic_mode = false;
+    }
+
+    fn expression(&mut self) {
+        self.parse_precedence(Precedence::Assignment);
+    }
+
+    fn parse_precedence(&mut self, precedence: Precedence) {
+        if self.nesting == MAX_NESTING {
+            self.error_at_current(tr("Expression nests too deeply."));
+            return;
+        }
+        self.nesting += 1;
+        self.advance();
+        let can_assign = precedence <= Precedence::Assignment;
+        if !self.prefix(self.previous.token_type(), can_assign) {
+            self.error(tr("Expect expression."));
+            self.nesting -= 1;
+            return;
+        }
+
+        while precedence <= Precedence::of(self.current.token_type()) {
+            self.advance();
+            self.infix(self.previous.token_type());
+        }
+
+        if can_assign && self.match_token(TokenType::EQUAL) {
+            self.error(tr("Invalid assignment target."));
+        }
+        self.nesting -= 1;
+    }
+
+    fn prefix(&mut self, t: TokenType, can_assign: bool) -> bool {
+        match t {
+            TokenType::LEFT_PAREN => self.grouping(),
+            TokenType::MINUS | TokenType::BANG => self.unary(),
+            TokenType::DOUBLE | TokenType::STRING => self.literal_val(),
+            TokenType::TRUE => self.emit(Opcode::OP_TRUE),
+            TokenType::FALSE => self.emit(Opcode::OP_FALSE),
+            TokenType::NULL => self.emit(Opcode::OP_NULL),
+            TokenType::IDENTIFIER => self.variable(can_assign),
+            _ => return false,
+        }
+        true
+    }
+
+    fn infix(&mut self, t: TokenType) {
+        match t {
+            TokenType::AND_AND => self.and(),
+            TokenType::OR_OR => self.or(),
+            TokenType::LEFT_PAREN => self.call(),
+            TokenType::DOT => self.dot(),
+            _ => self.binary(),
+        }
+    }
+
+    fn grouping(&mut self) {
+        self.expression();
+        self.consume(TokenType::RIGHT_PAREN, tr("Expect ')' after expression."));
+    }
+
+    fn unary(&mut self) {
+        let operator = self.previous.token_type();
+        self.parse_precedence(Precedence::Unary);
+
+        match operator {
+            TokenType::MINUS => self.emit(Opcode::OP_NEGATE),
+            TokenType::BANG => self.emit(Opcode::OP_NOT),
+            _ => {},
+        }
+    }
+
+    fn binary(&mut self) {
+        let operator = self.previous.token_type();
+        self.parse_precedence(Precedence::of(operator).next());
+
+        match operator {
+            TokenType::PLUS => self.emit(Opcode::OP_ADD),
+            TokenType::MINUS => self.emit(Opcode::OP_SUB),
+            TokenType::STAR => self.emit(Opcode::OP_MULTIPLY),
+            TokenType::SLASH => self.emit(Opcode::OP_DIVIDE),
+            TokenType::EQUAL_EQUAL => self.emit(Opcode::OP_EQUAL),
+            TokenType::BANG_EQUAL => {
+                self.emit(Opcode::OP_EQUAL);
+                self.emit(Opcode::OP_NOT);
+            },
+            TokenType::GREATER => self.emit(Opcode::OP_GREATER),
+            TokenType::GREATER_EQUAL => {
+                self.emit(Opcode::OP_LESS);
+                self.emit(Opcode::OP_NOT);
+            },
+            TokenType::LESS => self.emit(Opcode::OP_LESS),
+            TokenType::LESS_EQUAL => {
+                self.emit(Opcode::OP_GREATER);
+                self.emit(Opcode::OP_NOT);
+            },
+            TokenType::DOT_DOT => self.emit(Opcode::OP_RANGE),
+            TokenType::DOT_DOT_EQUAL => self.emit(Opcode::OP_RANGE_INCLUSIVE),
+            _ => {},
+        }
+    }
+
+    fn and(&mut self) {
+        let end_jump = self.emit_jump(Opcode::OP_JUMP_IF_FALSE(0));
+        self.emit(Opcode::OP_POP);
+        self.parse_precedence(Precedence::And);
+        self.patch_jump(end_jump);
+    }
+
+    fn or(&mut self) {
+        let else_jump = self.emit_jump(Opcode::OP_JUMP_IF_FALSE(0));
+        let end_jump = self.emit_jump(Opcode::OP_JUMP(0));
+        self.patch_jump(else_jump);
+        self.emit(Opcode::OP_POP);
+        self.parse_precedence(Precedence::Or);
+        self.patch_jump(end_jump);
+    }
+
+    fn call(&mut self) {
+        let mut argc = 0;
+        if !self.check(TokenType::RIGHT_PAREN) {
+            loop {
+                self.expression();
+                if argc == MAX_ARGS {
+                    self.error(tr("Can't have more than 255 arguments."));
+                }
+                argc += 1;
+                if !self.match_token(TokenType::COMMA) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RIGHT_PAREN, tr("Expect ')' after arguments."));
+        self.emit(Opcode::OP_CALL(argc.min(MAX_ARGS)));
+    }
+
+    // 属性访问，如 math.PI；调用 math.sqrt(2) 时先取属性再调用
+    fn dot(&mut self) {
+        self.consume(TokenType::IDENTIFIER, tr("Expect property name after '.'."));
+        let index = self.identifier_constant();
+        self.emit(Opcode::OP_GET_PROPERTY(index));
+    }
+
+    fn literal_val(&mut self) {
+        let val = match self.previous.val() {
+            Some(v) => v.clone(),
+            None => Value::Null,
+        };
+        self.emit_constant(val);
+    }
+
+    fn emit_constant(&mut self, val: Value) {
+        let index = self.make_constant(val);
+        if index <= MAX_SHORT_CONST {
+            self.emit(Opcode::OP_CONST(index));
+        }else {
+            self.emit(Opcode::OP_CONST_LONG(index));
+        }
+    }
+
+    fn variable(&mut self, can_assign: bool) {
+        let (get_op, set_op) = match self.resolve_local() {
+            Some(slot) => (Opcode::OP_GET_LOCAL(slot), Opcode::OP_SET_LOCAL(slot)),
+            None => {
+                let index = self.identifier_constant();
+                (Opcode::OP_GET_GLOBAL(index), Opcode::OP_SET_GLOBAL(index))
+            },
+        };
+
+        if can_assign && self.match_token(TokenType::EQUAL) {
+            self.expression();
+            self.emit(set_op);
+        }else {
+            self.emit(get_op);
+        }
+    }
+
+    fn parse_variable(&mut self, massege: &str) -> usize {
+        self.consume(TokenType::IDENTIFIER, massege);
+
+        self.declare_variable();
+        if self.scope_depth > 0 {
+            0
+        }else {
+            self.identifier_constant()
+        }
+    }
+
+    fn declare_variable(&mut self) {
+        self.declare_name(self.previous.lexeme().to_string());
+    }
+
+    fn declare_name(&mut self, name: String) {
+        if self.scope_depth == 0 {
+            return;
+        }
+
+        let duplicate = self.locals.iter().rev()
+            .take_while(|l| l.depth.is_none_or(|d| d >= self.scope_depth))
+            .any(|l| l.name == name);
+        if duplicate {
+            self.error(tr("Already a variable with this name in this scope."));
+        }
+        if self.locals.len() >= MAX_LOCALS {
+            self.error(tr("Too many local variables in function."));
+            return;
+        }
+
+        self.locals.push(Local { name, depth: None });
+    }
+
+    // 占用栈槽位但不能按名字访问的局部变量
+    fn add_hidden_local(&mut self) {
+        if self.locals.len() >= MAX_LOCALS {
+            self.error(tr("Too many local variables in function."));
+        }
+        self.locals.push(Local { name: String::new(), depth: Some(self.scope_depth) });
+    }
+
+    fn define_variable(&mut self, global: usize) {
+        if self.scope_depth > 0 {
+            if let Some(local) = self.locals.last_mut() {
+                local.depth = Some(self.scope_depth);
+            }
+            return;
+        }
+
+        self.emit(Opcode::OP_DEFINE_GLOBAL(global));
+    }
+
+    fn resolve_local(&mut self) -> Option<usize> {
+        let name = self.previous.lexeme();
+        let found = self.locals.iter().enumerate().rev()
+            .find(|(_, l)| l.name == name)
+            .map(|(slot, l)| (slot, l.depth.is_none()));
+
+        match found {
+            Some((slot, uninitialized)) => {
+                if uninitialized {
+                    self.error(tr("Can't read local variable in its own initializer."));
+                }
+                Some(slot)
+            },
+            None => {
+                // 暂不支持闭包，外层函数的局部变量不可见
+                if self.enclosing.iter().any(|e| e.locals.iter().any(|l| l.name == name)) {
+                    let massege = trf("Can't capture local variable '{}' of an enclosing function.", &[&self.previous.lexeme_string()]);
+                    self.error(&massege);
+                }
+                None
+            },
+        }
+    }
+
+    fn identifier_constant(&mut self) -> usize {
+        self.make_constant(Value::String(self.previous.lexeme_string()))
+    }
+
+    fn make_constant(&mut self, val: Value) -> usize {
+        if self.chunk.val_len() >= MAX_CONSTS {
+            self.error(tr("Too many constants in one chunk."));
+            return 0;
+        }
+        self.chunk.push_val(val)
+    }
+
+    fn begin_scope(&mut self) {
+        self.scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.scope_depth -= 1;
+
+        while let Some(local) = self.locals.last() {
+            if local.depth.is_some_and(|d| d <= self.scope_depth) {
+                break;
+            }
+            self.emit(Opcode::OP_POP);
+            self.locals.pop();
+        }
+    }
+
+    fn emit(&mut self, op: Opcode) {
+        self.chunk.push_op(op, self.previous.line());
+    }
+
+    fn emit_pops(&mut self, n: usize) {
+        for _ in 0..n {
+            self.emit(Opcode::OP_POP);
+        }
+    }
+
+    // 返回跳转指令的字节偏移，供 patch_jump 回填
+    fn emit_jump(&mut self, op: Opcode) -> usize {
+        let index = self.chunk.op_len();
+        self.emit(op);
+        index
+    }
+
+    fn patch_jump(&mut self, index: usize) {
+        let (op, size) = match self.chunk.get_op(index) {
+            Some(v) => v,
+            None => return,
+        };
+        let offset = self.chunk.op_len() - index - size;
+        if offset > MAX_JUMP {
+            self.error(tr("Too much code to jump over."));
+            return;
+        }
+
+        match op {
+            Opcode::OP_JUMP(_) => self.chunk.set_op(index, Opcode::OP_JUMP(offset)),
+            Opcode::OP_JUMP_IF_FALSE(_) => self.chunk.set_op(index, Opcode::OP_JUMP_IF_FALSE(offset)),
+            Opcode::OP_TRY(_) => self.chunk.set_op(index, Opcode::OP_TRY(offset)),
+            Opcode::OP_TRY_FINALLY(_) => self.chunk.set_op(index, Opcode::OP_TRY_FINALLY(offset)),
+            Opcode::OP_FOR_ITER(_) => self.chunk.set_op(index, Opcode::OP_FOR_ITER(offset)),
+            Opcode::OP_FOR_ENTRY(_) => self.chunk.set_op(index, Opcode::OP_FOR_ENTRY(offset)),
+            _ => {},
+        }
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) {
+        let offset = self.chunk.op_len() + Opcode::OP_LOOP(0).size() - loop_start;
+        if offset > MAX_JUMP {
+            self.error(tr("Loop body too large."));
+            return;
+        }
+        self.emit(Opcode::OP_LOOP(offset));
+    }
+
+    // 跳过词素直到语句边界，之后的错误才会被再次报告
+    fn synchronize(&mut self) {
+        self.panic_mode = false;
+
+        while self.current.token_type() != TokenType::EOF {
+            if self.previous.token_type() == TokenType::SEMICOLON {
+                return;
+            }
+
+            match self.current.token_type() {
+                TokenType::LET | TokenType::FN | TokenType::CLASS | TokenType::IF
+                | TokenType::WHILE | TokenType::FOR | TokenType::RETURN | TokenType::PRINT
+                | TokenType::IMPORT | TokenType::EXPORT | TokenType::TRY | TokenType::THROW
+                | TokenType::BREAK | TokenType::CONTINUE | TokenType::LABEL => return,
+                _ => {},
+            }
+
+            self.advance();
+        }
+    }
+
+    fn advance(&mut self) {
+        let next = self.next_token();
+        self.previous = mem::replace(&mut self.current, next);
+
+        // 词法错误的词素本身携带错误信息
+        while self.current.token_type() == TokenType::ERROR {
+            let massege = self.current.lexeme_string();
+            self.error_at_current(&massege);
+            self.current = self.next_token();
+        }
+    }
+
+    fn next_token(&mut self) -> Token<'a> {
+        match self.tokens.next() {
+            Some(t) => t,
+            None => Token::new("EOF", TokenType::EOF, None, self.current.line()),
+        }
+    }
+
+    fn check(&self, t: TokenType) -> bool {
+        self.current.token_type() == t
+    }
+
+    fn match_token(&mut self, t: TokenType) -> bool {
+        if self.check(t) {
+            self.advance();
+            true
+        }else {
+            false
+        }
+    }
+
+    fn consume(&mut self, t: TokenType, massege: &str) {
+        if self.check(t) {
+            self.advance();
+        }else {
+            self.error_at_current(massege);
+        }
+    }
+
+    fn error(&mut self, massege: &str) {
+        let token = self.previous.clone();
+        self.error_at(&token, massege);
+    }
+
+    fn error_at_current(&mut self, massege: &str) {
+        let token = self.current.clone();
+        self.error_at(&token, massege);
+    }
+
+    fn error_at(&mut self, token: &Token, massege: &str) {
+        if self.panic_mode {
+            return;
+        }
+        self.panic_mode = true;
+
+        let at = match token.token_type() {
+            TokenType::EOF => tr("end").to_string(),
+            TokenType::ERROR => String::new(),
+            _ => format!("'{}'", token.lexeme_string()),
+        };
+        self.errors.push(CompileError { line: token.line(), at, massege: massege.to_string() });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn errors(code: &str) -> Vec<CompileError> {
+        match compile(code) {
+            Ok(_) => vec![],
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn compile_ok_test() {
+        assert!(compile("let a = 1; { let b = a + 2; print b; } while a < 3 { a = a + 1; }").is_ok());
+        assert!(compile("if 1 > 2 { print 1; } else if true { print 2; } else { print 3; }").is_ok());
+    }
+
+    #[test]
+    fn report_every_error_test() {
+        let e = errors("let = 1;\nprint 1 +;\nlet b = 2;\n1 = 2;\n");
+        assert_eq!(e.len(), 3);
+        assert_eq!(e[0].line, 1);
+        assert_eq!(e[0].massege, "Expect variable name.");
+        assert_eq!(e[1].line, 2);
+        assert_eq!(e[1].massege, "Expect expression.");
+        assert_eq!(e[2].line, 4);
+        assert_eq!(e[2].massege, "Invalid assignment target.");
+    }
+
+    #[test]
+    fn suppress_cascade_test() {
+        // 同一语句中的后续错误不应被报告
+        let e = errors("print (1 + ) ) ) ;\nprint 2;");
+        assert_eq!(e.len(), 1);
+
+        // 同步到语句关键字
+        let e = errors("1 + + 2 let a = ; while { }");
+        assert_eq!(e.len(), 3);
+    }
+
+    #[test]
+    fn lexical_error_test() {
+        let e = errors("let a = #;\nlet b = \"abc");
+        assert_eq!(e.len(), 2);
+        assert_eq!(e[0].at, "");
+        assert_eq!(e[1].line, 2);
+    }
+
+    #[test]
+    fn nesting_test() {
+        let deep = |open: &str, close: &str, n: usize| format!("print {}1{};", open.repeat(n), close.repeat(n));
+        assert!(compile(&deep("(", ")", 200)).is_ok());
+        let e = errors(&deep("(", ")", 100_000));
+        assert_eq!(e.len(), 1);
+        assert_eq!(e[0].massege, "Expression nests too deeply.");
+        assert_eq!(errors(&deep("-", "", 100_000))[0].massege, "Expression nests too deeply.");
+
+        let e = errors(&format!("{}print 1;{}\nprint 2;", "{".repeat(100_000), "}".repeat(100_000)));
+        assert_eq!(e[0].massege, "Block nests too deeply.");
+    }
+
+    #[test]
+    fn unsupported_test() {
+        let e = errors("class B;\nclass A { }\nprint 1;");
+        assert_eq!(e.len(), 2);
+        assert_eq!(e[1].to_string(), "[line 2] error at 'class': 'class' is not supported yet.");
+    }
+
+    #[test]
+    fn call_test() {
+        assert!(compile("exit(arg(0) + 1, -f(2)(3));").is_ok());
+        assert_eq!(errors("f(1, 2;")[0].massege, "Expect ')' after arguments.");
+
+        let args = vec!["1"; 256].join(", ");
+        assert_eq!(errors(&format!("f({});", args))[0].massege, "Can't have more than 255 arguments.");
+    }
+
+    #[test]
+    fn function_test() {
+        assert!(compile("fn fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }\nfib(10)").is_ok());
+        assert!(compile("{ fn f(a, b) { return; } f(1, 2); }").is_ok());
+        assert_eq!(errors("return 1;")[0].massege, "Can't return from top-level code.");
+        assert_eq!(errors("{ let a = 1; fn f() { return a; } }")[0].massege, "Can't capture local variable 'a' of an enclosing function.");
+        assert_eq!(errors("fn f(a b) { }")[0].massege, "Expect ')' after parameters.");
+    }
+
+    #[test]
+    fn module_test() {
+        let (_, exports) = Compiler::new(Scanner::new("import \"lib/util.tl\";\nimport util as u;\nexport let a = 1;\nexport fn f() { }\nlet b = 2;\nexport b, u;")).compile_module().unwrap();
+        assert_eq!(exports, ["a", "f", "b", "u"]);
+        assert!(compile("{ import util; util.f(); }").is_ok());
+        assert_eq!(errors("fn f() { export let a = 1; }")[0].massege, "Can only export top-level declarations.");
+        assert_eq!(errors("{ export a; }")[0].massege, "Can only export top-level declarations.");
+        assert_eq!(errors("export 1;")[0].massege, "Expect name after 'export'.");
+        assert_eq!(errors("import 1;")[0].massege, "Expect module name or path after 'import'.");
+        assert_eq!(errors("import util as;")[0].massege, "Expect name after 'as'.");
+        assert_eq!(errors("import util")[0].massege, "Expect ';' after import.");
+    }
+
+    #[test]
+    fn try_test() {
+        assert!(compile("try { throw 1; } catch (e) { print e; } finally { print 2; }").is_ok());
+        assert!(compile("fn f() { try { return 1; } finally { } }").is_ok());
+        assert!(compile("try { } catch { }").is_ok());
+        assert_eq!(errors("try { }")[0].massege, "Expect 'catch' or 'finally' after try block.");
+        assert_eq!(errors("try { } catch (1) { }")[0].massege, "Expect exception variable name.");
+        assert_eq!(errors("try { } catch (e { }")[0].massege, "Expect ')' after exception variable.");
+        assert_eq!(errors("throw 1")[0].massege, "Expect ';' after thrown value.");
+        // 未声明的 catch 变量在块外不可见
+        assert!(compile("{ try { } catch (e) { let e2 = e; } let e = 1; }").is_ok());
+    }
+
+    #[test]
+    fn for_test() {
+        assert!(compile("for x in 0..3 { print x; } for k, v in \"ab\" { let x = k; print v; }").is_ok());
+        assert!(compile("fn f(l) { for x in l { while x { break; } if x { continue; } return x; } }").is_ok());
+        // 循环变量与循环体不在同一作用域
+        assert!(compile("for x in 0..3 { let x = 1; }").is_ok());
+        assert_eq!(errors("for k, k in 0..3 { }")[0].massege, "Already a variable with this name in this scope.");
+        assert_eq!(errors("for in 0..3 { }")[0].massege, "Expect loop variable name.");
+        assert_eq!(errors("for x 0..3 { }")[0].massege, "Expect 'in' after loop variable.");
+        assert_eq!(errors("for x in 0..3 print x;")[0].massege, "Expect '{' after for clause.");
+        assert_eq!(errors("break;")[0].to_string(), "[line 1] error at 'break': Can't use 'break' outside of a loop.");
+        assert_eq!(errors("while true { fn f() { continue; } }")[0].massege, "Can't use 'continue' outside of a loop.");
+        assert_eq!(errors("while true { break }")[0].massege, "Expect ';' after 'break'.");
+    }
+
+    #[test]
+    fn label_test() {
+        assert!(compile("'outer: for i in 0..3 { 'inner: while true { if i { continue 'outer; } break 'inner; } }").is_ok());
+        // 不同函数中的循环可以使用相同的标签
+        assert!(compile("'a: while true { fn f() { 'a: while true { break 'a; } } break 'a; }").is_ok());
+        assert!(compile("'a: while true { break; } 'a: while true { break 'a; }").is_ok());
+        assert_eq!(errors("'a: while true { 'a: while true { } }")[0].to_string(), "[line 1] error at ''a': Label 'a is already in use by an enclosing loop.");
+        assert_eq!(errors("'a: while true { break 'b; }")[0].to_string(), "[line 1] error at ''b': Unknown loop label 'b.");
+        assert_eq!(errors("'a: while true { } while true { continue 'a; }")[0].massege, "Unknown loop label 'a.");
+        assert_eq!(errors("continue 'a;")[0].massege, "Can't use 'continue' outside of a loop.");
+        assert_eq!(errors("'a while true { }")[0].massege, "Expect ':' after loop label.");
+        assert_eq!(errors("'a: { }")[0].massege, "Expect 'while' or 'for' after loop label.");
+        assert_eq!(errors("'a: print 1;\nlet = 2;")[1].line, 2);
+    }
+
+    #[test]
+    fn jump_test() {
+        // break 依次弹出循环体中的局部变量，结束 try 的两个处理器，再跳出循环
+        let chunk = compile("{ let a = 1; while a { let b = 2; try { let c = 3; break; } catch (e) { } } }").unwrap();
+        let mut ops = Vec::new();
+        let mut offset = 0;
+        while let Some((_, size)) = chunk.get_op(offset) {
+            ops.push(crate::debug_tools::disassemble::Instruction::decode(&chunk, offset).mnemonic);
+            offset += size;
+        }
+        let at = ops.iter().position(|m| *m == "OP_FINALLY_JUMP").unwrap();
+        assert_eq!(ops[at - 2..at + 3], ["OP_POP", "OP_END_TRY", "OP_FINALLY_JUMP", "OP_POP", "OP_JUMP"]);
+    }
+
+    #[test]
+    fn eof_error_test() {
+        let e = errors("print 1");
+        assert_eq!(e[0].to_string(), "[line 1] error at end: Expect ';' after value.");
+    }
+}
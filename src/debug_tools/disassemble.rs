@@ -1,54 +1,309 @@
-use crate::chunk::{chunk::*, op::*,};
+use std::io::{self, Write};
+use crate::{chunk::{chunk::*, op::*, value::*,}, stdlib::json};
 
-pub fn disassemble_chunk(chunk: &Chunk, name: &str) {
-    println!("====$ {} $====", name);
+// 反汇编得到的一条指令
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub offset: usize,
+    pub size: usize,
+    pub line: Option<u32>,
+    pub mnemonic: &'static str,
+    pub operands: Vec<usize>,
+    pub constant: Option<Value>, // 操作数指向的常量
+    pub target: Option<usize>, // 跳转目标的字节偏移
+}
+
+impl Instruction {
+    // 解码 offset 处的指令，无法解码时为 OP_UNKNOWN，长度按 1 字节计
+    pub fn decode(chunk: &Chunk, offset: usize) -> Instruction {
+        let mut ret = Instruction {
+            offset,
+            size: 1,
+            line: chunk.get_line(offset),
+            mnemonic: "OP_UNKNOWN",
+            operands: Vec::new(),
+            constant: None,
+            target: None,
+        };
+
+        let (op, size) = match chunk.get_op(offset) {
+            Some(v) => v,
+            None => return ret,
+        };
+        ret.size = size;
+        let (mnemonic, operand) = match op {
+            Opcode::OP_RETURN => ("OP_RETURN", None),
+            Opcode::OP_CONST(index) => ("OP_CONST", Some(index)),
+            Opcode::OP_CONST_LONG(index) => ("OP_CONST_LONG", Some(index)),
+            Opcode::OP_NULL => ("OP_NULL", None),
+            Opcode::OP_TRUE => ("OP_TRUE", None),
+            Opcode::OP_FALSE => ("OP_FALSE", None),
+            Opcode::OP_NEGATE => ("OP_NEGATE", None),
+            Opcode::OP_NOT => ("OP_NOT", None),
+            Opcode::OP_ADD => ("OP_ADD", None),
+            Opcode::OP_SUB => ("OP_SUBTRACT", None),
+            Opcode::OP_MULTIPLY => ("OP_MULTIPLY", None),
+            Opcode::OP_DIVIDE => ("OP_DIVIDE", None),
+            Opcode::OP_EQUAL => ("OP_EQUAL", None),
+            Opcode::OP_GREATER => ("OP_GREATER", None),
+            Opcode::OP_LESS => ("OP_LESS", None),
+            Opcode::OP_PRINT => ("OP_PRINT", None),
+            Opcode::OP_POP => ("OP_POP", None),
+            Opcode::OP_DEFINE_GLOBAL(index) => ("OP_DEFINE_GLOBAL", Some(index)),
+            Opcode::OP_GET_GLOBAL(index) => ("OP_GET_GLOBAL", Some(index)),
+            Opcode::OP_SET_GLOBAL(index) => ("OP_SET_GLOBAL", Some(index)),
+            Opcode::OP_GET_LOCAL(slot) => ("OP_GET_LOCAL", Some(slot)),
+            Opcode::OP_SET_LOCAL(slot) => ("OP_SET_LOCAL", Some(slot)),
+            Opcode::OP_JUMP(jump) => ("OP_JUMP", Some(jump)),
+            Opcode::OP_JUMP_IF_FALSE(jump) => ("OP_JUMP_IF_FALSE", Some(jump)),
+            Opcode::OP_LOOP(jump) => ("OP_LOOP", Some(jump)),
+            Opcode::OP_CALL(argc) => ("OP_CALL", Some(argc)),
+            Opcode::OP_GET_PROPERTY(index) => ("OP_GET_PROPERTY", Some(index)),
+            Opcode::OP_IMPORT(index) => ("OP_IMPORT", Some(index)),
+            Opcode::OP_TRY(jump) => ("OP_TRY", Some(jump)),
+            Opcode::OP_TRY_FINALLY(jump) => ("OP_TRY_FINALLY", Some(jump)),
+            Opcode::OP_END_TRY => ("OP_END_TRY", None),
+            Opcode::OP_THROW => ("OP_THROW", None),
+            Opcode::OP_END_FINALLY => ("OP_END_FINALLY", None),
+            Opcode::OP_ITER => ("OP_ITER", None),
+            Opcode::OP_FOR_ITER(jump) => ("OP_FOR_ITER", Some(jump)),
+            Opcode::OP_FOR_ENTRY(jump) => ("OP_FOR_ENTRY", Some(jump)),
+            Opcode::OP_RANGE => ("OP_RANGE", None),
+            Opcode::OP_RANGE_INCLUSIVE => ("OP_RANGE_INCLUSIVE", None),
+            Opcode::OP_FINALLY_JUMP => ("OP_FINALLY_JUMP", None),
+        };
+        ret.mnemonic = mnemonic;
+        ret.operands.extend(operand);
+
+        match op {
+            Opcode::OP_CONST(index) | Opcode::OP_CONST_LONG(index) | Opcode::OP_DEFINE_GLOBAL(index)
+            | Opcode::OP_GET_GLOBAL(index) | Opcode::OP_SET_GLOBAL(index) | Opcode::OP_GET_PROPERTY(index) | Opcode::OP_IMPORT(index) => {
+                ret.constant = Some(chunk.get_val(index).clone());
+            },
+            Opcode::OP_JUMP(jump) | Opcode::OP_JUMP_IF_FALSE(jump)
+            | Opcode::OP_TRY(jump) | Opcode::OP_TRY_FINALLY(jump)
+            | Opcode::OP_FOR_ITER(jump) | Opcode::OP_FOR_ENTRY(jump) => ret.target = Some(offset + size + jump),
+            Opcode::OP_LOOP(jump) => ret.target = (offset + size).checked_sub(jump),
+            _ => {},
+        }
+        ret
+    }
 
-    for offset in 0..chunk.op_len() {
-        disassemble_instruction(chunk, offset);
+    // 助记符及操作数，如 "OP_CONST 1"、"OP_JUMP 3 -> 10"
+    pub fn text(&self) -> String {
+        let mut ret = self.mnemonic.to_string();
+        match (&self.constant, self.target) {
+            (Some(val), _) => ret.push_str(&format!(" {}", val)),
+            (None, Some(target)) => ret.push_str(&format!(" {} -> {}", self.operands[0], target)),
+            (None, None) => {
+                for x in &self.operands {
+                    ret.push_str(&format!(" {}", x));
+                }
+            },
+        }
+        ret
+    }
+
+    // 与上一条指令同一行时行号列显示为 "   |"
+    pub fn write_text<W: Write>(&self, prev_line: Option<u32>, out: &mut W) -> io::Result<()> {
+        let line = match self.line {
+            Some(line) if prev_line == Some(line) => "   |".to_string(),
+            Some(line) => format!("{:04}", line),
+            None => "   ?".to_string(),
+        };
+        writeln!(out, "{} {:04}$ {},", line, self.offset, self.text())
     }
 
-    println!("====$ over $====");
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "{{\"offset\":{},\"line\":", self.offset)?;
+        match self.line {
+            Some(line) => write!(out, "{}", line)?,
+            None => write!(out, "null")?,
+        }
+        write!(out, ",\"mnemonic\":\"{}\",\"operands\":[", self.mnemonic)?;
+        for (i, x) in self.operands.iter().enumerate() {
+            write!(out, "{}{}", if i > 0 { "," }else { "" }, x)?;
+        }
+        write!(out, "],\"constant\":")?;
+        match &self.constant {
+            Some(Value::Double(d)) if d.is_finite() => write!(out, "{}", d)?,
+            Some(Value::String(s)) => write_json_string(s, out)?,
+            Some(Value::Bool(b)) => write!(out, "{}", b)?,
+            // 非有限的数字没有 JSON 表示，以字符串记录
+            Some(Value::Double(d)) => write_json_string(&d.to_string(), out)?,
+            Some(v @ (Value::Native(_) | Value::Function(_) | Value::Bound(_) | Value::List(_) | Value::Map(_) | Value::Regex(_) | Value::Range(_) | Value::Iterator(_))) => write_json_string(&v.to_string(), out)?,
+            Some(Value::Null) | None => write!(out, "null")?,
+        }
+        write!(out, ",\"target\":")?;
+        match self.target {
+            Some(target) => write!(out, "{}}}", target),
+            None => write!(out, "null}}"),
+        }
+    }
 }
 
-pub fn disassemble_instruction(chunk: &Chunk, offset: usize) {
-    let print_code = | massege | {
-        println!("{} {:04}$ {},",
-            match chunk.get_line(offset) {
-                Some(line) => {
-                    if offset > 0 && chunk.get_line(offset - 1).unwrap() == line {
-                        format!("   |")
-                    }else {
-                        format!("{:04}", line)
-                    }
-                },
-                None => format!("   ?"),
-            },
-            offset, massege);
-    };
-
-    let instruction = chunk.get_op(offset).unwrap();
-    match instruction {
-        Opcode::OP_RETURN => {
-            print_code("OP_RETURN");
-        },
-        Opcode::OP_CONST(index) => {
-            let val = chunk.get_val(*index).to_string();
-            print_code(&format!("OP_CONST {}", val));
-        },
-        Opcode::OP_NEGATE => {
-            print_code("OP_NEGATE");
-        },
-        Opcode::OP_ADD => {
-            print_code("OP_ADD");
-        },
-        Opcode::OP_DIVIDE => {
-            print_code("OP_DIVIDE");
-        },
-        Opcode::OP_MULTIPLY => {
-            print_code("OP_MULTIPLY");
-        },
-        Opcode::OP_SUB => {
-            print_code("OP_SUBTRACT");
+// 整个 chunk 的反汇编结果，常量池中的函数各自生成一份
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing {
+    pub name: String,
+    pub instructions: Vec<Instruction>,
+    pub functions: Vec<Listing>,
+}
+
+impl Listing {
+    pub fn from(chunk: &Chunk, name: &str) -> Listing {
+        let mut instructions = Vec::new();
+        let mut offset = 0;
+        while offset < chunk.op_len() {
+            let ins = Instruction::decode(chunk, offset);
+            offset += ins.size;
+            instructions.push(ins);
+        }
+
+        let functions = (0..chunk.val_len())
+            .filter_map(|i| match chunk.get_val(i) {
+                Value::Function(f) => Some(Listing::from(&f.chunk, &f.name)),
+                _ => None,
+            })
+            .collect();
+        Listing { name: name.to_string(), instructions, functions }
+    }
+
+    pub fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "====$ {} $====", self.name)?;
+        let mut prev_line = None;
+        for ins in &self.instructions {
+            ins.write_text(prev_line, out)?;
+            prev_line = ins.line;
+        }
+        writeln!(out, "====$ over $====")?;
+
+        for f in &self.functions {
+            f.write_text(out)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "{{\"name\":")?;
+        write_json_string(&self.name, out)?;
+        write!(out, ",\"instructions\":[")?;
+        for (i, ins) in self.instructions.iter().enumerate() {
+            write!(out, "{}\n  ", if i > 0 { "," }else { "" })?;
+            ins.write_json(out)?;
+        }
+        write!(out, "\n],\"functions\":[")?;
+        for (i, f) in self.functions.iter().enumerate() {
+            if i > 0 {
+                write!(out, ",")?;
+            }
+            f.write_json(out)?;
+        }
+        writeln!(out, "]}}")
+    }
+
+    // 左侧为源码，右侧为该行生成的指令；没有对应源码行的指令列在最后。
+    // 函数体中的指令以 "函数名:" 开头
+    pub fn write_side_by_side<W: Write>(&self, source: &str, out: &mut W) -> io::Result<()> {
+        let lines: Vec<&str> = source.lines().collect();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let mut all = Vec::new();
+        self.collect("", &mut all);
+
+        for (i, src) in lines.iter().enumerate() {
+            let line = i as u32 + 1;
+            let mut ins = all.iter().filter(|(_, ins)| ins.line == Some(line));
+            let pad = width - src.chars().count();
+            match ins.next() {
+                Some((prefix, first)) => writeln!(out, "{:4} | {}{} | {}{:04}$ {}", line, src, " ".repeat(pad), prefix, first.offset, first.text())?,
+                None => writeln!(out, "{:4} | {}", line, src)?,
+            }
+            for (prefix, rest) in ins {
+                writeln!(out, "     | {} | {}{:04}$ {}", " ".repeat(width), prefix, rest.offset, rest.text())?;
+            }
+        }
+
+        let count = lines.len() as u32;
+        for (prefix, ins) in all.iter().filter(|(_, ins)| ins.line.is_none_or(|l| l == 0 || l > count)) {
+            writeln!(out, "     | {} | {}{:04}$ {}", " ".repeat(width), prefix, ins.offset, ins.text())?;
+        }
+        Ok(())
+    }
+
+    fn collect<'a>(&'a self, prefix: &str, all: &mut Vec<(String, &'a Instruction)>) {
+        all.extend(self.instructions.iter().map(|ins| (prefix.to_string(), ins)));
+        for f in &self.functions {
+            f.collect(&format!("{}: ", f.name), all);
         }
     }
 }
+
+fn write_json_string<W: Write>(s: &str, out: &mut W) -> io::Result<()> {
+    out.write_all(json::quote(s).as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compiler::compiler::compile;
+
+    #[test]
+    fn listing_test() {
+        let chunk = compile("let a = 1;\nwhile a < 3 {\n a = a + 1; }").unwrap();
+        let listing = Listing::from(&chunk, "test");
+
+        let first = &listing.instructions[0];
+        assert_eq!((first.offset, first.line, first.mnemonic), (0, Some(1), "OP_CONST"));
+        assert_eq!(first.constant, Some(Value::Double(1.0)));
+        let jump = listing.instructions.iter().find(|i| i.mnemonic == "OP_JUMP_IF_FALSE").unwrap();
+        assert!(jump.target.unwrap() < chunk.op_len());
+        assert_eq!(listing.instructions.last().unwrap().mnemonic, "OP_RETURN");
+
+        let mut text = Vec::new();
+        listing.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with("====$ test $====\n0001 0000$ OP_CONST 1,\n   | 0002$ OP_DEFINE_GLOBAL a,\n"));
+    }
+
+    #[test]
+    fn json_test() {
+        let chunk = compile("print \"a\\b\";").unwrap();
+        let mut json = Vec::new();
+        Listing::from(&chunk, "t").write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with("{\"name\":\"t\",\"instructions\":["));
+        assert!(json.contains("{\"offset\":0,\"line\":1,\"mnemonic\":\"OP_CONST\",\"operands\":[0],\"constant\":\"a\\\\b\",\"target\":null}"));
+    }
+
+    #[test]
+    fn side_by_side_test() {
+        let source = "let a = 1;\n\nprint a;";
+        let chunk = compile(source).unwrap();
+        let mut out = Vec::new();
+        Listing::from(&chunk, "t").write_side_by_side(source, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "   1 | let a = 1; | 0000$ OP_CONST 1");
+        assert_eq!(lines[1], "     |            | 0002$ OP_DEFINE_GLOBAL a");
+        assert_eq!(lines[2], "   2 | ");
+        assert_eq!(lines[3], "   3 | print a;   | 0006$ OP_GET_GLOBAL a");
+    }
+
+    #[test]
+    fn function_test() {
+        let source = "fn f(x) {\n return x; }\nprint f(1);";
+        let chunk = compile(source).unwrap();
+        let listing = Listing::from(&chunk, "t");
+        assert_eq!(listing.functions.len(), 1);
+        assert_eq!(listing.instructions[0].text(), "OP_CONST <fn f>");
+
+        let mut text = Vec::new();
+        listing.write_text(&mut text).unwrap();
+        assert!(String::from_utf8(text).unwrap().contains("====$ f $====\n0002 0000$ OP_GET_LOCAL 1,\n"));
+
+        let mut out = Vec::new();
+        listing.write_side_by_side(source, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("   2 |  return x; } | 0000$ OP_CONST <fn f>\n     |              | 0002$ OP_DEFINE_GLOBAL f\n     |              | f: 0000$ OP_GET_LOCAL 1\n"));
+    }
+}
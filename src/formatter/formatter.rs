@@ -0,0 +1,218 @@
+use crate::{compiler::compile_error::CompileError, lexical_analysis::{scanner::Scanner, token::Token, token_type::TokenType}};
+
+const INDENT: &str = "    ";
+
+// 按统一风格重新排版源码：每条语句一行、花括号缩进 4 个空格、二元运算符两侧加空格。
+// 保留注释，连续的空行合并为一行；存在词法错误时不做修改。
+pub fn format(code: &str) -> Result<String, Vec<CompileError>> {
+    let mut scanner = Scanner::new(code);
+    scanner.set_comments(true);
+    let tokens: Vec<Token> = scanner.by_ref().collect();
+
+    let errors: Vec<CompileError> = tokens.iter()
+        .filter(|t| t.token_type() == TokenType::ERROR)
+        .map(|t| CompileError { line: t.line(), at: String::new(), massege: t.lexeme_string() })
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut f = Formatter { out: String::new(), depth: 0, line_start: true };
+    let mut prev: Option<&Token> = None; // 上一个非注释词素
+    let mut prev_unary = false;
+    let mut end_line = 0; // 上一个词素结束的行
+
+    for (i, t) in tokens.iter().enumerate() {
+        let t_type = t.token_type();
+        if t_type == TokenType::EOF {
+            break;
+        }
+        if f.line_start && !f.out.is_empty() && t.line() > end_line + 1 {
+            f.out.push('\n');
+        }
+
+        match t_type {
+            TokenType::COMMENT => {
+                // 行尾注释留在原来的行
+                if f.line_start && t.line() == end_line && f.out.ends_with('\n') {
+                    f.out.pop();
+                    f.line_start = false;
+                }
+                f.word(&t.lexeme_string(), !f.line_start);
+                f.newline();
+                end_line = t.line() + t.lexeme().matches('\n').count() as u32;
+                continue;
+            },
+            TokenType::LEFT_BRACE => {
+                f.word("{", true);
+                f.depth += 1;
+                f.newline();
+            },
+            TokenType::RIGHT_BRACE => {
+                if !f.line_start {
+                    f.newline();
+                }
+                f.depth = f.depth.saturating_sub(1);
+                f.word("}", false);
+                if tokens.get(i + 1).is_none_or(|n| !matches!(n.token_type(), TokenType::ELSE | TokenType::CATCH | TokenType::FINALLY)) {
+                    f.newline();
+                }
+            },
+            TokenType::SEMICOLON => {
+                f.word(";", false);
+                f.newline();
+            },
+            _ => {
+                let text = if t_type == TokenType::STRING {
+                    format!("\"{}\"", t.lexeme_string())
+                }else {
+                    t.lexeme_string()
+                };
+                let space = match prev {
+                    Some(p) => !prev_unary && needs_space(p.token_type(), t_type),
+                    None => false,
+                };
+                f.word(&text, space);
+            },
+        }
+
+        prev_unary = match t_type {
+            TokenType::BANG => true,
+            TokenType::MINUS => prev.is_none_or(|p| !is_operand(p.token_type())),
+            _ => false,
+        };
+        prev = Some(t);
+        end_line = t.line();
+    }
+
+    if !f.line_start {
+        f.newline();
+    }
+    Ok(f.out)
+}
+
+struct Formatter {
+    out: String,
+    depth: usize,
+    line_start: bool,
+}
+
+impl Formatter {
+    fn word(&mut self, text: &str, space: bool) {
+        if self.line_start {
+            for _ in 0..self.depth {
+                self.out.push_str(INDENT);
+            }
+            self.line_start = false;
+        }else if space {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.out.truncate(self.out.trim_end_matches(' ').len());
+        self.out.push('\n');
+        self.line_start = true;
+    }
+}
+
+// 可以作为二元运算符左侧操作数结尾的词素
+fn is_operand(t: TokenType) -> bool {
+    matches!(t, TokenType::IDENTIFIER | TokenType::STRING | TokenType::DOUBLE | TokenType::RIGHT_PAREN
+        | TokenType::TRUE | TokenType::FALSE | TokenType::NULL | TokenType::THIS | TokenType::SUPER)
+}
+
+fn needs_space(prev: TokenType, cur: TokenType) -> bool {
+    match (prev, cur) {
+        (_, TokenType::RIGHT_PAREN | TokenType::COMMA | TokenType::DOT | TokenType::COLON) => false,
+        (TokenType::LEFT_PAREN | TokenType::DOT, _) => false,
+        // 区间，如 0..10
+        (_, TokenType::DOT_DOT | TokenType::DOT_DOT_EQUAL) | (TokenType::DOT_DOT | TokenType::DOT_DOT_EQUAL, _) => false,
+        // 函数调用
+        (TokenType::IDENTIFIER | TokenType::RIGHT_PAREN | TokenType::THIS | TokenType::SUPER, TokenType::LEFT_PAREN) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_test() {
+        let code = "let a=-1;if a<0&&!(a== -2){print a*(3-a);}else if a{a=a+1;}\nwhile a<3 {a=a+1;}";
+        assert_eq!(format(code).unwrap(), "\
+let a = -1;
+if a < 0 && !(a == -2) {
+    print a * (3 - a);
+} else if a {
+    a = a + 1;
+}
+while a < 3 {
+    a = a + 1;
+}
+");
+    }
+
+    #[test]
+    fn try_test() {
+        assert_eq!(format("try{throw 1;}catch(e){print e;}finally{print 2;}").unwrap(), "\
+try {
+    throw 1;
+} catch (e) {
+    print e;
+} finally {
+    print 2;
+}
+");
+    }
+
+    #[test]
+    fn for_test() {
+        assert_eq!(format("for i,x in (0 ..= n+1).step(2){if x{continue;}break;}").unwrap(), "\
+for i, x in (0..=n + 1).step(2) {
+    if x {
+        continue;
+    }
+    break;
+}
+");
+    }
+
+    #[test]
+    fn label_test() {
+        assert_eq!(format("'outer : while a{break 'outer;}").unwrap(), "\
+'outer: while a {
+    break 'outer;
+}
+");
+    }
+
+    #[test]
+    fn comment_test() {
+        let code = "// tlang: keywords=zh\n令 a = \"x\";   // 行尾\n\n\n\n/* 多行\n注释 */\n当 a { 打印 a; }";
+        assert_eq!(format(code).unwrap(), "\
+// tlang: keywords=zh
+令 a = \"x\"; // 行尾
+
+/* 多行
+注释 */
+当 a {
+    打印 a;
+}
+");
+    }
+
+    #[test]
+    fn idempotent_test() {
+        let code = "let x = 1;\n{ let y = x/2; { print y; } }\n// end";
+        let once = format(code).unwrap();
+        assert_eq!(format(&once).unwrap(), once);
+    }
+
+    #[test]
+    fn lexical_error_test() {
+        assert_eq!(format("let a = #;").unwrap_err().len(), 1);
+    }
+}
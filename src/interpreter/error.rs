@@ -0,0 +1,43 @@
+use std::fmt::Display;
+use crate::{compiler::compile_error::CompileError, vm::{interpret_result::InterpretResult, runtime_error::RuntimeError}};
+use crate::interpreter_error::catalog::*;
+
+// Interpreter 返回的错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Compile(Vec<CompileError>),
+    Runtime(RuntimeError),
+    Exit(i32), // 脚本调用了 exit(code)
+}
+
+impl Error {
+    // InterpretResult::Ok 之外的结果转换为错误
+    pub fn check(result: InterpretResult) -> Result<(), Error> {
+        match result {
+            InterpretResult::Ok => Ok(()),
+            InterpretResult::COMPLE_ERROR(errors) => Err(Error::Compile(errors)),
+            InterpretResult::RUNTIME_ERROR(e) => Err(Error::Runtime(e)),
+            InterpretResult::EXIT(code) => Err(Error::Exit(code)),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Compile(errors) => {
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", e)?;
+                }
+                Ok(())
+            },
+            Error::Runtime(e) => write!(f, "{}", e),
+            Error::Exit(code) => write!(f, "{}", trf("script exited with code {}.", &[code])),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,153 @@
+use std::io::{BufRead, Write};
+use crate::{chunk::{chunk::Chunk, value::*}, compiler::compile_error::CompileError, vm::{runtime_error::RuntimeError, vm::VM}};
+use crate::interpreter_error::catalog::*;
+use super::error::Error;
+
+// 供宿主程序嵌入使用的解释器，全局变量在多次 eval 之间保留。
+// 库中不会退出进程，也不会直接读写标准输入输出；print 的去处由 set_output 决定，默认丢弃。
+pub struct Interpreter {
+    vm: VM,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self { vm: VM::from(Chunk::new()) }
+    }
+
+    // 执行源码，返回结尾不带 ';' 的表达式的值，否则为 null
+    pub fn eval(&mut self, code: &str) -> Result<Value, Error> {
+        Error::check(self.vm.interpret(code))?;
+        Ok(self.vm.take_result())
+    }
+
+    // 调用全局函数或本地函数
+    pub fn call(&mut self, fn_name: &str, args: &[Value]) -> Result<Value, Error> {
+        let callee = match self.vm.get_global(fn_name) {
+            Some(v) => v.clone(),
+            None => return Err(Error::Runtime(RuntimeError::new(trf("Undefined variable '{}'.", &[&fn_name])))),
+        };
+        Error::check(self.vm.call(callee, args))?;
+        Ok(self.vm.take_result())
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.vm.get_global(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, val: Value) -> &mut Self {
+        self.vm.set_global(name, val);
+        self
+    }
+
+    pub fn define_native(&mut self, name: &str, arity: usize, fun: NativeFn) -> &mut Self {
+        self.vm.define_native(name, arity, fun);
+        self
+    }
+
+    pub fn set_output(&mut self, out: Box<dyn Write>) -> &mut Self {
+        self.vm.set_output(out);
+        self
+    }
+
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) -> &mut Self {
+        self.vm.set_input(input);
+        self
+    }
+
+    // import 在导入者所在目录之后依次查找的目录
+    pub fn add_search_path(&mut self, path: impl Into<std::path::PathBuf>) -> &mut Self {
+        self.vm.add_search_path(path);
+        self
+    }
+
+    // 关闭后脚本不能读写文件或读取标准输入
+    pub fn set_io_allowed(&mut self, allowed: bool) -> &mut Self {
+        self.vm.set_io_allowed(allowed);
+        self
+    }
+
+    // 最近一次 eval 产生的警告
+    pub fn warnings(&self) -> &[CompileError] {
+        self.vm.warnings()
+    }
+
+    pub fn vm(&mut self) -> &mut VM {
+        &mut self.vm
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, io, rc::Rc};
+    use super::*;
+
+    // 收集 print 输出
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn eval_test() {
+        let mut t = Interpreter::new();
+        assert_eq!(t.eval("let a = 2;\na * 3"), Ok(Value::Double(6.0)));
+        assert_eq!(t.eval("a = a + 1;"), Ok(Value::Null));
+        assert_eq!(t.get_global("a"), Some(&Value::Double(3.0)));
+
+        t.set_global("name", Value::String("tlang".to_string()));
+        assert_eq!(t.eval("name + \"!\""), Ok(Value::String("tlang!".to_string())));
+
+        match t.eval("let = 1;") {
+            Err(Error::Compile(e)) => assert_eq!(e[0].massege, "Expect variable name."),
+            r => panic!("{:?}", r),
+        }
+        assert_eq!(t.eval("\n-name").unwrap_err().to_string(), "[line 2] runtime error: Operand must be a number.");
+        assert_eq!(t.eval("exit(3);"), Err(Error::Exit(3)));
+    }
+
+    #[test]
+    fn call_test() {
+        let mut t = Interpreter::new();
+        t.eval("fn fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }").unwrap();
+        assert_eq!(t.call("fib", &[Value::Double(10.0)]), Ok(Value::Double(55.0)));
+        assert_eq!(t.call("arg_count", &[]), Ok(Value::Double(0.0)));
+
+        assert_eq!(t.call("fib", &[]).unwrap_err().to_string(), "[line 0] runtime error: fib expects 1 arguments but got 0.");
+        assert_eq!(t.call("nope", &[]).unwrap_err().to_string(), "[line 0] runtime error: Undefined variable 'nope'.");
+
+        // 出错后解释器仍可继续使用
+        t.eval("fn bad(x) {\n return -x; }").unwrap();
+        assert_eq!(t.call("bad", &[Value::Null]).unwrap_err().to_string(), "[line 2] runtime error: Operand must be a number.");
+        assert_eq!(t.call("fib", &[Value::Double(6.0)]), Ok(Value::Double(8.0)));
+    }
+
+    #[test]
+    fn output_test() {
+        let out = Output::default();
+        let mut t = Interpreter::new();
+        t.set_output(Box::new(out.clone()));
+        t.eval("fn greet(who) { print \"hi \" + who; }\ngreet(\"a\");").unwrap();
+        t.call("greet", &[Value::String("b".to_string())]).unwrap();
+        assert_eq!(String::from_utf8(out.0.borrow().clone()).unwrap(), "hi a\nhi b\n");
+
+        // 默认不读标准输入，由宿主提供输入
+        let mut t = Interpreter::new();
+        assert_eq!(t.eval("io.read_line()"), Ok(Value::Null));
+        t.set_input(Box::new(io::Cursor::new("line")));
+        assert_eq!(t.eval("io.read_line()"), Ok(Value::String("line".to_string())));
+    }
+}
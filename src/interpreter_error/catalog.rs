@@ -0,0 +1,289 @@
+use std::cell::Cell;
+
+// 诊断信息的语言目录：以英文原文作为消息标识，按当前语言查找译文
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    En,
+    Zh,
+}
+
+impl Locale {
+    pub fn from_name(name: &str) -> Option<Locale> {
+        match name {
+            "en" | "english" => Some(Locale::En),
+            "zh" | "chinese" | "中文" => Some(Locale::Zh),
+            _ => {
+                // 兼容 LANG 形式，如 zh_CN.UTF-8
+                if name.starts_with("zh") {
+                    Some(Locale::Zh)
+                }else if name.starts_with("en") {
+                    Some(Locale::En)
+                }else {
+                    None
+                }
+            },
+        }
+    }
+}
+
+thread_local! {
+    static LOCALE: Cell<Locale> = const { Cell::new(Locale::En) };
+}
+
+pub fn set_locale(locale: Locale) {
+    LOCALE.with(|l| l.set(locale));
+}
+
+pub fn locale() -> Locale {
+    LOCALE.with(|l| l.get())
+}
+
+// (英文, 中文)
+const CATALOG: &[(&str, &str)] = &[
+    // interpreter_error
+    ("interpreter error!\nhere:\n{}", "解释器错误！\n位置：\n{}"),
+    ("interpreter warning!\nhere:\n{}", "解释器警告！\n位置：\n{}"),
+    ("can't read file ' {} '!", "无法读取文件 ' {} '！"),
+    ("lexical analysis errror!total {} error.", "词法分析错误！共 {} 个错误。"),
+    ("compile errror!total {} error.", "编译错误！共 {} 个错误。"),
+    ("unknown bytecode format ' {} '!", "未知的字节码格式 ' {} '！"),
+    ("output error!", "输出错误！"),
+
+    // cli
+    ("option ' {} ' needs a value!", "选项 ' {} ' 需要一个值！"),
+    ("invalid number ' {} '!", "无效的数字 ' {} '！"),
+    ("unknown option ' {} '!", "未知的选项 ' {} '！"),
+    ("unexpected argument ' {} '!", "多余的参数 ' {} '！"),
+    ("no input file!", "没有输入文件！"),
+    ("compile needs an output file (-o)!", "compile 需要输出文件 (-o)！"),
+    ("can't write file ' {} '!", "无法写入文件 ' {} '！"),
+    ("script is not valid utf-8!", "脚本不是有效的 UTF-8！"),
+    ("invalid bytecode file: {}", "无效的字节码文件：{}"),
+    ("\
+Usage: tlang [options] [command] [file | -] [script args...]
+
+Commands:
+    run <file>                  compile and run a script or a .tlc file (default)
+    repl                        start the interactive shell (default without a file)
+    tokens <file>               print the tokens of a script
+    disasm <file>               print the bytecode of a script
+    compile <file> -o <out>     write the bytecode to a .tlc file
+    check <file>                report errors without running
+    fmt <file>                  print the formatted script
+
+Options:
+    -e <code>                   use <code> instead of a file
+    -                           read the script from stdin
+    --trace                     print the stack and each instruction while running
+    --stack-max <n>             limit the VM stack to <n> values
+    --format <text|json|source> output format of disasm
+    -o <file>                   output file of compile
+    -w, --write                 let fmt rewrite the file in place
+    -I <dir>                    also look for imported modules in <dir>
+    -h, --help                  print this help", "\
+用法：tlang [选项] [命令] [文件 | -] [脚本参数...]
+
+命令：
+    run <文件>                  编译并运行脚本或 .tlc 文件（默认）
+    repl                        启动交互式 shell（未给出文件时默认）
+    tokens <文件>               输出脚本的词素
+    disasm <文件>               输出脚本的字节码
+    compile <文件> -o <输出>    将字节码写入 .tlc 文件
+    check <文件>                只检查错误，不运行
+    fmt <文件>                  输出格式化后的脚本
+
+选项：
+    -e <代码>                   以 <代码> 代替文件
+    -                           从标准输入读取脚本
+    --trace                     运行时输出栈和每条指令
+    --stack-max <n>             将 VM 栈限制为 <n> 个值
+    --format <text|json|source> disasm 的输出格式
+    -o <文件>                   compile 的输出文件
+    -w, --write                 fmt 直接改写文件
+    -I <目录>                   也在 <目录> 中查找导入的模块
+    -h, --help                  输出本帮助"),
+    ("{}\n in line={}", "{}\n 位于第 {} 行"),
+    ("input error!", "输入错误！"),
+    ("wellcome!this is tshell!", "欢迎！这里是 tshell！"),
+    ("unknown keyword set ' {} '!", "未知的关键字集 ' {} '！"),
+    ("unknown language ' {} '!", "未知的语言 ' {} '！"),
+
+    // compile_error
+    ("[line {}] error at {}: {}", "[第 {} 行] 错误，位于 {}：{}"),
+    ("[line {}] error: {}", "[第 {} 行] 错误：{}"),
+    ("end", "末尾"),
+
+    // scanner
+    ("{} char={}", "{} 字符={}"),
+    ("unknown char!", "未知字符！"),
+    ("Unterminated string", "字符串未结束"),
+    ("identifier mixes scripts {} and may be confusable.", "标识符混用了书写系统 {}，可能造成混淆。"),
+
+    // compiler
+    ("Expect variable name.", "此处应为变量名。"),
+    ("Expect ';' after variable declaration.", "变量声明后应有 ';'。"),
+    ("Expect ';' after value.", "值后应有 ';'。"),
+    ("Expect ';' after expression.", "表达式后应有 ';'。"),
+    ("Expect '{' after condition.", "条件后应有 '{'。"),
+    ("Expect '{' after 'else'.", "'else' 后应有 '{'。"),
+    ("Expect '}' after block.", "代码块末尾应有 '}'。"),
+    ("'{}' is not supported yet.", "暂不支持 '{}'。"),
+    ("Expect expression.", "此处应为表达式。"),
+    ("Expression nests too deeply.", "表达式嵌套过深。"),
+    ("Block nests too deeply.", "代码块嵌套过深。"),
+    ("Invalid assignment target.", "无效的赋值目标。"),
+    ("Expect ')' after expression.", "表达式后应有 ')'。"),
+    ("Already a variable with this name in this scope.", "此作用域中已存在同名变量。"),
+    ("Can't read local variable in its own initializer.", "不能在局部变量的初始化式中读取它自身。"),
+    ("Too many constants in one chunk.", "常量数量超出上限。"),
+    ("Too many local variables in function.", "局部变量数量超出上限。"),
+    ("Too much code to jump over.", "跳转跨越的代码过长。"),
+    ("Loop body too large.", "循环体过长。"),
+    ("Can't have more than 255 arguments.", "参数不能超过 255 个。"),
+    ("Expect ')' after arguments.", "参数列表后应有 ')'。"),
+    ("Expect property name after '.'.", "'.' 后应为属性名。"),
+    ("Expect function name.", "此处应为函数名。"),
+    ("Expect '(' after function name.", "函数名后应有 '('。"),
+    ("Can't have more than 255 parameters.", "形参不能超过 255 个。"),
+    ("Expect parameter name.", "此处应为形参名。"),
+    ("Expect ')' after parameters.", "形参列表后应有 ')'。"),
+    ("Expect '{' before function body.", "函数体前应有 '{'。"),
+    ("Can't return from top-level code.", "不能在顶层代码中 return。"),
+    ("Expect ';' after return value.", "返回值后应有 ';'。"),
+    ("Can't capture local variable '{}' of an enclosing function.", "不能引用外层函数的局部变量 '{}'。"),
+    ("Expect module name or path after 'import'.", "'import' 之后应为模块名或路径。"),
+    ("Expect name after 'as'.", "'as' 之后应为名字。"),
+    ("Expect ';' after import.", "import 之后应为 ';'。"),
+    ("Can only export top-level declarations.", "只能导出顶层的声明。"),
+    ("Expect name after 'export'.", "'export' 之后应为名字。"),
+    ("Expect ';' after exported names.", "导出的名字之后应为 ';'。"),
+    ("Expect '{' after 'try'.", "'try' 之后应有 '{'。"),
+    ("Expect exception variable name.", "此处应为异常变量名。"),
+    ("Expect ')' after exception variable.", "异常变量之后应有 ')'。"),
+    ("Expect '{' after 'catch'.", "'catch' 之后应有 '{'。"),
+    ("Expect '{' after 'finally'.", "'finally' 之后应有 '{'。"),
+    ("Expect 'catch' or 'finally' after try block.", "try 块之后应有 'catch' 或 'finally'。"),
+    ("Expect ';' after thrown value.", "抛出的值之后应有 ';'。"),
+    ("Expect loop variable name.", "此处应为循环变量名。"),
+    ("Expect 'in' after loop variable.", "循环变量之后应有 'in'。"),
+    ("Expect '{' after for clause.", "for 子句之后应有 '{'。"),
+    ("Can't use '{}' outside of a loop.", "不能在循环之外使用 '{}'。"),
+    ("Expect ';' after '{}'.", "'{}' 之后应有 ';'。"),
+    ("Expect ':' after loop label.", "循环标签之后应有 ':'。"),
+    ("Expect 'while' or 'for' after loop label.", "循环标签之后应为 'while' 或 'for'。"),
+    ("Label {} is already in use by an enclosing loop.", "外层循环已使用标签 {}。"),
+    ("Unknown loop label {}.", "未知的循环标签 {}。"),
+
+    // vm
+    ("[line {}] runtime error: {}", "[第 {} 行] 运行时错误：{}"),
+    ("Nothing to run.", "没有可执行的代码。"),
+    ("Invalid instruction.", "无效的指令。"),
+    ("Invalid jump.", "无效的跳转。"),
+    ("invalid bytecode: {}", "无效的字节码：{}"),
+    ("Stack overflow.", "栈溢出。"),
+    ("Stack underflow.", "栈下溢。"),
+    ("Operand must be a number.", "操作数必须是数字。"),
+    ("Operands must be numbers.", "操作数必须都是数字。"),
+    ("Division by zero.", "除数为零。"),
+    ("Uncaught exception: {}", "未捕获的异常：{}"),
+    ("Range bounds must be numbers.", "区间的上下界必须是数字。"),
+    ("Can't iterate over {}.", "不能迭代 {}。"),
+    ("iter() must return an object with a next method.", "iter() 必须返回带有 next 方法的对象。"),
+    ("Undefined variable '{}'.", "未定义的变量 '{}'。"),
+    ("Can only call functions.", "只能调用函数。"),
+    ("Undefined property '{}' on {}.", "属性 '{}' 在 {} 上未定义。"),
+    ("Can't find module '{}'.", "找不到模块 '{}'。"),
+    ("Circular import: {}.", "循环导入：{}。"),
+    ("Can't compile module '{}': {}", "无法编译模块 '{}'：{}"),
+    ("Error in module '{}' at line {}: {}", "模块 '{}' 第 {} 行出错：{}"),
+    ("Module '{}' exports undefined name '{}'.", "模块 '{}' 导出了未定义的名字 '{}'。"),
+    ("{} expects {} arguments but got {}.", "{} 需要 {} 个参数，实际为 {} 个。"),
+    ("{} expects argument {} to be {} but got {}.", "{} 的第 {} 个参数应为 {}，实际为 {}。"),
+    ("{} expects an integer but got {}.", "{} 需要整数，实际为 {}。"),
+    ("{} expects a code between {} and {} but got {}.", "{} 需要 {} 到 {} 之间的退出码，实际为 {}。"),
+    ("{} expects at least {} arguments but got {}.", "{} 至少需要 {} 个参数，实际为 {} 个。"),
+    ("{} got an empty range {}..={}.", "{} 收到空区间 {}..={}。"),
+    ("{} expects {} to {} arguments but got {}.", "{} 需要 {} 到 {} 个参数，实际为 {} 个。"),
+    ("{} index {} is out of range for length {}.", "{} 的下标 {} 超出长度 {} 的范围。"),
+    ("{} expects a non-negative count but got {}.", "{} 需要非负的次数，实际为 {}。"),
+    ("{} expects a non-zero step but got {}.", "{} 需要非零的步长，实际为 {}。"),
+    ("{} would produce a string longer than {} bytes.", "{} 生成的字符串将超过 {} 字节。"),
+    ("{} got a duration that is too long: {}.", "{} 的时长过长：{}。"),
+    ("{} expects an indent of at most {} but got {}.", "{} 的缩进最多为 {}，实际为 {}。"),
+    ("{} expects a timestamp between {} and {} but got {}.", "{} 需要介于 {} 与 {} 之间的时间戳，实际为 {}。"),
+    ("{} is not allowed in this sandbox.", "沙箱中不允许使用 {}。"),
+    ("{} failed on '{}': {}", "{} 处理 '{}' 时失败：{}"),
+    ("{} failed: {}", "{} 失败：{}"),
+    ("json.parse: {} at line {}, column {}.", "json.parse：{}，位于第 {} 行第 {} 列。"),
+    ("unexpected character '{}'", "出现意外的字符 '{}'"),
+    ("unexpected end of input", "输入意外结束"),
+    ("too deeply nested", "嵌套层数过多"),
+    ("invalid escape sequence", "无效的转义序列"),
+    ("control character in string", "字符串中含有控制字符"),
+    ("invalid number", "无效的数字"),
+    ("json.stringify found a list or map that contains itself.", "json.stringify 发现列表或映射包含其自身。"),
+    ("json.stringify can't encode non-finite number {}.", "json.stringify 不能编码非有限的数字 {}。"),
+    ("json.stringify can't encode a {}.", "json.stringify 不能编码 {}。"),
+    ("{} got an invalid format '{}'.", "{} 收到无效的格式 '{}'。"),
+    ("time.parse: '{}' does not match format '{}'.", "time.parse：'{}' 与格式 '{}' 不符。"),
+    ("invalid regex '{}': {} at position {}.", "无效的正则表达式 '{}'：{}，位于第 {} 个字符。"),
+    ("invalid regex '{}': {}", "无效的正则表达式 '{}'：{}"),
+
+    // convert
+    ("expected {} but got {}.", "应为 {}，实际为 {}。"),
+    ("a list or map contains itself and can't be serialized.", "列表或映射包含自身，无法序列化。"),
+    ("{} can't be serialized.", "{} 无法序列化。"),
+
+    // interpreter
+    ("script exited with code {}.", "脚本以状态码 {} 退出。"),
+];
+
+// 按当前语言翻译，目录中没有的消息原样返回
+pub fn tr(msgid: &'static str) -> &'static str {
+    match locale() {
+        Locale::En => msgid,
+        Locale::Zh => match CATALOG.iter().find(|(en, _)| *en == msgid) {
+            Some((_, zh)) => zh,
+            None => msgid,
+        },
+    }
+}
+
+// 翻译后依次以参数替换 "{}"
+pub fn trf(msgid: &'static str, args: &[&dyn std::fmt::Display]) -> String {
+    let mut parts = tr(msgid).split("{}");
+    let mut ret = String::from(parts.next().unwrap_or(""));
+    let mut args = args.iter();
+    for part in parts {
+        if let Some(a) = args.next() {
+            ret.push_str(&a.to_string());
+        }
+        ret.push_str(part);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tr_test() {
+        set_locale(Locale::En);
+        assert_eq!(tr("Expect expression."), "Expect expression.");
+        assert_eq!(trf("'{}' is not supported yet.", &[&"fn"]), "'fn' is not supported yet.");
+
+        set_locale(Locale::Zh);
+        assert_eq!(tr("Expect expression."), "此处应为表达式。");
+        assert_eq!(trf("'{}' is not supported yet.", &[&"fn"]), "暂不支持 'fn'。");
+        assert_eq!(tr("not in catalog"), "not in catalog");
+        set_locale(Locale::En);
+    }
+
+    #[test]
+    fn from_name_test() {
+        assert_eq!(Locale::from_name("zh_CN.UTF-8"), Some(Locale::Zh));
+        assert_eq!(Locale::from_name("中文"), Some(Locale::Zh));
+        assert_eq!(Locale::from_name("C"), None);
+    }
+}
@@ -0,0 +1,77 @@
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+use unicode_script::{Script, UnicodeScript};
+use unicode_xid::UnicodeXID;
+
+// 标识符规则遵循 Unicode UAX #31：首字符为 XID_Start 或 '_'，其余字符为 XID_Continue
+pub fn is_start(c: char) -> bool {
+    c == '_' || c.is_xid_start()
+}
+
+pub fn is_continue(c: char) -> bool {
+    c.is_xid_continue()
+}
+
+// NFC 规范化，使视觉上相同的标识符比较相等
+pub fn normalize(name: &str) -> Cow<'_, str> {
+    match is_nfc_quick(name.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(name),
+        _ => Cow::Owned(name.nfc().collect()),
+    }
+}
+
+// UTS #39 中 highly restrictive 级别允许的书写系统组合
+const ALLOWED_SCRIPTS: [&[Script]; 3] = [
+    &[Script::Latin, Script::Han, Script::Hiragana, Script::Katakana],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+    &[Script::Latin, Script::Han, Script::Hangul],
+];
+
+// 标识符混用了容易混淆的书写系统时，返回其中出现的书写系统
+pub fn mixed_scripts(name: &str) -> Option<Vec<Script>> {
+    let mut scripts: Vec<Script> = Vec::new();
+    for c in name.chars() {
+        let s = c.script();
+        if s == Script::Common || s == Script::Inherited || scripts.contains(&s) {
+            continue;
+        }
+        scripts.push(s);
+    }
+
+    if scripts.len() <= 1 || ALLOWED_SCRIPTS.iter().any(|set| scripts.iter().all(|s| set.contains(s))) {
+        None
+    }else {
+        Some(scripts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn xid_test() {
+        assert!(is_start('é'));
+        assert!(is_start('か'));
+        assert!(is_start('\u{20000}')); // CJK 扩展 B
+        assert!(is_start('_'));
+        assert!(!is_start('1'));
+        assert!(is_continue('1'));
+        assert!(is_continue('\u{0301}'));
+        assert!(!is_continue('-'));
+    }
+
+    #[test]
+    fn normalize_test() {
+        assert_eq!(normalize("cafe\u{0301}"), normalize("caf\u{00e9}"));
+        assert_eq!(normalize("变量"), "变量");
+    }
+
+    #[test]
+    fn mixed_scripts_test() {
+        assert_eq!(mixed_scripts("value"), None);
+        assert_eq!(mixed_scripts("x坐标"), None);
+        assert_eq!(mixed_scripts("ひらがな漢字カタカナ"), None);
+        assert_eq!(mixed_scripts("p\u{0430}y"), Some(vec![Script::Latin, Script::Cyrillic]));
+    }
+}
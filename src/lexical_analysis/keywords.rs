@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use super::token_type::*;
+
+// 可替换的关键字集，可按文件（首行 `// tlang: keywords=zh`）或按 REPL 会话选择
+#[derive(Debug, Clone)]
+pub struct KeywordSet {
+    name: &'static str,
+    words: HashMap<String, TokenType>,
+}
+
+impl KeywordSet {
+    pub fn english() -> Self {
+        Self::from_words("en", &[
+            ("let", TokenType::LET),
+            ("fn", TokenType::FN),
+            ("class", TokenType::CLASS),
+            ("false", TokenType::FALSE),
+            ("true", TokenType::TRUE),
+            ("this", TokenType::THIS),
+            ("else", TokenType::ELSE),
+            ("if", TokenType::IF),
+            ("for", TokenType::FOR),
+            ("while", TokenType::WHILE),
+            ("null", TokenType::NULL),
+            ("print", TokenType::PRINT),
+            ("return", TokenType::RETURN),
+            ("super", TokenType::SUPER),
+            ("import", TokenType::IMPORT),
+            ("export", TokenType::EXPORT),
+            ("as", TokenType::AS),
+            ("try", TokenType::TRY),
+            ("catch", TokenType::CATCH),
+            ("finally", TokenType::FINALLY),
+            ("throw", TokenType::THROW),
+            ("in", TokenType::IN),
+            ("break", TokenType::BREAK),
+            ("continue", TokenType::CONTINUE),
+        ])
+    }
+
+    pub fn chinese() -> Self {
+        Self::from_words("zh", &[
+            ("令", TokenType::LET),
+            ("函数", TokenType::FN),
+            ("类", TokenType::CLASS),
+            ("假", TokenType::FALSE),
+            ("真", TokenType::TRUE),
+            ("此", TokenType::THIS),
+            ("否则", TokenType::ELSE),
+            ("如果", TokenType::IF),
+            ("对于", TokenType::FOR),
+            ("当", TokenType::WHILE),
+            ("空", TokenType::NULL),
+            ("打印", TokenType::PRINT),
+            ("返回", TokenType::RETURN),
+            ("超类", TokenType::SUPER),
+            ("导入", TokenType::IMPORT),
+            ("导出", TokenType::EXPORT),
+            ("作为", TokenType::AS),
+            ("尝试", TokenType::TRY),
+            ("捕获", TokenType::CATCH),
+            ("最终", TokenType::FINALLY),
+            ("抛出", TokenType::THROW),
+            ("在", TokenType::IN),
+            ("跳出", TokenType::BREAK),
+            ("继续", TokenType::CONTINUE),
+        ])
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "en" | "english" => Some(Self::english()),
+            "zh" | "chinese" | "中文" => Some(Self::chinese()),
+            _ => None,
+        }
+    }
+
+    // 读取源码首行的 `// tlang: keywords=<name>` 声明
+    pub fn from_pragma(code: &str) -> Option<Self> {
+        let first = code.lines().next()?.trim();
+        let pragma = first.strip_prefix("//")?.trim().strip_prefix("tlang:")?.trim();
+        Self::from_name(pragma.strip_prefix("keywords=")?.trim())
+    }
+
+    fn from_words(name: &'static str, words: &[(&str, TokenType)]) -> Self {
+        Self {
+            name,
+            words: words.iter().map(|(w, t)| (w.to_string(), *t)).collect(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn insert(&mut self, word: &str, t: TokenType) {
+        self.words.insert(word.to_string(), t);
+    }
+
+    pub fn get(&self, word: &str) -> Option<TokenType> {
+        self.words.get(word).copied()
+    }
+}
+
+impl Default for KeywordSet {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pragma_test() {
+        assert_eq!(KeywordSet::from_pragma("// tlang: keywords=zh\n令 a = 1;").unwrap().name(), "zh");
+        assert_eq!(KeywordSet::from_pragma("//tlang:keywords=en").unwrap().name(), "en");
+        assert!(KeywordSet::from_pragma("let a = 1;\n// tlang: keywords=zh").is_none());
+        assert!(KeywordSet::from_pragma("// tlang: keywords=fr").is_none());
+    }
+
+    #[test]
+    fn get_test() {
+        assert_eq!(KeywordSet::chinese().get("如果"), Some(TokenType::IF));
+        assert_eq!(KeywordSet::chinese().get("if"), None);
+        assert_eq!(KeywordSet::english().get("while"), Some(TokenType::WHILE));
+        assert_eq!(KeywordSet::english().get("continue"), Some(TokenType::CONTINUE));
+        assert_eq!(KeywordSet::chinese().get("跳出"), Some(TokenType::BREAK));
+    }
+}
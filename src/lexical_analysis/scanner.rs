@@ -1,126 +1,132 @@
-use std::collections::HashMap;
-use super::{token::*, token_type::*};
-use crate::chunk::value::*;
+use super::{identifier, keywords::*, token::*, token_type::*};
+use crate::{chunk::value::*, compiler::compile_error::CompileError, interpreter_error::catalog::*};
 
-pub struct Scanner {
-    code: Vec<char>,
-    token_list: Vec<Token>,
+// 按需产生词素的扫描器，以字节偏移直接在源码上扫描
+pub struct Scanner<'a> {
+    code: &'a str,
 
-    start: usize, // 被扫描词素的第一个字符
-    current: usize, // 被处理的当前字符
+    start: usize, // 被扫描词素的第一个字节
+    current: usize, // 被处理的当前字节
     line: u32, // 被扫描词素所在行
-    keywords: HashMap<Vec<char>, TokenType>,
+    keywords: KeywordSet,
+    comments: bool, // 是否以 COMMENT 词素保留注释
 
     error: u32,
+    warnings: Vec<CompileError>,
+    over: bool, // 已产生 EOF 词素
 }
 
-impl Scanner {
-    pub fn new(code: String) -> Self {
-        let mut keywords: HashMap<Vec<char>, TokenType> = HashMap::new();
-        keywords.insert(vec!['l', 'e', 't'], TokenType::LET);
-        keywords.insert(vec!['f', 'n'], TokenType::FN);
-        keywords.insert(vec!['c', 'a', 'l', 's', 's'], TokenType::CLASS);
-        keywords.insert(vec!['f', 'a', 'l', 's', 'e'], TokenType::FALSE);
-        keywords.insert(vec!['t', 'r', 'u', 'e'], TokenType::TRUE);
-        keywords.insert(vec!['t', 'h', 'i', 's'], TokenType::THIS);
-        keywords.insert(vec!['e', 'l', 's', 'e'], TokenType::ELSE);
-        keywords.insert(vec!['i', 'f'], TokenType::IF);
-        keywords.insert(vec!['f', 'o', 'r'], TokenType::FOR);
-        keywords.insert(vec!['w', 'h', 'i', 'l', 'e'], TokenType::WHILE);
-        keywords.insert(vec!['n', 'u', 'l', 'l'], TokenType::NULL);
-        keywords.insert(vec!['p', 'r', 'i', 'n', 't'], TokenType::PRINT);
-        keywords.insert(vec!['r', 'e', 't', 'u', 'r', 'n'], TokenType::RETURN);
-        keywords.insert(vec!['s', 'u', 'p', 'e', 'r'], TokenType::SUPER);
+impl<'a> Scanner<'a> {
+    // 首行有关键字集声明时使用声明的集合，否则使用英文关键字
+    pub fn new(code: &'a str) -> Self {
+        let keywords = KeywordSet::from_pragma(code).unwrap_or_default();
+        Self::with_keywords(code, keywords)
+    }
 
+    pub fn with_keywords(code: &'a str, keywords: KeywordSet) -> Self {
         Self {
-            code: code.chars().collect(),
-            token_list: Vec::new(),
+            code,
             start: 0,
             current: 0,
             line: 1,
             error: 0,
+            warnings: Vec::new(),
+            over: false,
             keywords,
+            comments: false,
         }
     }
 
-    pub fn scann(&mut self) -> Result<&Vec<Token>, u32> {
-        while !self.is_end() {
-            // scanning token
-            self.start = self.current;
-            match self.get_token() {
-                None => {},
-                Some(val) => {
-                    self.token_list.push(val);
-                }
-            }
-        }
-        
-        // the end of code
-        self.token_list.push(Token::new(vec!['E', 'O', 'F'], TokenType::EOF, None, self.line));
+    // 供格式化等需要保留注释的工具使用
+    pub fn set_comments(&mut self, keep: bool) -> &mut Self {
+        self.comments = keep;
+        self
+    }
+
+    pub fn scann(&mut self) -> Result<Vec<Token<'a>>, u32> {
+        let token_list: Vec<Token<'a>> = self.by_ref().collect();
 
         if self.error != 0 {
             Err(self.error)
         }else {
-            Ok(&(self.token_list))
+            Ok(token_list)
         }
     }
 
-    fn get_token(&mut self) -> Option<Token> {
+    pub fn warnings(&self) -> &[CompileError] {
+        &self.warnings
+    }
+
+    // 扫描全部词素，词法错误以 ERROR 词素的形式保留在结果中
+    pub fn into_tokens(self) -> Vec<Token<'a>> {
+        self.collect()
+    }
+
+    fn get_token(&mut self) -> Option<Token<'a>> {
         let c = self.advance();
         match c {
             // 单字符匹配
-            '(' => Some(Token::new([c].to_vec(), TokenType::LEFT_PAREN, None, self.line)),
-            ')' => Some(Token::new([c].to_vec(), TokenType::RIGHT_PAREN, None, self.line)),
-            '{' => Some(Token::new([c].to_vec(), TokenType::LEFT_BRACE, None, self.line)),
-            '}' => Some(Token::new([c].to_vec(), TokenType::RIGHT_BRACE, None, self.line)),
-            '.' => Some(Token::new([c].to_vec(), TokenType::DOT, None, self.line)),
-            ',' => Some(Token::new([c].to_vec(), TokenType::COMMA, None, self.line)),
-            '+' => Some(Token::new([c].to_vec(), TokenType::PLUS, None, self.line)),
-            '*' => Some(Token::new([c].to_vec(), TokenType::STAR, None, self.line)),
-            '-' => Some(Token::new([c].to_vec(), TokenType::MINUS, None, self.line)),
-            ';' => Some(Token::new([c].to_vec(), TokenType::SEMICOLON, None, self.line)),
+            '(' => Some(Token::new(self.lexeme(), TokenType::LEFT_PAREN, None, self.line)),
+            ')' => Some(Token::new(self.lexeme(), TokenType::RIGHT_PAREN, None, self.line)),
+            '{' => Some(Token::new(self.lexeme(), TokenType::LEFT_BRACE, None, self.line)),
+            '}' => Some(Token::new(self.lexeme(), TokenType::RIGHT_BRACE, None, self.line)),
+            ',' => Some(Token::new(self.lexeme(), TokenType::COMMA, None, self.line)),
+            '+' => Some(Token::new(self.lexeme(), TokenType::PLUS, None, self.line)),
+            '*' => Some(Token::new(self.lexeme(), TokenType::STAR, None, self.line)),
+            '-' => Some(Token::new(self.lexeme(), TokenType::MINUS, None, self.line)),
+            ';' => Some(Token::new(self.lexeme(), TokenType::SEMICOLON, None, self.line)),
+            ':' => Some(Token::new(self.lexeme(), TokenType::COLON, None, self.line)),
 
             // 单字符或双字符匹配
             '!' => {
                 if self.match_next('=') {
-                    Some(Token::new([c].to_vec(), TokenType::BANG_EQUAL, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::BANG_EQUAL, None, self.line))
                 }else {
-                    Some(Token::new([c].to_vec(), TokenType::BANG, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::BANG, None, self.line))
                 }
             },
             '<' => {
                 if self.match_next('=') {
-                    Some(Token::new([c].to_vec(), TokenType::LESS_EQUAL, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::LESS_EQUAL, None, self.line))
                 }else {
-                    Some(Token::new([c].to_vec(), TokenType::LESS, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::LESS, None, self.line))
                 }
             },
             '>' => {
                 if self.match_next('=') {
-                    Some(Token::new([c].to_vec(), TokenType::GREATER_EQUAL, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::GREATER_EQUAL, None, self.line))
                 }else {
-                    Some(Token::new([c].to_vec(), TokenType::GREATER, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::GREATER, None, self.line))
                 }
             },
             '=' => {
                 if self.match_next('=') {
-                    Some(Token::new([c].to_vec(), TokenType::EQUAL_EQUAL, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::EQUAL_EQUAL, None, self.line))
                 }else {
-                    Some(Token::new([c].to_vec(), TokenType::EQUAL, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::EQUAL, None, self.line))
                 }
             },
             '&' => {
                 if self.match_next('&') {
-                    Some(Token::new([c].to_vec(), TokenType::AND_AND, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::AND_AND, None, self.line))
+                }else {
+                    Some(Token::new(self.lexeme(), TokenType::AND, None, self.line))
+                }
+            },
+            // . .. ..=
+            '.' => {
+                if self.match_next('.') {
+                    let t = if self.match_next('=') { TokenType::DOT_DOT_EQUAL }else { TokenType::DOT_DOT };
+                    Some(Token::new(self.lexeme(), t, None, self.line))
                 }else {
-                    Some(Token::new([c].to_vec(), TokenType::AND, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::DOT, None, self.line))
                 }
             },
             '|' => {
                 if self.match_next('|') {
-                    Some(Token::new([c].to_vec(), TokenType::OR_OR, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::OR_OR, None, self.line))
                 }else {
-                    Some(Token::new([c].to_vec(), TokenType::OR, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::OR, None, self.line))
                 }
             },
 
@@ -130,11 +136,13 @@ impl Scanner {
                     while self.peek() != '\n' && !self.is_end() {
                         self.advance();
                     }
-                    None
+                    self.comment(self.line)
                 }else if self.match_next('*') { // 多行注释
-                    self.multiline_comment()
+                    let line = self.line;
+                    self.multiline_comment();
+                    self.comment(line)
                 }else {
-                    Some(Token::new([c].to_vec(), TokenType::SLASH, None, self.line))
+                    Some(Token::new(self.lexeme(), TokenType::SLASH, None, self.line))
                 }
             },
 
@@ -153,21 +161,42 @@ impl Scanner {
                 self.get_string()
             },
 
+            // 循环标签，如 'outer
+            '\'' if Self::is_alpha(self.peek()) => {
+                while Self::is_digit_or_alpha(self.peek()) {
+                    self.advance();
+                }
+                Some(Token::new(identifier::normalize(self.lexeme()), TokenType::LABEL, None, self.line))
+            },
+
             _ => {
                 if Self::is_digit(c) {
                     self.get_digit()
                 }else if Self::is_alpha(c) {
                     self.identifier()
                 }else {
-                    self.error("unknown char!", self.line, &c.to_string())
+                    self.error(tr("unknown char!"), self.line, &c.to_string())
                 }
             },
         }
         
     }
 
-    fn multiline_comment(&mut self) -> Option<Token> {
-        while !(self.peek() == '*' && self.peek_next() == '/') && !self.is_end() {
+    // 当前词素在源码中的片段
+    fn lexeme(&self) -> &'a str {
+        &self.code[self.start..self.current]
+    }
+
+    fn comment(&self, line: u32) -> Option<Token<'a>> {
+        if self.comments {
+            Some(Token::new(self.lexeme(), TokenType::COMMENT, None, line))
+        }else {
+            None
+        }
+    }
+
+    fn multiline_comment(&mut self) {
+        while !(self.is_end() || self.peek() == '*' && self.peek_next() == '/') {
             if self.peek() == '\n' {
                 self.line += 1;
             }else if self.peek() == '/' && self.peek_next() == '*' {
@@ -183,33 +212,39 @@ impl Scanner {
         // 吸收 start 与 slash
         self.advance();
         self.advance();
-
-        None
     }
 
-    fn identifier(&mut self) -> Option<Token> {
+    fn identifier(&mut self) -> Option<Token<'a>> {
         while Self::is_digit_or_alpha(self.peek()) {
             self.advance();
         }
 
         // 获取标识符
-        let a = &self.code[self.start..self.current];
-        let a_type = self.keywords.get(a);
-        Some(Token::new(a.to_vec(),
-            match a_type { Some(&val) => val , None => TokenType::IDENTIFIER, },
+        let a = identifier::normalize(self.lexeme());
+        if let Some(scripts) = identifier::mixed_scripts(&a) {
+            let names: Vec<&str> = scripts.iter().map(|s| s.full_name()).collect();
+            self.warnings.push(CompileError {
+                line: self.line,
+                at: format!("'{}'", a),
+                massege: trf("identifier mixes scripts {} and may be confusable.", &[&names.join(", ")]),
+            });
+        }
+
+        let a_type = self.keywords.get(&a);
+        Some(Token::new(a,
+            match a_type { Some(val) => val , None => TokenType::IDENTIFIER, },
             None, self.line))
     }
 
     fn is_digit_or_alpha(c: char) -> bool {
-        Self::is_alpha(c) || Self::is_digit(c)
+        identifier::is_continue(c)
     }
 
     fn is_alpha(c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_' // 英文
-        || (c >= '\u{4e00}' && c <= '\u{9fa5}')
+        identifier::is_start(c)
     }
 
-    fn get_digit(&mut self) -> Option<Token> {
+    fn get_digit(&mut self) -> Option<Token<'a>> {
         while Self::is_digit(self.peek()) {
             self.advance();
         }
@@ -221,21 +256,17 @@ impl Scanner {
             }
         }
         
-        let val = &self.code[self.start..self.current];
-        let t: String = val.to_vec().iter().collect();
-        Some(Token::new(val.to_vec(), TokenType::DOUBLE, Some(Value::Double(t.parse::<f64>().unwrap())), self.line))
+        let val = self.lexeme();
+        Some(Token::new(val, TokenType::DOUBLE, Some(Value::Double(val.parse::<f64>().unwrap())), self.line))
     }
 
     fn peek_next(&self) -> char {
-        if self.is_end() || self.current+1 >= self.code.len() {
-            '\0'
-        }else {
-            // &self.code[self.current+1..self.current+2]
-            self.code[self.current + 1]
-        }
+        let mut chars = self.code[self.current..].chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
     }
 
-    fn get_string(&mut self) -> Option<Token> {
+    fn get_string(&mut self) -> Option<Token<'a>> {
         while self.peek() != '"' && !self.is_end() {
             if self.peek() == '\n' {
                 self.line += 1;
@@ -244,35 +275,31 @@ impl Scanner {
         }
 
         if self.is_end() {
-            return self.error("Unterminated string", self.line, "\"..");
+            return self.error(tr("Unterminated string"), self.line, "\"..");
         }
 
         self.advance(); // 读入结尾引号
 
         // 去除引号
-        let t = self.code[self.start+1..self.current-1].to_vec();
-        Some(Token::new(t.to_vec(), TokenType::STRING, Some(Value::String(t.iter().collect())), self.line))
+        let t = &self.code[self.start+1..self.current-1];
+        Some(Token::new(t, TokenType::STRING, Some(Value::String(t.to_string())), self.line))
     }
 
     fn peek(&self) -> char {
-        if self.is_end() {
-            '\0'
-        }else {
-            self.code[self.current]
-        }
+        self.code[self.current..].chars().next().unwrap_or('\0')
     }
 
-    fn error(&mut self, massege: &str, line:u32, val: &str) -> Option<Token> {
-        crate::interpreter_error::error(&format!("{}\n in line={}, char={}", massege, line, val));
+    // 错误信息作为 ERROR 词素的内容交给调用者报告
+    fn error(&mut self, massege: &str, line:u32, val: &str) -> Option<Token<'a>> {
         self.error += 1;
-        Some(Token::new(vec![], TokenType::ERROR, None, line))
+        Some(Token::new(trf("{} char={}", &[&massege, &val]), TokenType::ERROR, None, line))
     }
 
     fn match_next(&mut self, expected: char) -> bool {
         if self.is_end() {
             false
         }else if self.peek() == expected {
-            self.current += 1;
+            self.current += expected.len_utf8();
             true
         }else {
             false
@@ -283,8 +310,9 @@ impl Scanner {
         if self.is_end() {
             '\0'
         }else {
-            self.current += 1;
-            self.code[self.current - 1]
+            let c = self.peek();
+            self.current += c.len_utf8();
+            c
         }
     }
 
@@ -293,7 +321,29 @@ impl Scanner {
     }
 
     fn is_digit(c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
+    }
+}
+
+impl<'a> Iterator for Scanner<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        while !self.is_end() {
+            // scanning token
+            self.start = self.current;
+            if let Some(val) = self.get_token() {
+                return Some(val);
+            }
+        }
+
+        // the end of code
+        if self.over {
+            None
+        }else {
+            self.over = true;
+            Some(Token::new("EOF", TokenType::EOF, None, self.line))
+        }
     }
 }
 
@@ -303,21 +353,21 @@ mod test {
 
     #[test]
     fn advance_test() {
-        let mut a = Scanner::new(String::from("0123456789"));
+        let mut a = Scanner::new("0123456789");
         assert_eq!(a.advance(), '0');
         assert_eq!(a.advance(), '1');
     }
 
     #[test]
     fn peek_test() {
-        let mut a = Scanner::new("0123".to_string());
+        let mut a = Scanner::new("0123");
         a.advance(); // 0
         a.advance(); // 1
         assert_eq!(a.current, 2);
         assert_eq!(a.peek(), '2'); // 1
         assert_eq!(a.current, 2);
 
-        let mut a = Scanner::new("//23456\n01".to_string());
+        let mut a = Scanner::new("//23456\n01");
         a.get_token();
         a.get_token();
         assert_eq!(a.line, 2);
@@ -325,39 +375,116 @@ mod test {
 
     #[test]
     fn get_token_test() {
-        let mut a = Scanner::new("//00000".to_string());
+        let mut a = Scanner::new("//00000");
         assert_eq!(a.get_token(), None);
-        let mut a = Scanner::new("/000".to_string());
+        let mut a = Scanner::new("/000");
         assert_eq!(a.get_token().unwrap(), get_a_token(TokenType::SLASH));
     }
 
     #[test]
     fn get_string_test() {
-        let mut a = Scanner::new(r#""hello""#.to_string());
+        let mut a = Scanner::new(r#""hello""#);
         a.advance();
         let a = a.get_string();
         assert_eq!(a, Some(get_a_token(TokenType::STRING)));
         let a = a.unwrap();
-        assert_eq!(a.to_string(), "lexeme=['h', 'e', 'l', 'l', 'o'],type=String,line=1".to_string());
+        assert_eq!(a.to_string(), "lexeme=\"hello\",type=String,line=1".to_string());
     }
 
     #[test]
     fn is_digit_test() {
-        assert_eq!(Scanner::is_digit('\0'), false);
-        assert_eq!(Scanner::is_digit('0'), true);
-        assert_eq!(Scanner::is_digit('7'), true);
-        assert_eq!(Scanner::is_digit('a'), false);
+        assert!(!Scanner::is_digit('\0'));
+        assert!(Scanner::is_digit('0'));
+        assert!(Scanner::is_digit('7'));
+        assert!(!Scanner::is_digit('a'));
     }
 
     #[test]
     fn is_alpha_test() {
-        assert_eq!(Scanner::is_alpha('a'), true);
-        assert_eq!(Scanner::is_alpha('g'), true);
-        assert_eq!(Scanner::is_alpha('Z'), true);
-        assert_eq!(Scanner::is_alpha('0'), false);
+        assert!(Scanner::is_alpha('a'));
+        assert!(Scanner::is_alpha('g'));
+        assert!(Scanner::is_alpha('Z'));
+        assert!(!Scanner::is_alpha('0'));
+    }
+
+    #[test]
+    fn iterator_test() {
+        let mut a = Scanner::new("let 变量 = \"值\" != 1.5;");
+        let types: Vec<TokenType> = a.by_ref().map(|t| t.token_type()).collect();
+        assert_eq!(types, vec![TokenType::LET, TokenType::IDENTIFIER, TokenType::EQUAL, TokenType::STRING,
+            TokenType::BANG_EQUAL, TokenType::DOUBLE, TokenType::SEMICOLON, TokenType::EOF]);
+        assert_eq!(a.next(), None);
+
+        let t: Vec<Token> = Scanner::new("变量 != 值").collect();
+        assert_eq!(t[0].lexeme_string(), "变量");
+        assert_eq!(t[1].lexeme_string(), "!=");
+        assert_eq!(t[2].lexeme_string(), "值");
+    }
+
+    #[test]
+    fn range_test() {
+        let t: Vec<Token> = Scanner::new("for i in 0..10 .. 1.5..=a.b").collect();
+        let types: Vec<TokenType> = t.iter().map(|t| t.token_type()).collect();
+        assert_eq!(types, vec![TokenType::FOR, TokenType::IDENTIFIER, TokenType::IN, TokenType::DOUBLE, TokenType::DOT_DOT,
+            TokenType::DOUBLE, TokenType::DOT_DOT, TokenType::DOUBLE, TokenType::DOT_DOT_EQUAL, TokenType::IDENTIFIER,
+            TokenType::DOT, TokenType::IDENTIFIER, TokenType::EOF]);
+        assert_eq!(t[3].lexeme_string(), "0");
+        assert_eq!(t[7].lexeme_string(), "1.5");
+    }
+
+    #[test]
+    fn label_test() {
+        let t: Vec<Token> = Scanner::new("'outer: while x { break 'outer; } '外层 '").collect();
+        let types: Vec<TokenType> = t.iter().map(|t| t.token_type()).collect();
+        assert_eq!(types, vec![TokenType::LABEL, TokenType::COLON, TokenType::WHILE, TokenType::IDENTIFIER, TokenType::LEFT_BRACE,
+            TokenType::BREAK, TokenType::LABEL, TokenType::SEMICOLON, TokenType::RIGHT_BRACE, TokenType::LABEL, TokenType::ERROR, TokenType::EOF]);
+        assert_eq!(t[0].lexeme_string(), "'outer");
+        assert_eq!(t[9].lexeme_string(), "'外层");
+    }
+
+    #[test]
+    fn unicode_identifier_test() {
+        let t: Vec<Token> = Scanner::new("café ひらがな 𠀀x").collect();
+        assert_eq!(t.len(), 4);
+        assert!(t[..3].iter().all(|t| t.token_type() == TokenType::IDENTIFIER));
+
+        // 组合字符与预组合字符规范化后相同
+        let a: Vec<Token> = Scanner::new("cafe\u{0301}").collect();
+        assert_eq!(a[0].lexeme(), t[0].lexeme());
+    }
+
+    #[test]
+    fn confusable_warning_test() {
+        let mut a = Scanner::new("let p\u{0430}y = 1; let x坐标 = 2;");
+        a.by_ref().for_each(drop);
+        assert_eq!(a.warnings().len(), 1);
+        assert_eq!(a.warnings()[0].at, "'p\u{0430}y'");
+    }
+
+    #[test]
+    fn keyword_set_test() {
+        let types: Vec<TokenType> = Scanner::new("// tlang: keywords=zh\n令 如果 let").map(|t| t.token_type()).collect();
+        assert_eq!(types, vec![TokenType::LET, TokenType::IF, TokenType::IDENTIFIER, TokenType::EOF]);
+
+        let types: Vec<TokenType> = Scanner::with_keywords("当 打印 while", KeywordSet::chinese()).map(|t| t.token_type()).collect();
+        assert_eq!(types, vec![TokenType::WHILE, TokenType::PRINT, TokenType::IDENTIFIER, TokenType::EOF]);
+    }
+
+    #[test]
+    fn comment_test() {
+        let code = "// a\nlet /* b\n c */ x;";
+        assert_eq!(Scanner::new(code).count(), 4);
+
+        let mut a = Scanner::new(code);
+        a.set_comments(true);
+        let t: Vec<Token> = a.collect();
+        assert_eq!(t[0].token_type(), TokenType::COMMENT);
+        assert_eq!(t[0].lexeme_string(), "// a");
+        assert_eq!(t[2].lexeme_string(), "/* b\n c */");
+        assert_eq!((t[2].line(), t[3].line()), (2, 3));
     }
 
-    fn get_a_token<'a>(t: TokenType) -> Token {
-        Token::new(vec![], t, None, 0)
+    fn get_a_token(t: TokenType) -> Token<'static> {
+        Token::new("", t, None, 0)
     }
 }
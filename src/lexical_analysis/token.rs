@@ -1,33 +1,67 @@
+use std::{borrow::Cow, fmt::Display};
 use super::token_type::*;
 use crate::chunk::value::*;
 
 #[derive(Debug)]
-pub struct Token {
-    lexeme: Vec<char>, // 词素本身
+pub struct Token<'a> {
+    lexeme: Cow<'a, str>, // 词素本身，通常直接借用源码中的片段
     token_type: TokenType, // 类型
     val: Option<Value>, // 字面值
     line: u32, // 所在行
 }
 
-impl Token {
-    pub fn new(lexeme: Vec<char>, token_type: TokenType, val: Option<Value>, line: u32) -> Token {
+impl<'a> Token<'a> {
+    pub fn new(lexeme: impl Into<Cow<'a, str>>, token_type: TokenType, val: Option<Value>, line: u32) -> Token<'a> {
         Token {
-            lexeme,
+            lexeme: lexeme.into(),
             val,
             token_type,
             line,
         }
     }
 
-    pub fn to_string(&self) -> String {
-        format!("lexeme={:?},type={},line={}", self.lexeme, self.token_type, self.line)
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn lexeme_string(&self) -> String {
+        self.lexeme.to_string()
+    }
+
+    pub fn token_type(&self) -> TokenType {
+        self.token_type
+    }
+
+    pub fn val(&self) -> Option<&Value> {
+        self.val.as_ref()
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
     }
 }
 
-impl Eq for Token {}
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "lexeme={:?},type={},line={}", self.lexeme, self.token_type, self.line)
+    }
+}
 
-impl PartialEq for Token {
+impl Eq for Token<'_> {}
+
+impl PartialEq for Token<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.token_type == other.token_type
     }
 }
+
+impl Clone for Token<'_> {
+    fn clone(&self) -> Self {
+        Token {
+            lexeme: self.lexeme.clone(),
+            token_type: self.token_type,
+            val: self.val.clone(),
+            line: self.line,
+        }
+    }
+}
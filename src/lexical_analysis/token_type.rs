@@ -10,8 +10,8 @@ pub enum TokenType {
     LEFT_BRACE, RIGHT_BRACE,
     // , . - +
     COMMA, DOT, MINUS, PLUS,
-    // ; / *
-    SEMICOLON, SLASH, STAR,
+    // ; / * :
+    SEMICOLON, SLASH, STAR, COLON,
 
     // 单字符或双字符
     // ! !=
@@ -26,84 +26,112 @@ pub enum TokenType {
     AND, AND_AND,
     // | ||
     OR, OR_OR,
+    // .. ..=
+    DOT_DOT, DOT_DOT_EQUAL,
 
     // 文字
-    // 标识符 字符串 双精度浮点
-    IDENTIFIER, STRING, DOUBLE,
+    // 标识符 字符串 双精度浮点 循环标签
+    IDENTIFIER, STRING, DOUBLE, LABEL,
 
     // 关键字
     CLASS, ELSE, FALSE, FN, FOR, IF, NULL, PRINT, RETURN, SUPER, THIS, TRUE, LET, WHILE,
+    IMPORT, EXPORT, AS,
+    TRY, CATCH, FINALLY, THROW,
+    IN, BREAK, CONTINUE,
+
+    // 注释，仅在扫描器保留注释时产生
+    COMMENT,
 
     EOF, ERROR,
 }
 
 impl TokenType {
-    pub fn to_string(&self) -> String {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Self::LET => String::from("let"),
-            Self::WHILE => String::from("while"),
+            Self::LET => "let",
+            Self::WHILE => "while",
+
+            Self::THIS => "this",
+            Self::TRUE => "true",
+
+            Self::RETURN => "return",
+            Self::SUPER => "super",
+
+            Self::NULL => "null",
+            Self::PRINT => "print",
+
+            Self::FALSE => "false",
+            Self::FN => "fn",
+
+            Self::FOR => "for",
+            Self::IF => "if",
 
-            Self::THIS => String::from("this"),
-            Self::TRUE => String::from("true"),
+            Self::CLASS => "class",
+            Self::ELSE => "else",
 
-            Self::RETURN => String::from("return"),
-            Self::SUPER => String::from("super"),
+            Self::IMPORT => "import",
+            Self::EXPORT => "export",
+            Self::AS => "as",
 
-            Self::NULL => String::from("null"),
-            Self::PRINT => String::from("print"),
+            Self::TRY => "try",
+            Self::CATCH => "catch",
+            Self::FINALLY => "finally",
+            Self::THROW => "throw",
 
-            Self::FALSE => String::from("false"),
-            Self::FN => String::from("fn"),
+            Self::IN => "in",
+            Self::BREAK => "break",
+            Self::CONTINUE => "continue",
 
-            Self::FOR => String::from("for"),
-            Self::IF => String::from("if"),
+            Self::IDENTIFIER => "identifier",
+            Self::STRING => "String",
+            Self::DOUBLE => "double",
+            Self::LABEL => "label",
 
-            Self::CLASS => String::from("class"),
-            Self::ELSE => String::from("else"),
+            Self::AND => "&",
+            Self::OR => "|",
 
-            Self::IDENTIFIER => String::from("identifier"),
-            Self::STRING => String::from("String"),
-            Self::DOUBLE => String::from("double"),
+            Self::LESS => "<",
+            Self::LESS_EQUAL => "<=",
 
-            Self::AND => String::from("&"),
-            Self::OR => String::from("|"),
+            Self::EQUAL => "=",
+            Self::EQUAL_EQUAL => "==",
 
-            Self::LESS => String::from("<"),
-            Self::LESS_EQUAL => String::from("<="),
+            Self::GREATER => ">",
+            Self::GREATER_EQUAL => ">=",
 
-            Self::EQUAL => String::from("="),
-            Self::EQUAL_EQUAL => String::from("=="),
+            Self::BANG => "!",
+            Self::BANG_EQUAL => "!=",
 
-            Self::GREATER => String::from(">"),
-            Self::GREATER_EQUAL => String::from(">="),
+            Self::SEMICOLON => ";",
+            Self::SLASH => "/",
+            Self::STAR => "*",
+            Self::COLON => ":",
 
-            Self::BANG => String::from("!"),
-            Self::BANG_EQUAL => String::from("!="),
+            Self::LEFT_PAREN => "(",
+            Self::RIGHT_PAREN => ")",
 
-            Self::SEMICOLON => String::from(";"),
-            Self::SLASH => String::from("/"),
-            Self::STAR => String::from("*"),
+            Self::LEFT_BRACE => "{",
+            Self::RIGHT_BRACE => "}",
 
-            Self::LEFT_PAREN => String::from("("),
-            Self::RIGHT_PAREN => String::from(")"),
+            Self::COMMA => ",",
+            Self::DOT => ".",
+            Self::MINUS => "-",
+            Self::PLUS => "+",
 
-            Self::LEFT_BRACE => String::from("{"),
-            Self::RIGHT_BRACE => String::from("}"),
+            Self::AND_AND => "&&",
+            Self::OR_OR => "||",
 
-            Self::COMMA => String::from(","),
-            Self::DOT => String::from("."),
-            Self::MINUS => String::from("-"),
-            Self::PLUS => String::from("+"),
+            Self::DOT_DOT => "..",
+            Self::DOT_DOT_EQUAL => "..=",
 
-            Self::AND_AND => String::from("&&"),
-            Self::OR_OR => String::from("||"),
+            Self::COMMENT => "comment",
 
-            Self::EOF => String::from("$EOF$"),
-            Self::ERROR => String::from("$ERROR$"),
+            Self::EOF => "$EOF$",
+            Self::ERROR => "$ERROR$",
         }
     }
 
-    fn to_u32(&self) -> u32 {
+    fn to_u32(self) -> u32 {
         match self {
             Self::LET => 0,
             Self::WHILE => 1,
@@ -165,13 +193,34 @@ impl TokenType {
 
             Self::EOF => 40,
             Self::ERROR => 41,
+
+            Self::COMMENT => 42,
+
+            Self::IMPORT => 43,
+            Self::EXPORT => 44,
+            Self::AS => 45,
+
+            Self::TRY => 46,
+            Self::CATCH => 47,
+            Self::FINALLY => 48,
+            Self::THROW => 49,
+
+            Self::DOT_DOT => 50,
+            Self::DOT_DOT_EQUAL => 51,
+
+            Self::IN => 52,
+            Self::BREAK => 53,
+            Self::CONTINUE => 54,
+
+            Self::COLON => 55,
+            Self::LABEL => 56,
         }
     }
 }
 
 impl Display for TokenType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        write!(f, "{}", self.as_str())
     }
 }
 
@@ -1,16 +1,16 @@
+mod cli;
+mod repl;
+
 use std::env;
-use tlang::interpreter_error;
+use tlang::interpreter_error::catalog::*;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() > 2 {
-        interpreter_error::error_exit("Usage: jlox [script]", 64);
-    }else if args.len() < 2 {
-        // todo run with tshell
-        tlang::tshlle::tshlle();
-    }else {
-        // todo run with tlang
-        tlang::tlang::run(&args[1]);
+    // 诊断信息的语言取自 TLANG_LANG，其次为 LANG
+    if let Some(l) = env::var("TLANG_LANG").or_else(|_| env::var("LANG")).ok().and_then(|v| Locale::from_name(&v)) {
+        set_locale(l);
     }
+
+    std::process::exit(cli::main(&args[1..]));
 }
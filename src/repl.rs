@@ -0,0 +1,59 @@
+use std::io::{self, Write};
+use tlang::{chunk::value::Value, interpreter_error::catalog::*, lexical_analysis::keywords::KeywordSet, vm::{interpret_result::InterpretResult, vm::VM}};
+use crate::cli::{error, warning};
+
+// 交互式解释器，使用调用者配置好的 VM，如开启调试输出或限制栈大小；返回脚本请求的退出码。
+// 以 `:keywords zh` 切换本次会话的关键字集，以 `:lang zh` 切换诊断信息的语言
+pub fn tshlle(mut vm: VM) -> i32 {
+    println!("{}", tr("wellcome!this is tshell!"));
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            Ok(val) => {
+                if val == 0 {
+                    return 0;
+                }
+                let line = input.trim();
+                if let Some(name) = line.strip_prefix(":keywords") {
+                    match KeywordSet::from_name(name.trim()) {
+                        Some(k) => { vm.set_keywords(k); },
+                        None => error(&trf("unknown keyword set ' {} '!", &[&name.trim()])),
+                    }
+                    continue;
+                }
+                if let Some(name) = line.strip_prefix(":lang") {
+                    match Locale::from_name(name.trim()) {
+                        Some(l) => set_locale(l),
+                        None => error(&trf("unknown language ' {} '!", &[&name.trim()])),
+                    }
+                    continue;
+                }
+
+                let result = vm.interpret(&input);
+                for w in vm.warnings() {
+                    warning(&w.to_string());
+                }
+                match result {
+                    // 输入以表达式结尾时显示它的值
+                    InterpretResult::Ok => match vm.take_result() {
+                        Value::Null => {},
+                        v => println!("{}", v),
+                    },
+                    InterpretResult::COMPLE_ERROR(errors) => {
+                        for e in errors {
+                            error(&e.to_string());
+                        }
+                    },
+                    InterpretResult::RUNTIME_ERROR(e) => error(&e.to_string()),
+                    InterpretResult::EXIT(code) => return code,
+                }
+            }
+            Err(_) => {
+                error(tr("input error!"));
+                return 1;
+            }
+        }
+    }
+}
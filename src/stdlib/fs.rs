@@ -0,0 +1,133 @@
+use std::{fs, io::Write, path::Path};
+use crate::{chunk::{convert::IntoValue, value::*}, vm::{builtins::*, runtime_error::RuntimeError, vm::VM}};
+
+// 文件系统操作，失败时报告操作系统给出的错误信息
+pub fn define(vm: &mut VM) {
+    let mut m = Module::new("fs");
+    m.native("read", 1, read)
+        .native("lines", 1, lines)
+        .native("write", 2, write)
+        .native("append", 2, append)
+        .native("exists", 1, exists)
+        .native("remove", 1, remove)
+        .native("mkdir", 1, mkdir)
+        .native("list_dir", 1, list_dir);
+    m.define(vm);
+}
+
+// 检查沙箱并取路径参数
+fn path(vm: &VM, name: &str, v: &Value) -> Result<String, RuntimeError> {
+    require_io(vm, name)?;
+    param(name, 1, v)
+}
+
+// 整个文件的内容
+fn read(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let p = path(vm, "fs.read", &args[0])?;
+    fs::read_to_string(&p).map(Value::String).map_err(|e| os_error("fs.read", &p, e))
+}
+
+// 按行分割，不含行尾的换行符
+fn lines(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let p = path(vm, "fs.lines", &args[0])?;
+    let content = fs::read_to_string(&p).map_err(|e| os_error("fs.lines", &p, e))?;
+    Ok(content.lines().collect::<Vec<_>>().into_value())
+}
+
+// 覆盖写入，文件不存在时创建
+fn write(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let p = path(vm, "fs.write", &args[0])?;
+    let content: String = param("fs.write", 2, &args[1])?;
+    fs::write(&p, content).map_err(|e| os_error("fs.write", &p, e))?;
+    Ok(Value::Null)
+}
+
+// 追加到末尾，文件不存在时创建
+fn append(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let p = path(vm, "fs.append", &args[0])?;
+    let content: String = param("fs.append", 2, &args[1])?;
+    fs::OpenOptions::new().append(true).create(true).open(&p)
+        .and_then(|mut f| f.write_all(content.as_bytes()))
+        .map_err(|e| os_error("fs.append", &p, e))?;
+    Ok(Value::Null)
+}
+
+fn exists(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let p = path(vm, "fs.exists", &args[0])?;
+    Ok(Value::Bool(Path::new(&p).exists()))
+}
+
+// 删除文件或空目录
+fn remove(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let p = path(vm, "fs.remove", &args[0])?;
+    let ret = if Path::new(&p).is_dir() { fs::remove_dir(&p) }else { fs::remove_file(&p) };
+    ret.map_err(|e| os_error("fs.remove", &p, e))?;
+    Ok(Value::Null)
+}
+
+// 连同不存在的上级目录一起创建
+fn mkdir(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let p = path(vm, "fs.mkdir", &args[0])?;
+    fs::create_dir_all(&p).map_err(|e| os_error("fs.mkdir", &p, e))?;
+    Ok(Value::Null)
+}
+
+// 目录中的文件名，按名称排序
+fn list_dir(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let p = path(vm, "fs.list_dir", &args[0])?;
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&p).map_err(|e| os_error("fs.list_dir", &p, e))? {
+        let entry = entry.map_err(|e| os_error("fs.list_dir", &p, e))?;
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+    Ok(names.into_value())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, process};
+    use crate::{chunk::chunk::Chunk, stdlib::testing::*, vm::interpret_result::InterpretResult};
+    use super::*;
+
+    #[test]
+    fn fs_test() {
+        let dir = env::temp_dir().join(format!("tlang_fs_test_{}", process::id()));
+        let mut vm = VM::from(Chunk::new());
+        vm.set_global("dir", Value::String(dir.to_string_lossy().into_owned()));
+        // 字符串字面量不支持转义
+        vm.set_global("nl", Value::String("\n".to_string()));
+        let code = "fs.mkdir(dir + \"/sub\");
+let f = dir + \"/a.txt\";
+fs.write(f, \"一\" + nl);
+fs.append(f, \"二\" + nl);
+let text = fs.read(f);
+let lines = fs.lines(f);
+let names = fs.list_dir(dir);
+fs.remove(f);
+let gone = !fs.exists(f) && fs.exists(dir + \"/sub\");
+fs.remove(dir + \"/sub\");
+fs.remove(dir);";
+        assert_eq!(vm.interpret(code), InterpretResult::Ok);
+        assert_eq!(vm.get_global("text").unwrap().to_string(), "一\n二\n");
+        assert_eq!(vm.get_global("lines").unwrap().to_string(), "[\"一\", \"二\"]");
+        assert_eq!(vm.get_global("names").unwrap().to_string(), "[\"a.txt\", \"sub\"]");
+        assert_eq!(vm.get_global("gone"), Some(&Value::Bool(true)));
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn error_test() {
+        let mut vm = VM::from(Chunk::new());
+        match vm.interpret("fs.read(\"/nonexistent/tlang.txt\");") {
+            InterpretResult::RUNTIME_ERROR(e) => {
+                assert!(e.massege.starts_with("fs.read failed on '/nonexistent/tlang.txt': "), "{}", e.massege);
+                assert_eq!(e.line, 1);
+            },
+            r => panic!("{:?}", r),
+        }
+
+        vm.set_io_allowed(false);
+        assert_eq!(error(&mut vm, "fs.exists(\".\");"), "fs.exists is not allowed in this sandbox.");
+    }
+}
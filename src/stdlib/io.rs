@@ -0,0 +1,61 @@
+use crate::{chunk::{convert::IntoValue, value::*}, interpreter_error::catalog::*, vm::{builtins::*, runtime_error::RuntimeError, vm::VM}};
+
+// 标准输入，来源可由 VM::set_input 替换
+pub fn define(vm: &mut VM) {
+    let mut m = Module::new("io");
+    m.native("read_line", 0, read_line)
+        .native("lines", 0, lines);
+    m.define(vm);
+}
+
+fn read(vm: &mut VM, name: &str) -> Result<Option<String>, RuntimeError> {
+    require_io(vm, name)?;
+    let mut line = String::new();
+    match vm.input().read_line(&mut line) {
+        Ok(0) => Ok(None),
+        Ok(_) => {
+            // 去掉行尾的 "\n" 或 "\r\n"
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Ok(Some(line))
+        },
+        Err(e) => Err(RuntimeError::new(trf("{} failed: {}", &[&name, &e]))),
+    }
+}
+
+// 读取一行，输入结束时为 null
+fn read_line(vm: &mut VM, _: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(read(vm, "io.read_line")?.into_value())
+}
+
+// 读取剩余的所有行
+fn lines(vm: &mut VM, _: &[Value]) -> Result<Value, RuntimeError> {
+    let mut ret = Vec::new();
+    while let Some(line) = read(vm, "io.lines")? {
+        ret.push(line);
+    }
+    Ok(ret.into_value())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use crate::{chunk::chunk::Chunk, stdlib::testing::*};
+    use super::*;
+
+    #[test]
+    fn input_test() {
+        let mut vm = VM::from(Chunk::new());
+        vm.set_input(Box::new(Cursor::new("first\r\nsecond\nthird")));
+        assert_eq!(eval(&mut vm, "io.read_line()").to_string(), "first");
+        assert_eq!(eval(&mut vm, "io.lines()").to_string(), "[\"second\", \"third\"]");
+        assert_eq!(eval(&mut vm, "io.read_line()"), Value::Null);
+
+        vm.set_io_allowed(false);
+        assert_eq!(error(&mut vm, "io.read_line();"), "io.read_line is not allowed in this sandbox.");
+    }
+}
@@ -0,0 +1,456 @@
+use std::{collections::BTreeMap, fmt::Write, rc::Rc};
+use crate::{chunk::value::*, interpreter_error::catalog::*, vm::{builtins::*, runtime_error::RuntimeError, vm::VM}};
+
+// 嵌套层数上限，避免解析时栈溢出
+const MAX_DEPTH: usize = 512;
+// 与 JavaScript 的 JSON.stringify 相同，缩进最多 10 个空格
+const MAX_INDENT: usize = 10;
+
+pub fn define(vm: &mut VM) {
+    let mut m = Module::new("json");
+    m.native("parse", 1, parse_native)
+        .native_variadic("stringify", stringify_native);
+    m.define(vm);
+}
+
+fn parse_native(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let text: String = param("json.parse", 1, &args[0])?;
+    parse(&text)
+}
+
+// stringify(value) 输出紧凑的一行，stringify(value, indent) 每层缩进 indent 个空格
+fn stringify_native(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    arity_between("json.stringify", args.len(), 1, 2)?;
+    let indent = match args.get(1) {
+        None | Some(Value::Null) => 0,
+        Some(v) => {
+            let n = integer("json.stringify", 2, v)?;
+            if n < 0 {
+                return Err(RuntimeError::new(trf("{} expects a non-negative count but got {}.", &[&"json.stringify", &n])));
+            }
+            n as usize
+        },
+    };
+    stringify(&args[0], indent).map(Value::String)
+}
+
+// 整数转为数字，对象转为映射，重复的键以后出现的为准
+pub fn parse(text: &str) -> Result<Value, RuntimeError> {
+    let mut p = Parser { chars: text.chars().collect(), pos: 0, depth: 0 };
+    p.whitespace();
+    let v = p.value()?;
+    p.whitespace();
+    match p.peek() {
+        None => Ok(v),
+        Some(c) => Err(p.error(trf("unexpected character '{}'", &[&c]))),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<char, RuntimeError> {
+        match self.peek() {
+            Some(c) => {
+                self.pos += 1;
+                Ok(c)
+            },
+            None => Err(self.error(tr("unexpected end of input"))),
+        }
+    }
+
+    // 行号与列号从 1 开始，按字符计算
+    fn error(&self, massege: impl std::fmt::Display) -> RuntimeError {
+        let pos = self.pos.min(self.chars.len());
+        let line = self.chars[..pos].iter().filter(|c| **c == '\n').count() + 1;
+        let column = pos - self.chars[..pos].iter().rposition(|c| *c == '\n').map_or(0, |i| i + 1) + 1;
+        RuntimeError::new(trf("json.parse: {} at line {}, column {}.", &[&massege, &line, &column]))
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), RuntimeError> {
+        for w in word.chars() {
+            let c = self.next()?;
+            if c != w {
+                self.pos -= 1;
+                return Err(self.error(trf("unexpected character '{}'", &[&c])));
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, RuntimeError> {
+        match self.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => self.string().map(Value::String),
+            Some('t') => self.expect("true").map(|_| Value::Bool(true)),
+            Some('f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some('n') => self.expect("null").map(|_| Value::Null),
+            Some('-' | '0'..='9') => self.number(),
+            Some(c) => Err(self.error(trf("unexpected character '{}'", &[&c]))),
+            None => Err(self.error(tr("unexpected end of input"))),
+        }
+    }
+
+    fn nested(&mut self, f: fn(&mut Self) -> Result<Value, RuntimeError>) -> Result<Value, RuntimeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(tr("too deeply nested")));
+        }
+        self.depth += 1;
+        let ret = f(self);
+        self.depth -= 1;
+        ret
+    }
+
+    fn array(&mut self) -> Result<Value, RuntimeError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Value::list(items));
+        }
+        loop {
+            self.whitespace();
+            items.push(self.value()?);
+            self.whitespace();
+            match self.next()? {
+                ',' => continue,
+                ']' => return Ok(Value::list(items)),
+                c => {
+                    self.pos -= 1;
+                    return Err(self.error(trf("unexpected character '{}'", &[&c])));
+                },
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, RuntimeError> {
+        self.pos += 1;
+        let mut entries = BTreeMap::new();
+        self.whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::map(entries));
+        }
+        loop {
+            self.whitespace();
+            if self.peek() != Some('"') {
+                return Err(match self.peek() {
+                    Some(c) => self.error(trf("unexpected character '{}'", &[&c])),
+                    None => self.error(tr("unexpected end of input")),
+                });
+            }
+            let key = self.string()?;
+            self.whitespace();
+            self.expect(":")?;
+            self.whitespace();
+            entries.insert(key, self.value()?);
+            self.whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(Value::map(entries)),
+                c => {
+                    self.pos -= 1;
+                    return Err(self.error(trf("unexpected character '{}'", &[&c])));
+                },
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, RuntimeError> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(s),
+                '\\' => {
+                    let c = match self.next()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => self.unicode()?,
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.error(tr("invalid escape sequence")));
+                        },
+                    };
+                    s.push(c);
+                },
+                c if c < ' ' => {
+                    self.pos -= 1;
+                    return Err(self.error(tr("control character in string")));
+                },
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, RuntimeError> {
+        let mut n = 0;
+        for _ in 0..4 {
+            let c = self.next()?;
+            match c.to_digit(16) {
+                Some(d) => n = n * 16 + d,
+                None => {
+                    self.pos -= 1;
+                    return Err(self.error(tr("invalid escape sequence")));
+                },
+            }
+        }
+        Ok(n)
+    }
+
+    // \uXXXX，代理对需要成对出现
+    fn unicode(&mut self) -> Result<char, RuntimeError> {
+        let start = self.pos - 2;
+        let hi = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&hi) {
+            if self.peek() != Some('\\') || self.chars.get(self.pos + 1) != Some(&'u') {
+                self.pos = start;
+                return Err(self.error(tr("invalid escape sequence")));
+            }
+            self.pos += 2;
+            let lo = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&lo) {
+                self.pos = start;
+                return Err(self.error(tr("invalid escape sequence")));
+            }
+            0x10000 + ((hi - 0xd800) << 10) + (lo - 0xdc00)
+        }else {
+            hi
+        };
+        char::from_u32(code).ok_or_else(|| {
+            self.pos = start;
+            self.error(tr("invalid escape sequence"))
+        })
+    }
+
+    // -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+    fn number(&mut self) -> Result<Value, RuntimeError> {
+        let start = self.pos;
+        let digits = |p: &mut Self| {
+            let from = p.pos;
+            while matches!(p.peek(), Some('0'..='9')) {
+                p.pos += 1;
+            }
+            p.pos > from
+        };
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        let ok = if self.peek() == Some('0') {
+            self.pos += 1;
+            true
+        }else {
+            digits(self)
+        };
+        let ok = ok && (self.peek() != Some('.') || {
+            self.pos += 1;
+            digits(self)
+        });
+        let ok = ok && (!matches!(self.peek(), Some('e' | 'E')) || {
+            self.pos += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.pos += 1;
+            }
+            digits(self)
+        });
+        if !ok {
+            self.pos = start;
+            return Err(self.error(tr("invalid number")));
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        Ok(Value::Double(text.parse().unwrap_or(f64::NAN)))
+    }
+}
+
+// indent 为 0 时不换行
+pub fn stringify(v: &Value, indent: usize) -> Result<String, RuntimeError> {
+    if indent > MAX_INDENT {
+        return Err(RuntimeError::new(trf("{} expects an indent of at most {} but got {}.", &[&"json.stringify", &MAX_INDENT, &indent])));
+    }
+    let mut w = Writer { out: String::new(), indent, seen: Vec::new() };
+    w.value(v, 0)?;
+    Ok(w.out)
+}
+
+struct Writer {
+    out: String,
+    indent: usize,
+    seen: Vec<*const ()>, // 从根到当前位置经过的列表与映射
+}
+
+impl Writer {
+    fn newline(&mut self, depth: usize) {
+        if self.indent > 0 {
+            self.out.push('\n');
+            self.out.push_str(&" ".repeat(self.indent * depth));
+        }
+    }
+
+    fn enter(&mut self, ptr: *const ()) -> Result<(), RuntimeError> {
+        if self.seen.contains(&ptr) {
+            return Err(RuntimeError::new(tr("json.stringify found a list or map that contains itself.")));
+        }
+        self.seen.push(ptr);
+        Ok(())
+    }
+
+    fn value(&mut self, v: &Value, depth: usize) -> Result<(), RuntimeError> {
+        match v {
+            Value::Double(d) if !d.is_finite() => return Err(RuntimeError::new(trf("json.stringify can't encode non-finite number {}.", &[d]))),
+            Value::Double(d) => {
+                let _ = write!(self.out, "{}", d);
+            },
+            Value::String(s) => self.string(s),
+            Value::Bool(b) => {
+                let _ = write!(self.out, "{}", b);
+            },
+            Value::Null => self.out.push_str("null"),
+            Value::List(l) => {
+                self.enter(Rc::as_ptr(l) as *const ())?;
+                let items = l.borrow();
+                self.out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(',');
+                    }
+                    self.newline(depth + 1);
+                    self.value(item, depth + 1)?;
+                }
+                if !items.is_empty() {
+                    self.newline(depth);
+                }
+                self.out.push(']');
+                self.seen.pop();
+            },
+            Value::Map(m) => {
+                self.enter(Rc::as_ptr(m) as *const ())?;
+                let entries = m.borrow();
+                self.out.push('{');
+                for (i, (k, item)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(',');
+                    }
+                    self.newline(depth + 1);
+                    self.string(k);
+                    self.out.push_str(if self.indent > 0 { ": " }else { ":" });
+                    self.value(item, depth + 1)?;
+                }
+                if !entries.is_empty() {
+                    self.newline(depth);
+                }
+                self.out.push('}');
+                self.seen.pop();
+            },
+            Value::Native(_) | Value::Function(_) | Value::Bound(_) | Value::Regex(_) | Value::Range(_) | Value::Iterator(_) => {
+                return Err(RuntimeError::new(trf("json.stringify can't encode a {}.", &[&v.type_name()])));
+            },
+        }
+        Ok(())
+    }
+
+    fn string(&mut self, s: &str) {
+        self.out.push_str(&quote(s));
+    }
+}
+
+// 加上引号并转义，得到 JSON 字符串；反汇编的 JSON 输出也使用它
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{chunk::chunk::Chunk, stdlib::testing::*};
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let v = parse(" {\"name\": \"tlang\", \"tags\": [\"a\", -1, 2.5e1, true, null], \"nested\": {\"x\": {}}, \"s\": \"\\u4e2d\\ud83d\\ude00\\n\"} ").unwrap();
+        assert_eq!(v.to_string(), "{\"name\": \"tlang\", \"nested\": {\"x\": {}}, \"s\": \"中😀\\n\", \"tags\": [\"a\", -1, 25, true, null]}");
+        assert_eq!(parse("[]").unwrap().to_string(), "[]");
+        assert_eq!(parse("0.5").unwrap(), Value::Double(0.5));
+    }
+
+    #[test]
+    fn parse_error_test() {
+        let err = |s: &str| parse(s).unwrap_err().massege;
+        assert_eq!(err("{\n  \"a\": tru\n}"), "json.parse: unexpected character '\n' at line 2, column 11.");
+        assert_eq!(err("[1, 2"), "json.parse: unexpected end of input at line 1, column 6.");
+        assert_eq!(err("[1,]"), "json.parse: unexpected character ']' at line 1, column 4.");
+        assert_eq!(err("{1: 2}"), "json.parse: unexpected character '1' at line 1, column 2.");
+        assert_eq!(err("01"), "json.parse: unexpected character '1' at line 1, column 2.");
+        assert_eq!(err("-"), "json.parse: invalid number at line 1, column 1.");
+        assert_eq!(err("\"\\x\""), "json.parse: invalid escape sequence at line 1, column 3.");
+        assert_eq!(err("\"\\ud800\""), "json.parse: invalid escape sequence at line 1, column 2.");
+        assert_eq!(err("1 2"), "json.parse: unexpected character '2' at line 1, column 3.");
+        assert_eq!(err(&"[".repeat(MAX_DEPTH + 1)), format!("json.parse: too deeply nested at line 1, column {}.", MAX_DEPTH + 1));
+    }
+
+    #[test]
+    fn stringify_test() {
+        let v = parse("{\"b\": [1, \"x\\\"y\", {}], \"a\": null, \"c\": []}").unwrap();
+        assert_eq!(stringify(&v, 0).unwrap(), "{\"a\":null,\"b\":[1,\"x\\\"y\",{}],\"c\":[]}");
+        assert_eq!(stringify(&v, 2).unwrap(), "{\n  \"a\": null,\n  \"b\": [\n    1,\n    \"x\\\"y\",\n    {}\n  ],\n  \"c\": []\n}");
+        assert_eq!(parse(&stringify(&v, 4).unwrap()).unwrap().to_string(), v.to_string());
+    }
+
+    #[test]
+    fn script_test() {
+        let mut vm = VM::from(Chunk::new());
+        // 字符串字面量不支持转义，改用全局变量传入
+        vm.set_global("text", Value::String("{\"n\": [1, 2]}".to_string()));
+        assert_eq!(eval(&mut vm, "let data = json.parse(text);\njson.stringify(data.n)").to_string(), "[1,2]");
+
+        assert_eq!(error(&mut vm, "json.stringify(math.NAN);"), "json.stringify can't encode non-finite number NaN.");
+        assert_eq!(error(&mut vm, "json.stringify(math.sqrt);"), "json.stringify can't encode a native fn.");
+        assert_eq!(error(&mut vm, "json.stringify(1, -1);"), "json.stringify expects a non-negative count but got -1.");
+        assert_eq!(error(&mut vm, "json.stringify(1, 11);"), "json.stringify expects an indent of at most 10 but got 11.");
+        assert_eq!(error(&mut vm, "json.stringify(1, 9000000000000000000);"), "json.stringify expects an indent of at most 10 but got 9000000000000000000.");
+        assert_eq!(error(&mut vm, "json.stringify();"), "json.stringify expects 1 to 2 arguments but got 0.");
+        assert_eq!(error(&mut vm, "json.parse(\"[\");"), "json.parse: unexpected end of input at line 1, column 2.");
+
+        if let Some(Value::Map(m)) = vm.get_global("data").cloned() {
+            let data = Value::Map(m.clone());
+            m.borrow_mut().insert("self".to_string(), data);
+        }
+        assert_eq!(error(&mut vm, "json.stringify(data);"), "json.stringify found a list or map that contains itself.");
+    }
+}
@@ -0,0 +1,221 @@
+use std::f64::consts;
+use crate::{chunk::value::*, interpreter_error::catalog::*, vm::{builtins::*, runtime_error::{ErrorKind, RuntimeError}, vm::VM}};
+
+// 未调用 math.seed 或 VM::set_seed 时使用的种子，保证每次运行结果相同
+pub const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+pub fn define(vm: &mut VM) {
+    let mut m = Module::new("math");
+    m.constant("PI", Value::Double(consts::PI))
+        .constant("E", Value::Double(consts::E))
+        .constant("INF", Value::Double(f64::INFINITY))
+        .constant("NAN", Value::Double(f64::NAN));
+
+    m.native("sqrt", 1, sqrt)
+        .native("floor", 1, floor)
+        .native("ceil", 1, ceil)
+        .native("round", 1, round)
+        .native("abs", 1, abs)
+        .native("sin", 1, sin)
+        .native("cos", 1, cos)
+        .native("tan", 1, tan)
+        .native("asin", 1, asin)
+        .native("acos", 1, acos)
+        .native("atan", 1, atan)
+        .native("exp", 1, exp)
+        .native("log", 1, ln)
+        .native("log10", 1, log10)
+        .native("log2", 1, log2)
+        .native("pow", 2, pow)
+        .native("atan2", 2, atan2)
+        .native_variadic("min", min)
+        .native_variadic("max", max);
+
+    m.native("seed", 1, seed)
+        .native("random", 0, random)
+        .native("random_int", 2, random_int)
+        .native("shuffle", 1, shuffle);
+    m.define(vm);
+}
+
+// 一元函数直接对应 f64 的同名方法
+macro_rules! unary {
+    ($($name:ident),*) => {
+        $(
+            fn $name(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+                let x: f64 = param(concat!("math.", stringify!($name)), 1, &args[0])?;
+                Ok(Value::Double(x.$name()))
+            }
+        )*
+    };
+}
+
+unary!(sqrt, floor, ceil, round, abs, sin, cos, tan, asin, acos, atan, exp, log10, log2);
+
+// 自然对数
+fn ln(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let x: f64 = param("math.log", 1, &args[0])?;
+    Ok(Value::Double(x.ln()))
+}
+
+fn pow(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let x: f64 = param("math.pow", 1, &args[0])?;
+    let y: f64 = param("math.pow", 2, &args[1])?;
+    Ok(Value::Double(x.powf(y)))
+}
+
+fn atan2(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let y: f64 = param("math.atan2", 1, &args[0])?;
+    let x: f64 = param("math.atan2", 2, &args[1])?;
+    Ok(Value::Double(y.atan2(x)))
+}
+
+fn min(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    fold("math.min", args, f64::min)
+}
+
+fn max(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    fold("math.max", args, f64::max)
+}
+
+fn fold(name: &str, args: &[Value], f: fn(f64, f64) -> f64) -> Result<Value, RuntimeError> {
+    if args.is_empty() {
+        return Err(RuntimeError::with_kind(ErrorKind::Type, trf("{} expects at least {} arguments but got {}.", &[&name, &1, &0])));
+    }
+    let mut ret: f64 = param(name, 1, &args[0])?;
+    for (i, v) in args.iter().enumerate().skip(1) {
+        ret = f(ret, param(name, i + 1, v)?);
+    }
+    Ok(Value::Double(ret))
+}
+
+fn seed(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let seed = integer("math.seed", 1, &args[0])?;
+    vm.set_seed(seed as u64);
+    Ok(Value::Null)
+}
+
+// [0, 1) 中的数
+fn random(vm: &mut VM, _: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Double(vm.rng().next_f64()))
+}
+
+// [lo, hi] 中的整数
+fn random_int(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let lo = integer("math.random_int", 1, &args[0])?;
+    let hi = integer("math.random_int", 2, &args[1])?;
+    if lo > hi {
+        return Err(RuntimeError::new(trf("{} got an empty range {}..={}.", &[&"math.random_int", &lo, &hi])));
+    }
+    Ok(Value::Double(vm.rng().range(lo, hi) as f64))
+}
+
+// 原地打乱列表并返回它
+fn shuffle(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let list = match &args[0] {
+        Value::List(l) => l.clone(),
+        v => return Err(type_error("math.shuffle", 1, "list", v)),
+    };
+    let mut items = list.borrow_mut();
+    for i in (1..items.len()).rev() {
+        let j = vm.rng().range(0, i as i64) as usize;
+        items.swap(i, j);
+    }
+    Ok(args[0].clone())
+}
+
+// splitmix64：状态只有一个 u64，给定种子时序列确定
+pub struct Rng {
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // 取高 53 位，得到 [0, 1) 中均匀分布的数
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // [lo, hi] 中的整数，要求 lo <= hi
+    pub fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        let span = hi.wrapping_sub(lo) as u64 as u128 + 1;
+        let offset = (self.next_u64() as u128 * span) >> 64;
+        lo.wrapping_add(offset as i64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{chunk::chunk::Chunk, stdlib::testing::*};
+
+    #[test]
+    fn math_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(eval(&mut vm, "math.sqrt(16) + math.pow(2, 10)"), Value::Double(1028.0));
+        assert_eq!(eval(&mut vm, "math.floor(-1.5) + math.ceil(1.2) + math.round(2.5) + math.abs(-3)"), Value::Double(6.0));
+        assert_eq!(eval(&mut vm, "math.min(3, 1, 2) + math.max(3, 1, 2)"), Value::Double(4.0));
+        assert_eq!(eval(&mut vm, "math.log(math.exp(2))"), Value::Double(2.0));
+        assert_eq!(eval(&mut vm, "math.cos(math.PI)"), Value::Double(-1.0));
+        assert_eq!(eval(&mut vm, "math.INF > 1000000"), Value::Bool(true));
+        assert_eq!(eval(&mut vm, "math.NAN == math.NAN"), Value::Bool(false));
+    }
+
+    #[test]
+    fn error_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(error(&mut vm, "math.sqrt(\"4\");"), "math.sqrt expects argument 1 to be number but got string.");
+        assert_eq!(error(&mut vm, "math.pow(2);"), "math.pow expects 2 arguments but got 1.");
+        assert_eq!(error(&mut vm, "math.max();"), "math.max expects at least 1 arguments but got 0.");
+        assert_eq!(error(&mut vm, "math.random_int(3, 1);"), "math.random_int got an empty range 3..=1.");
+        assert_eq!(error(&mut vm, "math.tau;"), "Undefined property 'tau' on map.");
+        assert_eq!(error(&mut vm, "(1).x;"), "Undefined property 'x' on number.");
+    }
+
+    #[test]
+    fn random_test() {
+        let code = "math.seed(42);\nlet a = math.random();\nlet b = math.random_int(1, 6);\nlet c = math.random_int(-2, -2);";
+        let mut vm = VM::from(Chunk::new());
+        eval(&mut vm, code);
+        let first = (vm.get_global("a").cloned(), vm.get_global("b").cloned());
+        eval(&mut vm, code);
+        assert_eq!((vm.get_global("a").cloned(), vm.get_global("b").cloned()), first);
+        assert_eq!(vm.get_global("c"), Some(&Value::Double(-2.0)));
+
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            assert!((1..=6).contains(&rng.range(1, 6)));
+        }
+        assert_eq!(rng.range(i64::MIN, i64::MIN), i64::MIN);
+    }
+
+    #[test]
+    fn shuffle_test() {
+        let mut vm = VM::from(Chunk::new());
+        vm.set_global("items", Value::list((0..20).map(|i| Value::Double(i as f64)).collect()));
+        let shuffled = eval(&mut vm, "math.shuffle(items)");
+        let mut items: Vec<i64> = crate::chunk::convert::FromValue::from_value(&shuffled).unwrap();
+        assert_ne!(items, (0..20).collect::<Vec<i64>>());
+        items.sort();
+        assert_eq!(items, (0..20).collect::<Vec<i64>>());
+    }
+}
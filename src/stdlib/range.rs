@@ -0,0 +1,60 @@
+use crate::{chunk::value::*, interpreter_error::catalog::*, vm::{builtins::*, runtime_error::RuntimeError, vm::VM}};
+
+// 区间方法，如 (0..10).step(2)
+pub fn method(name: &str) -> Option<Native> {
+    let (arity, fun): (usize, NativeFn) = match name {
+        "step" => (1, step),
+        "contains" => (1, contains),
+        _ => return None,
+    };
+    Some(Native { name: format!("range.{}", name), arity: Some(arity), fun })
+}
+
+// 接收者总是区间，见 VM 对 OP_GET_PROPERTY 的处理
+fn receiver(args: &[Value]) -> Range {
+    match &args[0] {
+        Value::Range(r) => *r,
+        _ => Range::new(0.0, 0.0, false),
+    }
+}
+
+// 步长为负时从 start 向下数到 end
+fn step(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let step: f64 = param("range.step", 1, &args[1])?;
+    if step == 0.0 || !step.is_finite() {
+        return Err(RuntimeError::new(trf("{} expects a non-zero step but got {}.", &[&"range.step", &step])));
+    }
+    Ok(Value::Range(Range { step, ..receiver(args) }))
+}
+
+// 是否位于区间的起点与终点之间，方向由步长决定，不考虑步长的间隔
+fn contains(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let r = receiver(args);
+    let x: f64 = param("range.contains", 1, &args[1])?;
+    let inside = if r.step > 0.0 { r.start <= x && x < r.end }else { r.start >= x && x > r.end };
+    Ok(Value::Bool(inside || r.inclusive && x == r.end))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{chunk::chunk::Chunk, stdlib::testing::*};
+    use super::*;
+
+    #[test]
+    fn method_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(eval(&mut vm, "0..10").to_string(), "0..10");
+        assert_eq!(eval(&mut vm, "(1..=2 + 3).step(2)").to_string(), "(1..=5).step(2)");
+        assert_eq!(eval(&mut vm, "(0..10).contains(0) && (0..10).contains(9.5) && !(0..10).contains(10)").to_string(), "true");
+        assert_eq!(eval(&mut vm, "(0..=10).contains(10) && (10..0).step(-1).contains(1) && !(10..0).contains(1)").to_string(), "true");
+        assert_eq!(eval(&mut vm, "!(3..3).contains(3) && (3..=3).contains(3)").to_string(), "true");
+        assert_eq!(eval(&mut vm, "0..1 == 0..1").to_string(), "true");
+    }
+
+    #[test]
+    fn error_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(error(&mut vm, "(0..1).step(0);"), "range.step expects a non-zero step but got 0.");
+        assert_eq!(error(&mut vm, "let r = 0..\"a\";"), "Range bounds must be numbers.");
+    }
+}
@@ -0,0 +1,172 @@
+use std::{collections::BTreeMap, rc::Rc};
+use crate::{chunk::{convert::IntoValue, value::*}, interpreter_error::catalog::*, vm::{builtins::*, runtime_error::RuntimeError, vm::VM}};
+
+// 编译好的正则表达式，可以反复使用，如 let r = re.compile("[0-9]+"); r.find_all(s);
+pub struct Regex {
+    pub pattern: String,
+    regex: regex::Regex,
+}
+
+// 按模式比较
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Regex {
+    // 语法错误报告模式中的字符位置，从 1 开始
+    pub fn new(pattern: &str) -> Result<Self, RuntimeError> {
+        if let Err(e) = regex_syntax::Parser::new().parse(pattern) {
+            let (kind, span) = match &e {
+                regex_syntax::Error::Parse(e) => (e.kind().to_string(), *e.span()),
+                regex_syntax::Error::Translate(e) => (e.kind().to_string(), *e.span()),
+                _ => (e.to_string(), regex_syntax::ast::Span::splat(regex_syntax::ast::Position::new(0, 1, 1))),
+            };
+            let at = pattern[..span.start.offset].chars().count() + 1;
+            return Err(RuntimeError::new(trf("invalid regex '{}': {} at position {}.", &[&pattern, &kind, &at])));
+        }
+        match regex::Regex::new(pattern) {
+            Ok(regex) => Ok(Self { pattern: pattern.to_string(), regex }),
+            Err(e) => Err(RuntimeError::new(trf("invalid regex '{}': {}", &[&pattern, &e]))),
+        }
+    }
+}
+
+// 每个函数既是 re 模块的成员，第一个参数为模式或编译好的正则表达式，
+// 也是正则表达式值的方法，接收者即第一个参数。base 使报告的参数位置与调用者看到的一致
+macro_rules! natives {
+    ($($fun:ident => $name:literal, $arity:literal;)*) => {
+        pub fn define(vm: &mut VM) {
+            let mut m = Module::new("re");
+            m.native("compile", 1, compile);
+            $(
+                m.native($name, $arity + 1, |_, args| $fun(concat!("re.", $name), 1, args));
+            )*
+            m.define(vm);
+        }
+
+        // 正则表达式值的方法
+        pub fn method(name: &str) -> Option<Native> {
+            let (arity, fun): (usize, NativeFn) = match name {
+                $(
+                    $name => ($arity, |_, args| $fun(concat!("regex.", $name), 0, args)),
+                )*
+                _ => return None,
+            };
+            Some(Native { name: format!("regex.{}", name), arity: Some(arity), fun })
+        }
+    };
+}
+
+natives! {
+    is_match => "match", 1;
+    find => "find", 1;
+    find_all => "find_all", 1;
+    captures => "captures", 1;
+    named_captures => "named_captures", 1;
+    replace => "replace", 2;
+    split => "split", 1;
+}
+
+fn compile(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let pattern: String = param("re.compile", 1, &args[0])?;
+    Ok(Value::Regex(Rc::new(Regex::new(&pattern)?)))
+}
+
+// 字符串在每次调用时编译
+fn regex(name: &str, base: usize, v: &Value) -> Result<Rc<Regex>, RuntimeError> {
+    match v {
+        Value::Regex(r) => Ok(r.clone()),
+        Value::String(p) => Ok(Rc::new(Regex::new(p)?)),
+        _ => Err(type_error(name, base, "regex or string", v)),
+    }
+}
+
+fn text(name: &str, base: usize, args: &[Value]) -> Result<(Rc<Regex>, String), RuntimeError> {
+    Ok((regex(name, base, &args[0])?, param(name, base + 1, &args[1])?))
+}
+
+// 是否在任意位置匹配
+fn is_match(name: &str, base: usize, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (r, s) = text(name, base, args)?;
+    Ok(Value::Bool(r.regex.is_match(&s)))
+}
+
+// 第一处匹配的文本，没有时为 null
+fn find(name: &str, base: usize, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (r, s) = text(name, base, args)?;
+    Ok(r.regex.find(&s).map(|m| m.as_str()).into_value())
+}
+
+fn find_all(name: &str, base: usize, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (r, s) = text(name, base, args)?;
+    Ok(r.regex.find_iter(&s).map(|m| m.as_str()).collect::<Vec<_>>().into_value())
+}
+
+// 第一处匹配的各个分组，第 0 项为整个匹配，未参与匹配的分组为 null；没有匹配时为 null
+fn captures(name: &str, base: usize, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (r, s) = text(name, base, args)?;
+    Ok(r.regex.captures(&s).map(|caps| caps.iter().map(|m| m.map(|m| m.as_str())).collect::<Vec<_>>()).into_value())
+}
+
+// 命名分组组成的映射，如 (?P<year>[0-9]{4})
+fn named_captures(name: &str, base: usize, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (r, s) = text(name, base, args)?;
+    Ok(r.regex.captures(&s).map(|caps| {
+        r.regex.capture_names().flatten().map(|n| (n.to_string(), caps.name(n).map(|m| m.as_str()))).collect::<BTreeMap<_, _>>()
+    }).into_value())
+}
+
+// 替换所有匹配，替换文本中可以用 $1 或 ${name} 引用分组
+fn replace(name: &str, base: usize, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (r, s) = text(name, base, args)?;
+    let rep: String = param(name, base + 2, &args[2])?;
+    Ok(Value::String(r.regex.replace_all(&s, rep.as_str()).into_owned()))
+}
+
+fn split(name: &str, base: usize, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (r, s) = text(name, base, args)?;
+    Ok(r.regex.split(&s).collect::<Vec<_>>().into_value())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{chunk::chunk::Chunk, stdlib::testing::*};
+    use super::*;
+
+    #[test]
+    fn module_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(eval(&mut vm, "re.match(\"^[a-z]+$\", \"tlang\")").to_string(), "true");
+        assert_eq!(eval(&mut vm, "re.find(\"[0-9]+\", \"v12.3\")").to_string(), "12");
+        assert_eq!(eval(&mut vm, "re.find(\"[0-9]+\", \"none\")").to_string(), "null");
+        assert_eq!(eval(&mut vm, "re.find_all(\"[0-9]+\", \"1, 22 and 333\")").to_string(), "[\"1\", \"22\", \"333\"]");
+        assert_eq!(eval(&mut vm, "re.captures(\"(\\w+)@(\\w+)?\", \"me@\")").to_string(), "[\"me@\", \"me\", null]");
+        assert_eq!(eval(&mut vm, "re.named_captures(\"(?P<y>[0-9]{4})-(?P<m>[0-9]{2})\", \"on 2024-05\")").to_string(), "{\"m\": \"05\", \"y\": \"2024\"}");
+        assert_eq!(eval(&mut vm, "re.replace(\"(\\w+)=(\\w+)\", \"a=1 b=2\", \"$2=$1\")").to_string(), "1=a 2=b");
+        assert_eq!(eval(&mut vm, "re.split(\"\\s*,\\s*\", \"x , y,z\")").to_string(), "[\"x\", \"y\", \"z\"]");
+        assert_eq!(eval(&mut vm, "re.find(\"\\p{Han}+\", \"tlang 中文\")").to_string(), "中文");
+    }
+
+    #[test]
+    fn compiled_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(eval(&mut vm, "let digits = re.compile(\"[0-9]+\");\ndigits").to_string(), "<regex \"[0-9]+\">");
+        assert_eq!(eval(&mut vm, "digits.find_all(\"a1b22\")").to_string(), "[\"1\", \"22\"]");
+        assert_eq!(eval(&mut vm, "digits.replace(\"a1b22\", \"#\")").to_string(), "a#b#");
+        assert_eq!(eval(&mut vm, "re.match(digits, \"x9\") && digits.match(\"7\")").to_string(), "true");
+        assert_eq!(eval(&mut vm, "digits == re.compile(\"[0-9]+\")").to_string(), "true");
+    }
+
+    #[test]
+    fn error_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(error(&mut vm, "re.compile(\"ab(c\");"), "invalid regex 'ab(c': unclosed group at position 3.");
+        assert_eq!(error(&mut vm, "re.match(\"中文[z-a]\", \"\");"), "invalid regex '中文[z-a]': invalid character class range, the start must be <= the end at position 4.");
+        assert_eq!(error(&mut vm, "re.find(1, \"\");"), "re.find expects argument 1 to be regex or string but got number.");
+        assert_eq!(error(&mut vm, "re.find(\"a\", 1);"), "re.find expects argument 2 to be string but got number.");
+        assert_eq!(error(&mut vm, "re.compile(\"a\").find(1);"), "regex.find expects argument 1 to be string but got number.");
+        assert_eq!(error(&mut vm, "re.compile(\"a\").size;"), "Undefined property 'size' on regex.");
+    }
+}
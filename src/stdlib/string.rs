@@ -0,0 +1,241 @@
+use crate::{chunk::{convert::IntoValue, value::*}, interpreter_error::catalog::*, vm::{builtins::*, runtime_error::{ErrorKind, RuntimeError}, vm::VM}};
+
+// repeat 结果的最大字节数
+const MAX_LEN: usize = 1 << 28;
+
+// 字符串方法，如 "abc".upper()。下标与长度均按字符计算，中日韩文字每个字算一个字符；
+// 负数下标从末尾倒数
+pub fn method(name: &str) -> Option<Native> {
+    let (arity, fun): (Option<usize>, NativeFn) = match name {
+        "len" => (Some(0), len),
+        "substring" => (None, substring),
+        "split" => (None, split),
+        "join" => (Some(1), join),
+        "trim" => (Some(0), trim),
+        "trim_start" => (Some(0), trim_start),
+        "trim_end" => (Some(0), trim_end),
+        "upper" => (Some(0), upper),
+        "lower" => (Some(0), lower),
+        "find" => (Some(1), find),
+        "replace" => (Some(2), replace),
+        "starts_with" => (Some(1), starts_with),
+        "ends_with" => (Some(1), ends_with),
+        "repeat" => (Some(1), repeat),
+        "chars" => (Some(0), chars),
+        "format" => (None, format),
+        _ => return None,
+    };
+    Some(Native { name: format!("string.{}", name), arity, fun })
+}
+
+// 接收者总是字符串，见 VM 对 OP_GET_PROPERTY 的处理
+fn receiver(args: &[Value]) -> &str {
+    match &args[0] {
+        Value::String(s) => s,
+        _ => "",
+    }
+}
+
+// 下标可以等于长度，表示末尾
+fn index(name: &str, pos: usize, v: &Value, len: usize) -> Result<usize, RuntimeError> {
+    let i = integer(name, pos, v)?;
+    let at = if i < 0 { i + len as i64 }else { i };
+    if at < 0 || at > len as i64 {
+        return Err(RuntimeError::with_kind(ErrorKind::Index, trf("{} index {} is out of range for length {}.", &[&name, &i, &len])));
+    }
+    Ok(at as usize)
+}
+
+fn len(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Double(receiver(args).chars().count() as f64))
+}
+
+// substring(start) 或 substring(start, end)，不含 end
+fn substring(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    arity_between("string.substring", args.len() - 1, 1, 2)?;
+    let chars: Vec<char> = receiver(args).chars().collect();
+    let start = index("string.substring", 1, &args[1], chars.len())?;
+    let end = match args.get(2) {
+        Some(v) => index("string.substring", 2, v, chars.len())?,
+        None => chars.len(),
+    };
+    Ok(Value::String(chars[start..end.max(start)].iter().collect()))
+}
+
+// 不带参数时按空白分割
+fn split(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    arity_between("string.split", args.len() - 1, 0, 1)?;
+    let s = receiver(args);
+    let parts: Vec<&str> = match args.get(1) {
+        None => s.split_whitespace().collect(),
+        Some(v) => {
+            let sep: String = param("string.split", 1, v)?;
+            if sep.is_empty() {
+                return Ok(chars_of(s));
+            }
+            s.split(sep.as_str()).collect()
+        },
+    };
+    Ok(parts.into_value())
+}
+
+// 以接收者为分隔符连接列表，如 ", ".join(items)
+fn join(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let items = match &args[1] {
+        Value::List(l) => l.clone(),
+        v => return Err(type_error("string.join", 1, "list", v)),
+    };
+    let parts: Vec<String> = items.borrow().iter().map(|v| v.to_string()).collect();
+    Ok(Value::String(parts.join(receiver(args))))
+}
+
+fn trim(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(receiver(args).trim().into_value())
+}
+
+fn trim_start(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(receiver(args).trim_start().into_value())
+}
+
+fn trim_end(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(receiver(args).trim_end().into_value())
+}
+
+fn upper(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::String(receiver(args).to_uppercase()))
+}
+
+fn lower(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::String(receiver(args).to_lowercase()))
+}
+
+// 第一次出现的字符下标，找不到时为 null
+fn find(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let s = receiver(args);
+    let sub: String = param("string.find", 1, &args[1])?;
+    Ok(s.find(sub.as_str()).map(|byte| s[..byte].chars().count() as i64).into_value())
+}
+
+// 替换全部出现的位置
+fn replace(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let from: String = param("string.replace", 1, &args[1])?;
+    let to: String = param("string.replace", 2, &args[2])?;
+    Ok(Value::String(receiver(args).replace(from.as_str(), &to)))
+}
+
+fn starts_with(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let prefix: String = param("string.starts_with", 1, &args[1])?;
+    Ok(Value::Bool(receiver(args).starts_with(prefix.as_str())))
+}
+
+fn ends_with(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let suffix: String = param("string.ends_with", 1, &args[1])?;
+    Ok(Value::Bool(receiver(args).ends_with(suffix.as_str())))
+}
+
+fn repeat(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let n = integer("string.repeat", 1, &args[1])?;
+    if n < 0 {
+        return Err(RuntimeError::new(trf("{} expects a non-negative count but got {}.", &[&"string.repeat", &n])));
+    }
+    let s = receiver(args);
+    match s.len().checked_mul(n as usize) {
+        Some(len) if len <= MAX_LEN => Ok(Value::String(s.repeat(n as usize))),
+        _ => Err(RuntimeError::new(trf("{} would produce a string longer than {} bytes.", &[&"string.repeat", &MAX_LEN]))),
+    }
+}
+
+fn chars(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(chars_of(receiver(args)))
+}
+
+fn chars_of(s: &str) -> Value {
+    s.chars().map(|c| c.to_string()).collect::<Vec<_>>().into_value()
+}
+
+// 依次以参数替换 "{}"，"{{" 与 "}}" 表示花括号本身
+fn format(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let template = receiver(args);
+    let expected = placeholders(template);
+    if expected > args.len() - 1 {
+        return Err(RuntimeError::with_kind(ErrorKind::Type, trf("{} expects {} arguments but got {}.", &[&"string.format", &expected, &(args.len() - 1)])));
+    }
+    let mut ret = String::new();
+    let mut rest = args[1..].iter();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                ret.push(c);
+            },
+            ('{', Some('}')) => {
+                chars.next();
+                if let Some(v) = rest.next() {
+                    ret.push_str(&v.to_string());
+                }
+            },
+            _ => ret.push(c),
+        }
+    }
+    Ok(Value::String(ret))
+}
+
+// 模板中 "{}" 的个数，跳过转义的花括号
+fn placeholders(template: &str) -> usize {
+    let mut count = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => { chars.next(); },
+            ('{', Some('}')) => {
+                chars.next();
+                count += 1;
+            },
+            _ => {},
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{chunk::chunk::Chunk, stdlib::testing::*, vm::vm::VM};
+
+    #[test]
+    fn method_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(eval(&mut vm, "\"你好，世界\".len()").to_string(), "5");
+        assert_eq!(eval(&mut vm, "\"你好，世界\".substring(3)").to_string(), "世界");
+        assert_eq!(eval(&mut vm, "\"你好，世界\".substring(0, -3)").to_string(), "你好");
+        assert_eq!(eval(&mut vm, "\"a,b,,c\".split(\",\")").to_string(), "[\"a\", \"b\", \"\", \"c\"]");
+        assert_eq!(eval(&mut vm, "\" a  b \".split()").to_string(), "[\"a\", \"b\"]");
+        assert_eq!(eval(&mut vm, "\"-\".join(\"汉字\".chars())").to_string(), "汉-字");
+        assert_eq!(eval(&mut vm, "\"  x \".trim() + \"|\"").to_string(), "x|");
+        assert_eq!(eval(&mut vm, "\"Tlang\".upper() + \"Tlang\".lower()").to_string(), "TLANGtlang");
+        assert_eq!(eval(&mut vm, "\"中文字符\".find(\"字\")").to_string(), "2");
+        assert_eq!(eval(&mut vm, "\"abc\".find(\"x\")").to_string(), "null");
+        assert_eq!(eval(&mut vm, "\"a-b-c\".replace(\"-\", \"+\")").to_string(), "a+b+c");
+        assert_eq!(eval(&mut vm, "\"main.tl\".ends_with(\".tl\") && \"main.tl\".starts_with(\"ma\")").to_string(), "true");
+        assert_eq!(eval(&mut vm, "\"ab\".repeat(3)").to_string(), "ababab");
+        assert_eq!(eval(&mut vm, "\"{} + {} = {{{}}}\".format(1, 2, 3)").to_string(), "1 + 2 = {3}");
+
+        // 方法可以先取出再调用
+        assert_eq!(eval(&mut vm, "let up = \"q\".upper;\nup()").to_string(), "Q");
+    }
+
+    #[test]
+    fn error_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(error(&mut vm, "\"abc\".substring(4);"), "string.substring index 4 is out of range for length 3.");
+        assert_eq!(error(&mut vm, "\"abc\".substring();"), "string.substring expects 1 to 2 arguments but got 0.");
+        assert_eq!(error(&mut vm, "\"abc\".upper(1);"), "string.upper expects 0 arguments but got 1.");
+        assert_eq!(error(&mut vm, "\"abc\".repeat(-1);"), "string.repeat expects a non-negative count but got -1.");
+        assert_eq!(error(&mut vm, "\"ab\".repeat(9000000000000000000);"), "string.repeat would produce a string longer than 268435456 bytes.");
+        assert_eq!(error(&mut vm, "\"ab\".repeat(200000000);"), "string.repeat would produce a string longer than 268435456 bytes.");
+        assert_eq!(error(&mut vm, "\"abc\".find(1);"), "string.find expects argument 1 to be string but got number.");
+        assert_eq!(error(&mut vm, "\"{}{}\".format(1);"), "string.format expects 2 arguments but got 1.");
+        assert_eq!(error(&mut vm, "\"{} {{}} {} {} {}\".format(1, 2);"), "string.format expects 4 arguments but got 2.");
+        assert_eq!(error(&mut vm, "\"abc\".size();"), "Undefined property 'size' on string.");
+    }
+}
@@ -0,0 +1,16 @@
+// 标准库各模块测试共用的辅助函数
+use crate::{chunk::value::Value, vm::{interpret_result::InterpretResult, vm::VM}};
+
+// 执行代码并取出末尾表达式的值
+pub fn eval(vm: &mut VM, code: &str) -> Value {
+    assert_eq!(vm.interpret(code), InterpretResult::Ok, "{}", code);
+    vm.take_result()
+}
+
+// 执行代码，返回运行时错误的信息
+pub fn error(vm: &mut VM, code: &str) -> String {
+    match vm.interpret(code) {
+        InterpretResult::RUNTIME_ERROR(e) => e.massege,
+        r => panic!("{}: {:?}", code, r),
+    }
+}
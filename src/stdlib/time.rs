@@ -0,0 +1,265 @@
+use std::{thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use crate::{chunk::value::*, interpreter_error::catalog::*, vm::{builtins::*, runtime_error::RuntimeError, vm::VM}};
+
+// time.format 与 time.parse 省略格式时使用 ISO 8601
+const DEFAULT_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+// time.format 接受的范围，即 0000-01-01T00:00:00Z 到 9999-12-31T23:59:59Z
+const MIN_SECS: f64 = -62_167_219_200.0;
+const MAX_SECS: f64 = 253_402_300_799.0;
+
+// 时间的来源，测试时可以用 ManualClock 代替系统时钟
+pub trait Clock {
+    // 单调递增的秒数，只用于计算时间差
+    fn monotonic(&self) -> f64;
+    // 自 1970-01-01T00:00:00Z 起的秒数
+    fn now(&self) -> f64;
+    fn sleep(&mut self, duration: Duration);
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn monotonic(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    fn now(&self) -> f64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// 不会自行走动的时钟，sleep 只把时间往前拨
+pub struct ManualClock {
+    pub monotonic: f64,
+    pub epoch: f64,
+}
+
+impl ManualClock {
+    pub fn new(epoch: f64) -> Self {
+        Self { monotonic: 0.0, epoch }
+    }
+}
+
+impl Clock for ManualClock {
+    fn monotonic(&self) -> f64 {
+        self.monotonic
+    }
+
+    fn now(&self) -> f64 {
+        self.epoch
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        self.monotonic += duration.as_secs_f64();
+        self.epoch += duration.as_secs_f64();
+    }
+}
+
+pub fn define(vm: &mut VM) {
+    let mut m = Module::new("time");
+    m.native("clock", 0, clock)
+        .native("now", 0, now)
+        .native("sleep", 1, sleep)
+        .native_variadic("format", format)
+        .native_variadic("parse", parse);
+    m.define(vm);
+}
+
+fn clock(vm: &mut VM, _: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Double(vm.clock().monotonic()))
+}
+
+fn now(vm: &mut VM, _: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Double(vm.clock().now()))
+}
+
+// 参数为毫秒
+fn sleep(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let ms: f64 = param("time.sleep", 1, &args[0])?;
+    if !(ms >= 0.0 && ms.is_finite()) {
+        return Err(RuntimeError::new(trf("{} expects a non-negative count but got {}.", &[&"time.sleep", &ms])));
+    }
+    let duration = Duration::try_from_secs_f64(ms / 1000.0)
+        .map_err(|_| RuntimeError::new(trf("{} got a duration that is too long: {}.", &[&"time.sleep", &ms])))?;
+    vm.clock().sleep(duration);
+    Ok(Value::Null)
+}
+
+// format(seconds[, fmt])，按 UTC 输出，支持 %Y %m %d %H %M %S %%
+fn format(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    arity_between("time.format", args.len(), 1, 2)?;
+    let secs = param::<f64>("time.format", 1, &args[0])?.floor();
+    if !secs.is_finite() {
+        return Err(type_error("time.format", 1, "finite number", &args[0]));
+    }
+    if !(MIN_SECS..=MAX_SECS).contains(&secs) {
+        return Err(RuntimeError::new(trf("{} expects a timestamp between {} and {} but got {}.", &[&"time.format", &MIN_SECS, &MAX_SECS, &args[0]])));
+    }
+    let fmt: String = match args.get(1) {
+        Some(v) => param("time.format", 2, v)?,
+        None => DEFAULT_FORMAT.to_string(),
+    };
+    format_utc(secs as i64, &fmt).map(Value::String)
+}
+
+// parse(text[, fmt])，返回 UTC 的秒数
+fn parse(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    arity_between("time.parse", args.len(), 1, 2)?;
+    let text: String = param("time.parse", 1, &args[0])?;
+    let fmt: String = match args.get(1) {
+        Some(v) => param("time.parse", 2, v)?,
+        None => DEFAULT_FORMAT.to_string(),
+    };
+    parse_utc(&text, &fmt).map(|secs| Value::Double(secs as f64))
+}
+
+pub fn format_utc(secs: i64, fmt: &str) -> Result<String, RuntimeError> {
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let (y, m, d) = civil_from_days(days);
+    let mut ret = String::new();
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            ret.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => ret.push_str(&format!("{:04}", y)),
+            Some('m') => ret.push_str(&format!("{:02}", m)),
+            Some('d') => ret.push_str(&format!("{:02}", d)),
+            Some('H') => ret.push_str(&format!("{:02}", rem / 3600)),
+            Some('M') => ret.push_str(&format!("{:02}", rem / 60 % 60)),
+            Some('S') => ret.push_str(&format!("{:02}", rem % 60)),
+            Some('%') => ret.push('%'),
+            _ => return Err(RuntimeError::new(trf("{} got an invalid format '{}'.", &[&"time.format", &fmt]))),
+        }
+    }
+    Ok(ret)
+}
+
+pub fn parse_utc(text: &str, fmt: &str) -> Result<i64, RuntimeError> {
+    let mismatch = || RuntimeError::new(trf("time.parse: '{}' does not match format '{}'.", &[&text, &fmt]));
+    let (mut y, mut m, mut d, mut hh, mut mm, mut ss) = (1970, 1, 1, 0, 0, 0);
+    let mut input = text.chars();
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        let (field, width) = match c {
+            '%' => match chars.next() {
+                Some('Y') => (&mut y, 4),
+                Some('m') => (&mut m, 2),
+                Some('d') => (&mut d, 2),
+                Some('H') => (&mut hh, 2),
+                Some('M') => (&mut mm, 2),
+                Some('S') => (&mut ss, 2),
+                Some('%') => {
+                    if input.next() != Some('%') {
+                        return Err(mismatch());
+                    }
+                    continue;
+                },
+                _ => return Err(RuntimeError::new(trf("{} got an invalid format '{}'.", &[&"time.parse", &fmt]))),
+            },
+            c => {
+                if input.next() != Some(c) {
+                    return Err(mismatch());
+                }
+                continue;
+            },
+        };
+        // 每个字段恰好 width 位数字
+        let mut n = 0;
+        for _ in 0..width {
+            match input.next().and_then(|c| c.to_digit(10)) {
+                Some(digit) => n = n * 10 + digit as i64,
+                None => return Err(mismatch()),
+            }
+        }
+        *field = n;
+    }
+    if input.next().is_some() || !(1..=12).contains(&m) || d < 1 || d > days_in_month(y, m) || hh > 23 || mm > 59 || ss > 59 {
+        return Err(mismatch());
+    }
+    Ok(days_from_civil(y, m, d) * 86400 + hh * 3600 + mm * 60 + ss)
+}
+
+fn days_in_month(y: i64, m: i64) -> i64 {
+    match m {
+        2 if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// 公历日期与 1970-01-01 起的天数互相换算，见 Howard Hinnant 的 chrono 兼容算法
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 }else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 }else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 }else { mp - 9 };
+    (if m <= 2 { yoe + era * 400 + 1 }else { yoe + era * 400 }, m, d)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{chunk::chunk::Chunk, stdlib::testing::*, vm::interpret_result::InterpretResult};
+    use super::*;
+
+    #[test]
+    fn clock_test() {
+        let mut vm = VM::from(Chunk::new());
+        vm.set_clock(Box::new(ManualClock::new(1_700_000_000.0)));
+        assert_eq!(vm.interpret("let t0 = time.clock();\ntime.sleep(1500);\nlet t1 = time.clock();\nlet at = time.format(time.now());"), InterpretResult::Ok);
+        assert_eq!(vm.get_global("t0"), Some(&Value::Double(0.0)));
+        assert_eq!(vm.get_global("t1"), Some(&Value::Double(1.5)));
+        assert_eq!(vm.get_global("at").unwrap().to_string(), "2023-11-14T22:13:21Z");
+    }
+
+    #[test]
+    fn date_test() {
+        assert_eq!(format_utc(0, DEFAULT_FORMAT).unwrap(), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(-1, "%Y/%m/%d %H:%M:%S %%").unwrap(), "1969/12/31 23:59:59 %");
+        assert_eq!(format_utc(951782400, "%Y-%m-%d").unwrap(), "2000-02-29");
+        assert_eq!(parse_utc("2000-02-29", "%Y-%m-%d").unwrap(), 951782400);
+        for secs in [0, 1_700_000_000, -86400 * 365 * 300, 4_102_444_800] {
+            assert_eq!(parse_utc(&format_utc(secs, DEFAULT_FORMAT).unwrap(), DEFAULT_FORMAT).unwrap(), secs);
+        }
+
+        assert_eq!(parse_utc("2001-02-29", "%Y-%m-%d").unwrap_err().massege, "time.parse: '2001-02-29' does not match format '%Y-%m-%d'.");
+        assert_eq!(parse_utc("2001-1-01", "%Y-%m-%d").unwrap_err().massege, "time.parse: '2001-1-01' does not match format '%Y-%m-%d'.");
+        assert_eq!(format_utc(0, "%q").unwrap_err().massege, "time.format got an invalid format '%q'.");
+    }
+
+    #[test]
+    fn error_test() {
+        let mut vm = VM::from(Chunk::new());
+        assert_eq!(error(&mut vm, "time.sleep(-1);"), "time.sleep expects a non-negative count but got -1.");
+        assert_eq!(error(&mut vm, &format!("time.sleep(1{});", "0".repeat(30))), "time.sleep got a duration that is too long: 1000000000000000000000000000000.");
+        assert_eq!(error(&mut vm, "time.format(10000000000000000000);"), "time.format expects a timestamp between -62167219200 and 253402300799 but got 10000000000000000000.");
+    }
+}
@@ -0,0 +1,124 @@
+use std::{collections::BTreeMap, env};
+use crate::{chunk::{convert::{FromValue, IntoValue}, value::*}, interpreter_error::catalog::*};
+use crate::stdlib::{fs, io, json, math, re, time};
+use super::{vm::VM, runtime_error::{ErrorKind, RuntimeError}};
+
+// 每个 VM 都带有的全局函数
+pub fn define_builtins(vm: &mut VM) {
+    vm.define_native("arg_count", 0, arg_count);
+    vm.define_native("arg", 1, arg);
+    vm.define_native("env", 1, env_var);
+    vm.define_native("exit", 1, exit);
+
+    math::define(vm);
+    io::define(vm);
+    fs::define(vm);
+    json::define(vm);
+    time::define(vm);
+    re::define(vm);
+}
+
+// 以映射表示的模块，成员通过 DOT 访问，如 math.sqrt(2)
+pub struct Module {
+    name: &'static str,
+    members: BTreeMap<String, Value>,
+}
+
+impl Module {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, members: BTreeMap::new() }
+    }
+
+    // 本地函数以 "模块名.函数名" 报告错误
+    pub fn native(&mut self, name: &str, arity: usize, fun: NativeFn) -> &mut Self {
+        let native = Native { name: format!("{}.{}", self.name, name), arity: Some(arity), fun };
+        self.members.insert(name.to_string(), Value::Native(native));
+        self
+    }
+
+    pub fn native_variadic(&mut self, name: &str, fun: NativeFn) -> &mut Self {
+        let native = Native { name: format!("{}.{}", self.name, name), arity: None, fun };
+        self.members.insert(name.to_string(), Value::Native(native));
+        self
+    }
+
+    pub fn constant(&mut self, name: &str, val: Value) -> &mut Self {
+        self.members.insert(name.to_string(), val);
+        self
+    }
+
+    // 作为全局变量注册
+    pub fn define(&mut self, vm: &mut VM) {
+        vm.set_global(self.name, Value::map(std::mem::take(&mut self.members)));
+    }
+}
+
+// 沙箱关闭了 io 能力时拒绝执行
+pub fn require_io(vm: &VM, name: &str) -> Result<(), RuntimeError> {
+    if !vm.io_allowed() {
+        return Err(RuntimeError::new(trf("{} is not allowed in this sandbox.", &[&name])));
+    }
+    Ok(())
+}
+
+// 操作系统返回的错误，附带出错的路径
+pub fn os_error(name: &str, path: &str, e: std::io::Error) -> RuntimeError {
+    RuntimeError::new(trf("{} failed on '{}': {}", &[&name, &path, &e]))
+}
+
+// 可选参数的个数检查，argc 为实际的参数个数
+pub fn arity_between(name: &str, argc: usize, min: usize, max: usize) -> Result<(), RuntimeError> {
+    if argc < min || argc > max {
+        return Err(RuntimeError::with_kind(ErrorKind::Type, trf("{} expects {} to {} arguments but got {}.", &[&name, &min, &max, &argc])));
+    }
+    Ok(())
+}
+
+// 参数类型不符时的错误，index 从 1 开始
+pub fn type_error(name: &str, index: usize, expected: &str, actual: &Value) -> RuntimeError {
+    RuntimeError::with_kind(ErrorKind::Type, trf("{} expects argument {} to be {} but got {}.", &[&name, &index, &expected, &actual.type_name()]))
+}
+
+// 按类型取参数，类型不符时报告参数位置
+pub fn param<T: FromValue>(name: &str, index: usize, v: &Value) -> Result<T, RuntimeError> {
+    T::from_value(v).map_err(|e| RuntimeError::with_kind(ErrorKind::Type, trf("{} expects argument {} to be {} but got {}.", &[&name, &index, &e.expected, &e.actual])))
+}
+
+// 取整数参数，带小数部分的数字也视为类型错误
+pub fn integer(name: &str, index: usize, v: &Value) -> Result<i64, RuntimeError> {
+    match v {
+        Value::Double(d) if d.fract() == 0.0 && d.is_finite() => Ok(*d as i64),
+        Value::Double(d) => Err(RuntimeError::with_kind(ErrorKind::Type, trf("{} expects an integer but got {}.", &[&name, d]))),
+        _ => Err(type_error(name, index, "number", v)),
+    }
+}
+
+// 脚本参数的个数
+fn arg_count(vm: &mut VM, _: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Double(vm.args().len() as f64))
+}
+
+// 第 i 个脚本参数，不存在时为 null
+fn arg(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let i = integer("arg", 1, &args[0])?;
+    Ok(match usize::try_from(i).ok().and_then(|i| vm.args().get(i)) {
+        Some(a) => Value::String(a.clone()),
+        None => Value::Null,
+    })
+}
+
+// 环境变量的值，未设置时为 null
+fn env_var(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let name: String = param("env", 1, &args[0])?;
+    Ok(env::var(name).ok().into_value())
+}
+
+// 退出码须在进程退出状态的范围内，不做截断
+fn exit(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let code = integer("exit", 1, &args[0])?;
+    if !(0..=255).contains(&code) {
+        return Err(RuntimeError::new(trf("{} expects a code between {} and {} but got {}.", &[&"exit", &0, &255, &code])));
+    }
+    vm.exit(code as i32);
+    Ok(Value::Null)
+}
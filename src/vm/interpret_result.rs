@@ -1,6 +1,11 @@
+use crate::compiler::compile_error::CompileError;
+use super::runtime_error::RuntimeError;
+
 #[allow(non_camel_case_types)]
+#[derive(Debug, PartialEq)]
 pub enum InterpretResult {
     Ok,
-    COMPLE_ERROR,
-    RUNTIME_ERROR,
-}
\ No newline at end of file
+    COMPLE_ERROR(Vec<CompileError>), // 一次编译中发现的全部语法错误
+    RUNTIME_ERROR(RuntimeError),
+    EXIT(i32), // 脚本调用 exit(code) 结束
+}
@@ -0,0 +1,110 @@
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+use crate::{chunk::value::*, interpreter_error::catalog::*};
+use super::runtime_error::{ErrorKind, RuntimeError};
+
+// for 循环的迭代状态。每一项为 (键, 值)：映射的键为键名，其余为从 0 开始的序号
+pub enum Iter {
+    List { list: Rc<RefCell<Vec<Value>>>, index: usize },
+    // 按开始迭代时的键依次取值
+    Map { map: Rc<RefCell<BTreeMap<String, Value>>>, keys: Vec<String>, index: usize },
+    Chars { chars: Vec<char>, index: usize },
+    Range { range: Range, index: usize },
+    // 实现了 next 方法的对象，由 VM 调用，返回 null 时结束
+    Object { next: Value, index: usize },
+}
+
+// 迭代状态按身份比较
+impl PartialEq for Iter {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Iter {
+    // 内置类型的迭代状态；对象的 iter 与 next 方法由 VM 处理
+    pub fn new(v: &Value) -> Result<Self, RuntimeError> {
+        Ok(match v {
+            Value::List(l) => Self::List { list: l.clone(), index: 0 },
+            Value::Map(m) => Self::Map { map: m.clone(), keys: m.borrow().keys().cloned().collect(), index: 0 },
+            Value::String(s) => Self::Chars { chars: s.chars().collect(), index: 0 },
+            Value::Range(r) => Self::Range { range: *r, index: 0 },
+            _ => return Err(RuntimeError::with_kind(ErrorKind::Type, trf("Can't iterate over {}.", &[&v.type_name()]))),
+        })
+    }
+
+    // 取出下一项；列表在迭代中变长或变短时以当时的长度为准
+    pub fn advance(&mut self) -> Option<(Value, Value)> {
+        match self {
+            Self::List { list, index } => {
+                let v = list.borrow().get(*index).cloned()?;
+                *index += 1;
+                Some((Value::Double((*index - 1) as f64), v))
+            },
+            Self::Map { map, keys, index } => loop {
+                let key = keys.get(*index)?;
+                *index += 1;
+                if let Some(v) = map.borrow().get(key) {
+                    return Some((Value::String(key.clone()), v.clone()));
+                }
+            },
+            Self::Chars { chars, index } => {
+                let c = chars.get(*index)?;
+                *index += 1;
+                Some((Value::Double((*index - 1) as f64), Value::String(c.to_string())))
+            },
+            Self::Range { range, index } => {
+                let v = range.get(*index)?;
+                *index += 1;
+                Some((Value::Double((*index - 1) as f64), Value::Double(v)))
+            },
+            Self::Object { .. } => None,
+        }
+    }
+
+    // 对象的 next 方法返回的值，null 表示结束
+    pub fn object_item(&mut self, v: Value) -> Option<(Value, Value)> {
+        match self {
+            Self::Object { index, .. } if v != Value::Null => {
+                *index += 1;
+                Some((Value::Double((*index - 1) as f64), v))
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn collect(v: &Value) -> Vec<String> {
+        let mut it = Iter::new(v).unwrap();
+        let mut ret = Vec::new();
+        while let Some((k, v)) = it.advance() {
+            ret.push(format!("{}={}", k, v));
+        }
+        ret
+    }
+
+    #[test]
+    fn iter_test() {
+        assert_eq!(collect(&Value::list(vec![Value::Bool(true), Value::Null])), ["0=true", "1=null"]);
+        assert_eq!(collect(&Value::String("中a".to_string())), ["0=中", "1=a"]);
+        let mut m = BTreeMap::new();
+        m.insert("b".to_string(), Value::Double(2.0));
+        m.insert("a".to_string(), Value::Double(1.0));
+        assert_eq!(collect(&Value::map(m)), ["a=1", "b=2"]);
+        assert_eq!(Iter::new(&Value::Null).err().unwrap().massege, "Can't iterate over null.");
+    }
+
+    #[test]
+    fn range_test() {
+        let values = |r: Range| collect(&Value::Range(r)).iter().map(|s| s.split('=').nth(1).unwrap().to_string()).collect::<Vec<_>>().join(",");
+        assert_eq!(values(Range::new(0.0, 3.0, false)), "0,1,2");
+        assert_eq!(values(Range::new(0.0, 3.0, true)), "0,1,2,3");
+        assert_eq!(values(Range::new(3.0, 0.0, false)), "");
+        assert_eq!(values(Range { step: 0.5, ..Range::new(0.0, 1.0, true) }), "0,0.5,1");
+        assert_eq!(values(Range { step: -2.0, ..Range::new(5.0, 0.0, false) }), "5,3,1");
+        assert_eq!(values(Range { step: 0.1, ..Range::new(0.0, 0.3, false) }).split(',').count(), 3);
+    }
+}
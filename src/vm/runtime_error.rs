@@ -0,0 +1,86 @@
+use std::{collections::BTreeMap, fmt::Display};
+use crate::{chunk::{convert::ConvertError, value::Value}, interpreter_error::catalog::*};
+
+// 运行时错误的类别，被 catch 捕获时作为错误对象的 kind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    Error,
+    Type, // 操作数或参数的类型不符、调用了非函数
+    Index, // 下标越界
+    Division, // 除以零
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Error => "Error",
+            Self::Type => "TypeError",
+            Self::Index => "IndexError",
+            Self::Division => "DivisionError",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Error" => Some(Self::Error),
+            "TypeError" => Some(Self::Type),
+            "IndexError" => Some(Self::Index),
+            "DivisionError" => Some(Self::Division),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub line: u32, // 出错指令所在行，由 VM 填写
+    pub massege: String,
+    pub kind: ErrorKind,
+}
+
+impl RuntimeError {
+    // 本地函数只需给出错误信息，行号由 VM 补上
+    pub fn new(massege: impl Into<String>) -> Self {
+        Self::with_kind(ErrorKind::Error, massege)
+    }
+
+    pub fn with_kind(kind: ErrorKind, massege: impl Into<String>) -> Self {
+        Self { line: 0, massege: massege.into(), kind }
+    }
+
+    // catch 得到的错误对象：{"kind": ..., "line": ..., "message": ...}
+    pub fn to_value(&self) -> Value {
+        let mut m = BTreeMap::new();
+        m.insert("kind".to_string(), Value::String(self.kind.name().to_string()));
+        m.insert("line".to_string(), Value::Double(self.line as f64));
+        m.insert("message".to_string(), Value::String(self.massege.clone()));
+        Value::map(m)
+    }
+
+    // 再次抛出的错误对象还原为原来的错误
+    pub fn from_value(v: &Value) -> Option<Self> {
+        let m = match v {
+            Value::Map(m) => m.borrow(),
+            _ => return None,
+        };
+        match (m.get("kind"), m.get("line"), m.get("message")) {
+            (Some(Value::String(kind)), Some(Value::Double(line)), Some(Value::String(massege))) if m.len() == 3 => {
+                Some(Self { line: *line as u32, massege: massege.clone(), kind: ErrorKind::from_name(kind)? })
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", trf("[line {}] runtime error: {}", &[&self.line, &self.massege]))
+    }
+}
+
+// 本地函数中可以直接对转换结果使用 `?`
+impl From<ConvertError> for RuntimeError {
+    fn from(e: ConvertError) -> Self {
+        RuntimeError::with_kind(ErrorKind::Type, e.to_string())
+    }
+}
@@ -1,146 +1,994 @@
-use crate::{debug_tools::disassemble, chunk::{chunk::*, op::Opcode, value::*,}};
-use super::interpret_result::*;
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, fs, io::{self, BufRead, Write}, path::{Path, PathBuf}, rc::Rc};
+use crate::{debug_tools::disassemble::Instruction, chunk::{chunk::*, op::Opcode, value::*, verifier::{self, VerifyError}}, compiler::{compiler::Compiler, compile_error::CompileError}, lexical_analysis::{keywords::KeywordSet, scanner::Scanner}};
+use crate::interpreter_error::catalog::*;
+use crate::stdlib::{math::Rng, range, re, string, time::{Clock, SystemClock}};
+use super::{builtins, interpret_result::*, iterator::Iter, runtime_error::{ErrorKind, RuntimeError}};
+
+// 调用深度上限
+const FRAMES_MAX: usize = 256;
+
+// 进入 finally 时压入的完成方式，正常执行到 finally 时为 null
+const COMPLETE_THROW: f64 = 1.0;
+const COMPLETE_RETURN: f64 = 2.0;
+const COMPLETE_JUMP: f64 = 3.0; // break 或 continue，值为跳出 finally 后继续执行的位置
+
+struct CallFrame {
+    function: Rc<Function>,
+    ip: usize,
+    base: usize, // 局部变量槽位的起点；函数的第 0 个槽位是被调用者本身
+}
+
+// try 语句安装的异常处理器
+struct Handler {
+    frames: usize, // 安装时的调用深度，处理器属于第 frames - 1 层调用帧
+    stack_len: usize, // 跳转到处理代码前栈回到的高度
+    ip: Option<usize>, // catch 或 finally 代码的位置，try 语句没有该子句时为 None
+    finally: bool,
+}
+
+// import 载入的模块，编号从 1 开始
+struct LoadedModule {
+    path: PathBuf, // 规范化后的路径，用于缓存；载入失败后清空，下次重新载入
+    name: String, // 用于错误信息
+    globals: HashMap<String, Value>,
+    exports: Option<Value>, // 执行完毕后为导出的映射，执行中为 None
+}
 
 pub struct VM {
-    chunk: Chunk,
-    ip: Option<usize>,
-    
+    frames: Vec<CallFrame>,
+    op_start: usize, // 正在执行的指令的字节偏移，用于报告错误所在行
+    verified: bool, // 已通过 verifier 检查时跳过逐条指令的栈上限检查
+
     debug: bool,
 
     stack: Vec<Value>,
     stack_max: usize,
+    handlers: Vec<Handler>,
+    thrown: Option<Value>, // throw 抛出的值，交给外层的处理器
+
+    globals: HashMap<String, Value>,
+    warnings: Vec<CompileError>, // 最近一次编译产生的警告
+    args: Vec<String>, // 脚本路径之后的命令行参数
+    exit_code: Option<i32>, // 脚本请求退出时的状态码
+    keywords: KeywordSet, // 源码未声明关键字集时使用
+    result: Value, // 最近一次执行或调用的返回值
+    out: Box<dyn Write>, // print 与调试输出的去处
+    rng: Rng, // math.random 等使用的伪随机数发生器
+    input: Box<dyn BufRead>, // io.read_line 等读取的来源
+    io_allowed: bool, // 沙箱关闭时 io 与 fs 模块的函数一律报错
+    clock: Box<dyn Clock>, // time 模块的时间来源
+    modules: Vec<LoadedModule>,
+    script_path: Option<PathBuf>, // 主脚本的路径，其所在目录是相对导入的起点
+    search_paths: Vec<PathBuf>, // 在导入者所在目录之后依次查找模块
 }
 
 impl VM {
     pub fn from(chunk: Chunk) -> Self {
-        Self {
-            ip: if chunk.op_len() > 0 { Some(0) }else { None },
-            chunk,
+        let mut vm = Self {
+            frames: Vec::new(),
+            op_start: 0,
+            verified: false,
             debug: false,
             stack: Vec::new(),
             stack_max: 128,
-        }
+            handlers: Vec::new(),
+            thrown: None,
+            globals: HashMap::new(),
+            warnings: Vec::new(),
+            keywords: KeywordSet::english(),
+            args: Vec::new(),
+            exit_code: None,
+            result: Value::Null,
+            out: Box::new(io::sink()),
+            rng: Rng::default(),
+            input: Box::new(io::empty()),
+            io_allowed: true,
+            clock: Box::new(SystemClock::default()),
+            modules: Vec::new(),
+            script_path: None,
+            search_paths: Vec::new(),
+        };
+        vm.set_chunk(chunk);
+        builtins::define_builtins(&mut vm);
+        vm
     }
 
     pub fn set_stack_max(&mut self, size: usize) {
         self.stack_max = size;
     }
 
-    pub fn set_chunk(& mut self, c: Chunk) -> &mut Self {
-        self.chunk = c;
-        if self.chunk.op_len() > 0 {
-            self.ip = Some(0);
+    pub fn set_chunk(& mut self, mut c: Chunk) -> &mut Self {
+        c.set_dense_lines(self.debug);
+        self.stack.clear();
+        self.frames.clear();
+        if c.op_len() > 0 {
+            let script = Rc::new(Function::new("script", 0, c));
+            self.frames.push(CallFrame { function: script, ip: 0, base: 0 });
         }
+        self.verified = false;
         self
     }
 
+    // 检查通过后载入 chunk，所需栈深度超过 stack_max 时拒绝
+    pub fn set_verified_chunk(&mut self, c: Chunk) -> Result<&mut Self, VerifyError> {
+        let needed = verifier::verify(&c)?;
+        if needed > self.stack_max {
+            return Err(VerifyError::StackOverflow { needed, max: self.stack_max });
+        }
+
+        self.set_chunk(c);
+        self.stack.reserve(needed);
+        self.verified = true;
+        Ok(self)
+    }
+
     pub fn set_debug(&mut self, f: bool) -> &mut Self {
         self.debug = f;
+        // 调试时每一步都要查询行号
+        if let Some(script) = self.frames.first_mut().and_then(|frame| Rc::get_mut(&mut frame.function)) {
+            script.chunk.set_dense_lines(f);
+        }
+        self
+    }
+
+    pub fn set_keywords(&mut self, keywords: KeywordSet) -> &mut Self {
+        self.keywords = keywords;
+        self
+    }
+
+    // print 语句与 --trace 的输出写到 out，默认丢弃；命令行程序设为标准输出
+    pub fn set_output(&mut self, out: Box<dyn Write>) -> &mut Self {
+        self.out = out;
+        self
+    }
+
+    // io.read_line 与 io.lines 从 input 读取，默认没有输入；命令行程序设为标准输入
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) -> &mut Self {
+        self.input = input;
+        self
+    }
+
+    pub fn input(&mut self) -> &mut dyn BufRead {
+        &mut *self.input
+    }
+
+    // 嵌入时可以关闭文件系统与标准输入的访问
+    pub fn set_io_allowed(&mut self, allowed: bool) -> &mut Self {
+        self.io_allowed = allowed;
         self
     }
 
+    pub fn io_allowed(&self) -> bool {
+        self.io_allowed
+    }
+
+    // 替换 time 模块使用的时钟，测试中可使结果确定
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) -> &mut Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&mut self) -> &mut dyn Clock {
+        &mut *self.clock
+    }
+
+    // 主脚本中的相对导入从 path 所在目录开始查找，未设置时为当前目录
+    pub fn set_script_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.script_path = Some(path.into());
+        self
+    }
+
+    pub fn add_search_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.search_paths.push(path.into());
+        self
+    }
+
+    // 以固定的种子重置伪随机数发生器，使结果可以复现
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    // 编译并执行源码，全局变量在多次调用之间保留
+    pub fn interpret(&mut self, code: &str) -> InterpretResult {
+        let keywords = KeywordSet::from_pragma(code).unwrap_or_else(|| self.keywords.clone());
+        let mut scanner = Scanner::with_keywords(code, keywords);
+        let result = Compiler::new(&mut scanner).compile();
+        self.warnings = scanner.warnings().to_vec();
+
+        match result {
+            Ok(chunk) => match self.set_verified_chunk(chunk) {
+                Ok(_) => self.run(),
+                Err(e) => InterpretResult::RUNTIME_ERROR(RuntimeError::new(trf("invalid bytecode: {}", &[&e]))),
+            },
+            Err(errors) => InterpretResult::COMPLE_ERROR(errors),
+        }
+    }
+
+    // 以 args 调用函数或本地函数，结果由 take_result 取得
+    pub fn call(&mut self, callee: Value, args: &[Value]) -> InterpretResult {
+        let (stack_len, depth) = (self.stack.len(), self.frames.len());
+
+        self.stack.push(callee);
+        self.stack.extend_from_slice(args);
+        let result = match self.call_value(args.len()) {
+            Ok(_) if self.frames.len() > depth => self.execute(depth),
+            Ok(_) => {
+                self.result = self.stack.pop().unwrap_or(Value::Null);
+                match self.exit_code.take() {
+                    Some(code) => InterpretResult::EXIT(code),
+                    None => InterpretResult::Ok,
+                }
+            },
+            Err(e) => e,
+        };
+
+        // 出错时丢弃未完成的调用
+        self.stack.truncate(stack_len);
+        self.frames.truncate(depth);
+        self.handlers.retain(|h| h.frames <= depth);
+        result
+    }
+
+    pub fn take_result(&mut self) -> Value {
+        std::mem::replace(&mut self.result, Value::Null)
+    }
+
     pub fn run(&mut self) -> InterpretResult {
-        if self.ip.eq(&None) {
-            return InterpretResult::RUNTIME_ERROR;
+        if self.frames.is_empty() {
+            return self.error(tr("Nothing to run."));
+        }
+
+        let result = self.execute(0);
+        if !matches!(result, InterpretResult::Ok) {
+            self.frames.clear();
+            self.stack.clear();
+            self.handlers.clear();
+        }
+        result
+    }
+
+    // 执行到调用栈回到 depth 层为止；运行时错误与 throw 交给本次执行中安装的处理器
+    fn execute(&mut self, depth: usize) -> InterpretResult {
+        loop {
+            match self.dispatch(depth) {
+                InterpretResult::RUNTIME_ERROR(e) => {
+                    let exception = self.thrown.take().unwrap_or_else(|| e.to_value());
+                    if !self.throw(exception.clone(), depth) {
+                        // 嵌套的执行（如 for 循环调用的 next 方法）把异常交给外层的处理器
+                        if depth > 0 {
+                            self.thrown = Some(exception);
+                        }
+                        return InterpretResult::RUNTIME_ERROR(e);
+                    }
+                },
+                r => return r,
+            }
+        }
+    }
+
+    // 展开到最近的处理器：catch 得到异常，finally 得到完成方式与异常
+    fn throw(&mut self, exception: Value, depth: usize) -> bool {
+        let (handler, ip) = loop {
+            let handler = match self.handlers.last() {
+                Some(h) if h.frames > depth => self.handlers.pop().unwrap(),
+                _ => return false,
+            };
+            if let Some(ip) = handler.ip {
+                break (handler, ip);
+            }
+        };
+        self.frames.truncate(handler.frames);
+        self.stack.truncate(handler.stack_len);
+        if handler.finally {
+            self.stack.push(Value::Double(COMPLETE_THROW));
         }
-        
+        self.stack.push(exception);
+        self.frame_mut().ip = ip;
+        true
+    }
+
+    // 从当前函数返回；先执行函数中尚未结束的 finally，返回值暂存在栈上。
+    // 调用栈回到 depth 层时返回执行结果
+    fn return_from(&mut self, result: Value, depth: usize) -> Option<InterpretResult> {
+        while let Some(h) = self.handlers.last() {
+            if h.frames < self.frames.len() {
+                break;
+            }
+            let handler = self.handlers.pop().unwrap();
+            if let (true, Some(ip)) = (handler.finally, handler.ip) {
+                self.stack.truncate(handler.stack_len);
+                self.stack.push(Value::Double(COMPLETE_RETURN));
+                self.stack.push(result);
+                self.frame_mut().ip = ip;
+                return None;
+            }
+        }
+
+        let frame = self.frames.pop().unwrap();
+        self.stack.truncate(frame.base);
+        if self.frames.len() == depth {
+            self.result = result;
+            return Some(InterpretResult::Ok);
+        }
+        self.stack.push(result);
+        None
+    }
+
+    fn dispatch(&mut self, depth: usize) -> InterpretResult {
         loop {
+            let start = self.frame().ip;
+            self.op_start = start;
             if self.debug {
-                if self.ip.ne(&None) {
-                    println!("stack:{:?}", self.stack);
-                    disassemble::disassemble_instruction(&self.chunk, self.ip.unwrap());
-                }else{
-                    return InterpretResult::RUNTIME_ERROR;
-                }
+                let _ = writeln!(self.out, "stack:{:?}", self.stack);
+                let chunk = &self.frames.last().unwrap().function.chunk;
+                let prev_line = if start > 0 { chunk.get_line(start - 1) }else { None };
+                let _ = Instruction::decode(chunk, start).write_text(prev_line, &mut self.out);
             }
 
             let a = match self.advance() {
-                None => {
-                    return InterpretResult::RUNTIME_ERROR;
-                },
-                Some(val) => {
-                    val
-                }
+                None => return self.error(tr("Invalid instruction.")),
+                Some(val) => val,
             };
 
-            match *a {
-                Opcode::OP_CONST(val) => {
-                    if self.stack.len() == self.stack_max {
-                        return InterpretResult::RUNTIME_ERROR;
+            match a {
+                Opcode::OP_CONST(val) | Opcode::OP_CONST_LONG(val) => {
+                    let v = self.chunk().get_val(val).clone();
+                    if let Err(e) = self.push(v) {
+                        return e;
+                    }
+                },
+                Opcode::OP_NULL => {
+                    if let Err(e) = self.push(Value::Null) {
+                        return e;
+                    }
+                },
+                Opcode::OP_TRUE => {
+                    if let Err(e) = self.push(Value::Bool(true)) {
+                        return e;
+                    }
+                },
+                Opcode::OP_FALSE => {
+                    if let Err(e) = self.push(Value::Bool(false)) {
+                        return e;
                     }
-
-                    self.stack.push(self.chunk.get_val(val).clone());
                 },
                 Opcode::OP_RETURN => {
-                    if self.stack.is_empty() {
-                        println!("stack is empty!");
-                    }else {
-                        println!("stack top = {}", self.stack.pop().unwrap().to_string());
+                    let result = match self.stack.pop() {
+                        Some(v) => v,
+                        None => return self.error(tr("Stack underflow.")),
+                    };
+                    if let Some(r) = self.return_from(result, depth) {
+                        return r;
                     }
-
-                    return InterpretResult::Ok;
                 },
                 Opcode::OP_NEGATE => {
-                    if self.stack.is_empty() {
-                        return InterpretResult::RUNTIME_ERROR;
+                    if self.underflow(1) {
+                        return self.error(tr("Stack underflow."));
                     }
 
                     let a = self.stack.last_mut().unwrap();
                     match a {
                         Value::Double(d) => *a = Value::Double(-(*d)),
-                        _ => return InterpretResult::RUNTIME_ERROR,
+                        _ => return self.error_of(ErrorKind::Type, tr("Operand must be a number.")),
                     }
                 },
-                Opcode::OP_ADD => {
-                    if self.stack.len() < 2 {
-                        return InterpretResult::RUNTIME_ERROR;
+                Opcode::OP_NOT => {
+                    if self.underflow(1) {
+                        return self.error(tr("Stack underflow."));
                     }
 
-                    let right = self.stack.pop().unwrap();
-                    let left = self.stack.pop().unwrap();
-                    self.stack.push(left.add(&right));
+                    let a = self.stack.last_mut().unwrap();
+                    *a = Value::Bool(a.is_falsey());
                 },
-                Opcode::OP_SUB => {
-                    if self.stack.len() < 2 {
-                        return InterpretResult::RUNTIME_ERROR;
+                Opcode::OP_ADD | Opcode::OP_SUB | Opcode::OP_MULTIPLY | Opcode::OP_DIVIDE | Opcode::OP_EQUAL => {
+                    if self.underflow(2) {
+                        return self.error(tr("Stack underflow."));
                     }
 
+                    if let (Opcode::OP_DIVIDE, Some(Value::Double(d))) = (a, self.stack.last()) {
+                        if *d == 0.0 && matches!(self.stack[self.stack.len() - 2], Value::Double(_)) {
+                            return self.error_of(ErrorKind::Division, tr("Division by zero."));
+                        }
+                    }
                     let right = self.stack.pop().unwrap();
                     let left = self.stack.pop().unwrap();
-                    self.stack.push(left.sub(&right));
+                    // 算术运算只用于数字，加法还可以连接两个字符串
+                    let valid = match (&left, &right) {
+                        (Value::Double(_), Value::Double(_)) => true,
+                        (Value::String(_), Value::String(_)) => matches!(a, Opcode::OP_ADD),
+                        _ => false,
+                    };
+                    if !valid && !matches!(a, Opcode::OP_EQUAL) {
+                        return self.error_of(ErrorKind::Type, tr("Operands must be numbers."));
+                    }
+                    self.stack.push(match a {
+                        Opcode::OP_ADD => left.add(&right),
+                        Opcode::OP_SUB => left.sub(&right),
+                        Opcode::OP_MULTIPLY => left.multiply(&right),
+                        Opcode::OP_DIVIDE => left.divide(&right),
+                        _ => Value::Bool(left.equal(&right)),
+                    });
                 },
-                Opcode::OP_MULTIPLY => {
-                    if self.stack.len() < 2 {
-                        return InterpretResult::RUNTIME_ERROR;
+                Opcode::OP_GREATER | Opcode::OP_LESS => {
+                    if self.underflow(2) {
+                        return self.error(tr("Stack underflow."));
                     }
 
                     let right = self.stack.pop().unwrap();
                     let left = self.stack.pop().unwrap();
-                    self.stack.push(left.multiply(&right));
+                    match (left, right) {
+                        (Value::Double(l), Value::Double(r)) => {
+                            let b = if let Opcode::OP_GREATER = a { l > r }else { l < r };
+                            self.stack.push(Value::Bool(b));
+                        },
+                        _ => return self.error_of(ErrorKind::Type, tr("Operands must be numbers.")),
+                    }
+                },
+                Opcode::OP_PRINT => {
+                    match self.stack.pop() {
+                        Some(v) => {
+                            if writeln!(self.out, "{}", v).is_err() {
+                                return self.error(tr("output error!"));
+                            }
+                        },
+                        None => return self.error(tr("Stack underflow.")),
+                    }
+                },
+                Opcode::OP_POP => {
+                    if self.stack.pop().is_none() {
+                        return self.error(tr("Stack underflow."));
+                    }
+                },
+                Opcode::OP_DEFINE_GLOBAL(index) => {
+                    let name = self.chunk().get_val(index).to_string();
+                    match self.stack.pop() {
+                        Some(v) => {
+                            self.globals_mut().insert(name, v);
+                        },
+                        None => return self.error(tr("Stack underflow.")),
+                    }
+                },
+                Opcode::OP_GET_GLOBAL(index) => {
+                    let name = self.chunk().get_val(index).to_string();
+                    let v = match self.lookup_global(&name) {
+                        Some(v) => v.clone(),
+                        None => return self.error(trf("Undefined variable '{}'.", &[&name])),
+                    };
+                    if let Err(e) = self.push(v) {
+                        return e;
+                    }
+                },
+                Opcode::OP_SET_GLOBAL(index) => {
+                    let name = self.chunk().get_val(index).to_string();
+                    let v = match self.stack.last() {
+                        Some(v) => v.clone(),
+                        None => return self.error(tr("Stack underflow.")),
+                    };
+                    match self.globals_mut().get_mut(&name) {
+                        Some(old) => *old = v,
+                        None => return self.error(trf("Undefined variable '{}'.", &[&name])),
+                    }
+                },
+                Opcode::OP_GET_LOCAL(slot) => {
+                    let v = match self.stack.get(self.frame().base + slot) {
+                        Some(v) => v.clone(),
+                        None => return self.error(tr("Stack underflow.")),
+                    };
+                    if let Err(e) = self.push(v) {
+                        return e;
+                    }
+                },
+                Opcode::OP_SET_LOCAL(slot) => {
+                    let v = match self.stack.last() {
+                        Some(v) => v.clone(),
+                        None => return self.error(tr("Stack underflow.")),
+                    };
+                    let base = self.frame().base;
+                    match self.stack.get_mut(base + slot) {
+                        Some(old) => *old = v,
+                        None => return self.error(tr("Stack underflow.")),
+                    }
+                },
+                Opcode::OP_JUMP(offset) => {
+                    self.frame_mut().ip += offset;
+                },
+                Opcode::OP_JUMP_IF_FALSE(offset) => {
+                    match self.stack.last() {
+                        Some(v) => {
+                            if v.is_falsey() {
+                                self.frame_mut().ip += offset;
+                            }
+                        },
+                        None => return self.error(tr("Stack underflow.")),
+                    }
+                },
+                Opcode::OP_LOOP(offset) => {
+                    match self.frame().ip.checked_sub(offset) {
+                        Some(ip) => self.frame_mut().ip = ip,
+                        None => return self.error(tr("Invalid jump.")),
+                    }
+                },
+                Opcode::OP_GET_PROPERTY(index) => {
+                    let name = self.chunk().get_val(index).to_string();
+                    let v = match self.stack.pop() {
+                        Some(v) => v,
+                        None => return self.error(tr("Stack underflow.")),
+                    };
+                    let prop = match &v {
+                        Value::Map(m) => m.borrow().get(&name).cloned(),
+                        Value::String(_) => string::method(&name).map(|method| Value::Bound(Rc::new(Bound { receiver: v.clone(), method }))),
+                        Value::Regex(_) => re::method(&name).map(|method| Value::Bound(Rc::new(Bound { receiver: v.clone(), method }))),
+                        Value::Range(_) => range::method(&name).map(|method| Value::Bound(Rc::new(Bound { receiver: v.clone(), method }))),
+                        _ => None,
+                    };
+                    match prop {
+                        Some(p) => self.stack.push(p),
+                        None => return self.error(trf("Undefined property '{}' on {}.", &[&name, &v.type_name()])),
+                    }
+                },
+                Opcode::OP_IMPORT(index) => {
+                    let spec = self.chunk().get_val(index).to_string();
+                    let module = match self.import(&spec) {
+                        Ok(m) => m,
+                        Err(e) => return e,
+                    };
+                    if let Err(e) = self.push(module) {
+                        return e;
+                    }
+                },
+                Opcode::OP_CALL(argc) => {
+                    if let Err(e) = self.call_value(argc) {
+                        return e;
+                    }
+                    if let Some(code) = self.exit_code.take() {
+                        return InterpretResult::EXIT(code);
+                    }
+                },
+                Opcode::OP_TRY(offset) | Opcode::OP_TRY_FINALLY(offset) => {
+                    let (frames, stack_len) = (self.frames.len(), self.stack.len());
+                    let ip = (offset > 0).then(|| self.frame().ip + offset);
+                    self.handlers.push(Handler { frames, stack_len, ip, finally: matches!(a, Opcode::OP_TRY_FINALLY(_)) });
+                },
+                Opcode::OP_END_TRY => {
+                    self.handlers.pop();
+                },
+                Opcode::OP_THROW => {
+                    let v = match self.stack.pop() {
+                        Some(v) => v,
+                        None => return self.error(tr("Stack underflow.")),
+                    };
+                    return self.raise(v);
+                },
+                Opcode::OP_END_FINALLY => {
+                    if self.underflow(2) {
+                        return self.error(tr("Stack underflow."));
+                    }
+                    let v = self.stack.pop().unwrap();
+                    // 完成方式只能是正常执行（两个 null）或处理器压入的标记，其他值不予解释
+                    match self.stack.pop().unwrap() {
+                        Value::Null if matches!(v, Value::Null) => {},
+                        Value::Double(c) if c == COMPLETE_THROW => return self.raise(v),
+                        Value::Double(c) if c == COMPLETE_RETURN => {
+                            if let Some(r) = self.return_from(v, depth) {
+                                return r;
+                            }
+                        },
+                        Value::Double(c) if c == COMPLETE_JUMP => match v {
+                            Value::Double(ip) if self.chunk().is_resume(ip as usize) => self.frame_mut().ip = ip as usize,
+                            _ => return self.error(tr("Invalid instruction.")),
+                        },
+                        _ => return self.error(tr("Invalid instruction.")),
+                    }
+                },
+                // break 或 continue 跳出 try 语句：移除 finally 的处理器，有 finally 时先执行它
+                Opcode::OP_FINALLY_JUMP => {
+                    if let Some(Handler { stack_len, ip: Some(ip), .. }) = self.handlers.pop() {
+                        let resume = self.frame().ip;
+                        self.stack.truncate(stack_len);
+                        self.stack.push(Value::Double(COMPLETE_JUMP));
+                        self.stack.push(Value::Double(resume as f64));
+                        self.frame_mut().ip = ip;
+                    }
                 },
-                Opcode::OP_DIVIDE => {
-                    if self.stack.len() < 2 {
-                        return InterpretResult::RUNTIME_ERROR;
+                Opcode::OP_RANGE | Opcode::OP_RANGE_INCLUSIVE => {
+                    if self.underflow(2) {
+                        return self.error(tr("Stack underflow."));
                     }
 
-                    let right = self.stack.pop().unwrap();
-                    let left = self.stack.pop().unwrap();
-                    self.stack.push(left.divide(&right));
+                    let end = self.stack.pop().unwrap();
+                    let start = self.stack.pop().unwrap();
+                    match (start, end) {
+                        (Value::Double(start), Value::Double(end)) => {
+                            self.stack.push(Value::Range(Range::new(start, end, matches!(a, Opcode::OP_RANGE_INCLUSIVE))));
+                        },
+                        _ => return self.error_of(ErrorKind::Type, tr("Range bounds must be numbers.")),
+                    }
+                },
+                Opcode::OP_ITER => {
+                    let v = match self.stack.pop() {
+                        Some(v) => v,
+                        None => return self.error(tr("Stack underflow.")),
+                    };
+                    let iter = match self.iterator(v) {
+                        Ok(iter) => iter,
+                        Err(e) => return e,
+                    };
+                    self.stack.push(Value::Iterator(Rc::new(RefCell::new(iter))));
+                },
+                Opcode::OP_FOR_ITER(offset) | Opcode::OP_FOR_ENTRY(offset) => {
+                    let iter = match self.stack.last() {
+                        Some(Value::Iterator(iter)) => iter.clone(),
+                        _ => return self.error(tr("Invalid instruction.")),
+                    };
+                    let item = match self.next_item(&iter) {
+                        Ok(item) => item,
+                        Err(e) => return e,
+                    };
+                    // 只有一个循环变量时，映射取键，其余取值
+                    match item {
+                        Some((key, v)) => {
+                            let pushed = match a {
+                                Opcode::OP_FOR_ENTRY(_) => self.push(key).and_then(|_| self.push(v)),
+                                _ if matches!(*iter.borrow(), Iter::Map { .. }) => self.push(key),
+                                _ => self.push(v),
+                            };
+                            if let Err(e) = pushed {
+                                return e;
+                            }
+                        },
+                        None => self.frame_mut().ip += offset,
+                    }
+                },
+            }
+        }
+    }
+
+    // 栈顶为被调用者与 argc 个参数：本地函数直接执行并压入结果，脚本函数压入新的调用帧
+    fn call_value(&mut self, argc: usize) -> Result<(), InterpretResult> {
+        if self.underflow(argc + 1) {
+            return Err(self.error(tr("Stack underflow.")));
+        }
+
+        let base = self.stack.len() - argc - 1;
+        match &self.stack[base] {
+            Value::Native(n) => {
+                let native = n.clone();
+                if let Some(arity) = native.arity {
+                    if arity != argc {
+                        return Err(self.error_of(ErrorKind::Type, trf("{} expects {} arguments but got {}.", &[&native.name, &arity, &argc])));
+                    }
+                }
+
+                let args = self.stack.split_off(base + 1);
+                self.stack.pop();
+                match (native.fun)(self, &args) {
+                    Ok(v) => self.stack.push(v),
+                    Err(e) => return Err(self.error_of(e.kind, e.massege)),
+                }
+            },
+            Value::Bound(b) => {
+                let bound = b.clone();
+                if let Some(arity) = bound.method.arity {
+                    if arity != argc {
+                        return Err(self.error_of(ErrorKind::Type, trf("{} expects {} arguments but got {}.", &[&bound.method.name, &arity, &argc])));
+                    }
+                }
+
+                let mut args = Vec::with_capacity(argc + 1);
+                args.push(bound.receiver.clone());
+                args.extend(self.stack.drain(base + 1..));
+                self.stack.pop();
+                match (bound.method.fun)(self, &args) {
+                    Ok(v) => self.stack.push(v),
+                    Err(e) => return Err(self.error_of(e.kind, e.massege)),
+                }
+            },
+            Value::Function(f) => {
+                let function = f.clone();
+                if function.arity != argc {
+                    return Err(self.error_of(ErrorKind::Type, trf("{} expects {} arguments but got {}.", &[&function.name, &function.arity, &argc])));
+                }
+                if self.frames.len() == FRAMES_MAX {
+                    return Err(self.error(tr("Stack overflow.")));
+                }
+                // 已检查的代码只在进入函数时确认栈空间足够
+                if self.verified {
+                    let needed = match verifier::verify_function(&function) {
+                        Ok(n) => n,
+                        Err(e) => return Err(self.error(trf("invalid bytecode: {}", &[&e]))),
+                    };
+                    if base + needed > self.stack_max {
+                        return Err(self.error(tr("Stack overflow.")));
+                    }
+                }
+                self.frames.push(CallFrame { function, ip: 0, base });
+            },
+            _ => return Err(self.error_of(ErrorKind::Type, tr("Can only call functions."))),
+        }
+        Ok(())
+    }
+
+    // 在当前指令中调用无参数的函数并取得返回值
+    fn call_nested(&mut self, callee: Value) -> Result<Value, InterpretResult> {
+        let (op_start, depth, stack_len) = (self.op_start, self.frames.len(), self.stack.len());
+        self.push(callee)?;
+        self.call_value(0)?;
+        let result = if self.frames.len() > depth {
+            match self.execute(depth) {
+                InterpretResult::Ok => Ok(self.take_result()),
+                r => {
+                    self.frames.truncate(depth);
+                    self.stack.truncate(stack_len);
+                    self.handlers.retain(|h| h.frames <= depth);
+                    Err(r)
                 },
             }
+        }else {
+            Ok(self.stack.pop().unwrap_or(Value::Null))
+        };
+        self.op_start = op_start;
+        match self.exit_code.take() {
+            Some(code) => Err(InterpretResult::EXIT(code)),
+            None => result,
         }
     }
 
-    pub fn advance(&mut self) -> Option<&Opcode> {
-        match &mut self.ip {
-            Some(p) => {
-                let ret = self.chunk.get_op(*p);
-                *p += 1;
-                ret
+    // for 循环的迭代器。带有 iter 方法的对象以其返回的对象迭代，带有 next 方法的对象本身就是迭代器
+    fn iterator(&mut self, v: Value) -> Result<Iter, InterpretResult> {
+        fn method(v: &Value, name: &str) -> Option<Value> {
+            match v {
+                Value::Map(m) => m.borrow().get(name).filter(|f| matches!(f, Value::Function(_) | Value::Native(_) | Value::Bound(_))).cloned(),
+                _ => None,
+            }
+        }
+
+        if let Some(iter) = method(&v, "iter") {
+            let object = self.call_nested(iter)?;
+            return match method(&object, "next") {
+                Some(next) => Ok(Iter::Object { next, index: 0 }),
+                None => Err(self.error_of(ErrorKind::Type, tr("iter() must return an object with a next method."))),
+            };
+        }
+        if let Some(next) = method(&v, "next") {
+            return Ok(Iter::Object { next, index: 0 });
+        }
+        Iter::new(&v).map_err(|e| self.error_of(e.kind, e.massege))
+    }
+
+    // 迭代器的下一项；对象的 next 方法返回 null 时结束
+    fn next_item(&mut self, iter: &RefCell<Iter>) -> Result<Option<(Value, Value)>, InterpretResult> {
+        let next = match &*iter.borrow() {
+            Iter::Object { next, .. } => Some(next.clone()),
+            _ => None,
+        };
+        let next = match next {
+            Some(next) => next,
+            None => return Ok(iter.borrow_mut().advance()),
+        };
+        let v = self.call_nested(next)?;
+        Ok(iter.borrow_mut().object_item(v))
+    }
+
+    // 载入模块并返回其导出的映射，每个模块只执行一次
+    fn import(&mut self, spec: &str) -> Result<Value, InterpretResult> {
+        if !self.io_allowed {
+            return Err(self.error(trf("{} is not allowed in this sandbox.", &[&"import"])));
+        }
+        let path = match self.resolve_module(spec) {
+            Some(p) => p,
+            None => return Err(self.error(trf("Can't find module '{}'.", &[&spec]))),
+        };
+        let key = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if let Some(i) = self.modules.iter().position(|m| m.path == key) {
+            if let Some(exports) = &self.modules[i].exports {
+                return Ok(exports.clone());
+            }
+            // 尚未执行完的模块依次嵌套在后面
+            let mut chain: Vec<&str> = self.modules[i..].iter()
+                .filter(|m| m.exports.is_none() && !m.path.as_os_str().is_empty())
+                .map(|m| m.name.as_str())
+                .collect();
+            chain.push(&self.modules[i].name);
+            return Err(self.error(trf("Circular import: {}.", &[&chain.join(" -> ")])));
+        }
+
+        let name = path.display().to_string();
+        let code = match fs::read_to_string(&path) {
+            Ok(code) => code,
+            Err(e) => return Err(self.error(trf("{} failed on '{}': {}", &[&"import", &name, &e]))),
+        };
+        let keywords = KeywordSet::from_pragma(&code).unwrap_or_else(|| self.keywords.clone());
+        let (chunk, exports) = match Compiler::new(Scanner::with_keywords(&code, keywords)).compile_module() {
+            Ok(ret) => ret,
+            Err(errors) => return Err(self.error(trf("Can't compile module '{}': {}", &[&name, &errors[0]]))),
+        };
+        if self.frames.len() == FRAMES_MAX {
+            return Err(self.error(tr("Stack overflow.")));
+        }
+        let function = Rc::new(Function::new(&name, 0, chunk));
+        if self.verified {
+            let needed = match verifier::verify_function(&function) {
+                Ok(n) => n,
+                Err(e) => return Err(self.error(trf("invalid bytecode: {}", &[&e]))),
+            };
+            if self.stack.len() + needed > self.stack_max {
+                return Err(self.error(tr("Stack overflow.")));
+            }
+        }
+
+        self.modules.push(LoadedModule { path: key, name: name.clone(), globals: HashMap::new(), exports: None });
+        let id = self.modules.len();
+        function.set_module(id);
+        let (op_start, depth, base) = (self.op_start, self.frames.len(), self.stack.len());
+        self.frames.push(CallFrame { function, ip: 0, base });
+        let result = self.execute(depth);
+        self.op_start = op_start;
+        if !matches!(result, InterpretResult::Ok) {
+            // 丢弃模块中未完成的调用，错误按导入语句所在行报告
+            self.frames.truncate(depth);
+            self.stack.truncate(base);
+            self.handlers.retain(|h| h.frames <= depth);
+        }
+        match result {
+            InterpretResult::Ok => {},
+            InterpretResult::RUNTIME_ERROR(e) => {
+                self.modules[id - 1].path = PathBuf::new();
+                self.thrown = None;
+                return Err(self.error_of(e.kind, trf("Error in module '{}' at line {}: {}", &[&name, &e.line, &e.massege])));
+            },
+            r => {
+                self.modules[id - 1].path = PathBuf::new();
+                return Err(r);
             },
-            None => {
-                None
+        }
+
+        let mut members = BTreeMap::new();
+        for export in exports {
+            match self.modules[id - 1].globals.get(&export) {
+                Some(v) => {
+                    members.insert(export, v.clone());
+                },
+                None => {
+                    self.modules[id - 1].path = PathBuf::new();
+                    return Err(self.error(trf("Module '{}' exports undefined name '{}'.", &[&name, &export])));
+                },
             }
         }
+        let exports = Value::map(members);
+        self.modules[id - 1].exports = Some(exports.clone());
+        Ok(exports)
+    }
+
+    // 带扩展名时按原样查找，否则加上 .tl；相对路径先在导入者所在目录查找，再查找 search_paths
+    fn resolve_module(&self, spec: &str) -> Option<PathBuf> {
+        let file = match Path::new(spec).extension() {
+            Some(_) => PathBuf::from(spec),
+            None => PathBuf::from(format!("{}.tl", spec)),
+        };
+        if file.is_absolute() {
+            return file.is_file().then_some(file);
+        }
+
+        let importer = match self.frame().function.module.get() {
+            0 => self.script_path.as_deref(),
+            m => Some(self.modules[m - 1].path.as_path()),
+        };
+        let base = importer.and_then(Path::parent).unwrap_or(Path::new("")).to_path_buf();
+        std::iter::once(base).chain(self.search_paths.iter().cloned())
+            .map(|dir| dir.join(&file))
+            .find(|p| p.is_file())
+    }
+
+    // 模块中的代码先查找模块自身的全局变量，再查找主脚本的全局变量（内置函数等在其中）
+    fn lookup_global(&self, name: &str) -> Option<&Value> {
+        match self.frame().function.module.get() {
+            0 => self.globals.get(name),
+            m => self.modules[m - 1].globals.get(name).or_else(|| self.globals.get(name)),
+        }
+    }
+
+    // 定义与赋值只作用于当前模块
+    fn globals_mut(&mut self) -> &mut HashMap<String, Value> {
+        match self.frame().function.module.get() {
+            0 => &mut self.globals,
+            m => &mut self.modules[m - 1].globals,
+        }
+    }
+
+    pub fn advance(&mut self) -> Option<Opcode> {
+        let frame = self.frames.last_mut()?;
+        let (ret, size) = frame.function.chunk.get_op(frame.ip)?;
+        frame.ip += size;
+        Some(ret)
+    }
+
+    pub fn warnings(&self) -> &[CompileError] {
+        &self.warnings
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, val: Value) {
+        self.globals.insert(name.to_string(), val);
+    }
+
+    pub fn set_args(&mut self, args: Vec<String>) -> &mut Self {
+        self.args = args;
+        self
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    // 注册本地函数，调用时检查参数个数
+    pub fn define_native(&mut self, name: &str, arity: usize, fun: NativeFn) {
+        self.set_global(name, Value::Native(Native { name: name.to_string(), arity: Some(arity), fun }));
+    }
+
+    // 注册接受任意个参数的本地函数
+    pub fn define_native_variadic(&mut self, name: &str, fun: NativeFn) {
+        self.set_global(name, Value::Native(Native { name: name.to_string(), arity: None, fun }));
+    }
+
+    // 当前的本地函数返回后结束执行，interpret 返回 EXIT(code)
+    pub fn exit(&mut self, code: i32) {
+        self.exit_code = Some(code);
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().unwrap()
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn chunk(&self) -> &Chunk {
+        &self.frame().function.chunk
+    }
+
+    // 即使 chunk 已经检查过也要确认，随后的出栈不会因构造的字节码而 panic
+    fn underflow(&self, n: usize) -> bool {
+        self.stack.len() < n
+    }
+
+    fn push(&mut self, v: Value) -> Result<(), InterpretResult> {
+        if !self.verified && self.stack.len() == self.stack_max {
+            return Err(self.error(tr("Stack overflow.")));
+        }
+
+        self.stack.push(v);
+        Ok(())
+    }
+
+    // 以当前指令所在的行报告运行时错误
+    fn error(&self, massege: impl Into<String>) -> InterpretResult {
+        self.error_of(ErrorKind::Error, massege)
+    }
+
+    fn error_of(&self, kind: ErrorKind, massege: impl Into<String>) -> InterpretResult {
+        InterpretResult::RUNTIME_ERROR(RuntimeError {
+            line: self.frames.last().and_then(|f| f.function.chunk.get_line(self.op_start)).unwrap_or(0),
+            massege: massege.into(),
+            kind,
+        })
+    }
+
+    // 抛出任意值；没有处理器时，错误对象还原为原来的错误，其他值报告为未捕获的异常
+    fn raise(&mut self, v: Value) -> InterpretResult {
+        let result = match RuntimeError::from_value(&v) {
+            Some(e) => InterpretResult::RUNTIME_ERROR(e),
+            None => self.error(trf("Uncaught exception: {}", &[&v])),
+        };
+        self.thrown = Some(v);
+        result
     }
 }
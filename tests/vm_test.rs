@@ -0,0 +1,470 @@
+#[test]
+fn interpret_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::{chunk::*, value::*}};
+
+    let mut vm = VM::from(Chunk::new());
+    assert_eq!(vm.interpret("let a = 1; let i = 0; while i < 3 { let t = i; a = a * 2; i = t + 1; }"), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("a"), Some(Value::Double(d)) if *d == 8.0));
+    assert!(matches!(vm.get_global("i"), Some(Value::Double(d)) if *d == 3.0));
+
+    assert_eq!(vm.interpret("let b = a >= 8 && !(a == 9) || false;"), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("b"), Some(Value::Bool(true))));
+}
+
+#[test]
+fn compile_error_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::chunk::*};
+
+    let mut vm = VM::from(Chunk::new());
+    match vm.interpret("let a = ;\nprint a\nlet c = 1;\n{ let d = 2; d + ; }") {
+        InterpretResult::COMPLE_ERROR(errors) => {
+            let lines: Vec<u32> = errors.iter().map(|e| e.line).collect();
+            assert_eq!(lines, vec![1, 3, 4]);
+        },
+        _ => panic!("expect compile error"),
+    }
+}
+
+#[test]
+fn unicode_identifier_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::{chunk::*, value::*}};
+
+    let mut vm = VM::from(Chunk::new());
+    assert_eq!(vm.interpret("let cafe\u{0301} = 1; caf\u{00e9} = caf\u{00e9} + 1; let p\u{0430}y = 0;"), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("caf\u{00e9}"), Some(Value::Double(d)) if *d == 2.0));
+    assert_eq!(vm.warnings().len(), 1);
+}
+
+#[test]
+fn chinese_keywords_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::{chunk::*, value::*}, lexical_analysis::keywords::*,
+        interpreter_error::catalog::*};
+
+    let mut vm = VM::from(Chunk::new());
+    assert_eq!(vm.interpret("// tlang: keywords=zh\n令 和 = 0; 令 i = 1; 当 i <= 4 { 和 = 和 + i; i = i + 1; }"), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("和"), Some(Value::Double(d)) if *d == 10.0));
+
+    vm.set_keywords(KeywordSet::chinese());
+    assert_eq!(vm.interpret("如果 和 > 5 { 和 = 真; } 否则 { 和 = 假; }"), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("和"), Some(Value::Bool(true))));
+
+    set_locale(Locale::Zh);
+    match vm.interpret("令 = 1;") {
+        InterpretResult::COMPLE_ERROR(errors) => assert_eq!(errors[0].to_string(), "[第 1 行] 错误，位于 '='：此处应为变量名。"),
+        _ => panic!("expect compile error"),
+    }
+    set_locale(Locale::En);
+}
+
+#[test]
+fn const_long_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::{chunk::*, value::*}};
+
+    // 超过 256 个常量时使用 OP_CONST_LONG
+    let code: String = (0..300).map(|i| format!("let v{} = {};\n", i, i)).collect();
+    let mut vm = VM::from(Chunk::new());
+    assert_eq!(vm.interpret(&code), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("v299"), Some(Value::Double(d)) if *d == 299.0));
+}
+
+#[test]
+fn verified_chunk_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::{chunk::*, op::*, value::*, verifier::*}, compiler::compiler::compile};
+
+    let chunk = Chunk::deserialize(&compile("let a = 2; { let b = a * (a + 1); a = b; }").unwrap().serialize()).unwrap();
+    let mut vm = VM::from(Chunk::new());
+    vm.set_verified_chunk(chunk).unwrap();
+    assert_eq!(vm.run(), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("a"), Some(Value::Double(d)) if *d == 6.0));
+
+    // 所需栈深度超过上限时在执行前拒绝
+    let mut chunk = Chunk::new();
+    chunk.push_val(Value::Double(1.0));
+    for _ in 0..3 {
+        chunk.push_op(Opcode::OP_CONST(0), 1);
+    }
+    chunk.push_op(Opcode::OP_RETURN, 1);
+    vm.set_stack_max(2);
+    assert_eq!(vm.set_verified_chunk(chunk).err(), Some(VerifyError::StackOverflow { needed: 3, max: 2 }));
+
+    // 从文件读入的字节码在检查时拒绝，不会让 VM 在出栈时 panic
+    let mut chunk = Chunk::new();
+    chunk.push_val(Value::Double(3.0));
+    for op in [Opcode::OP_CONST(0), Opcode::OP_CONST(0), Opcode::OP_END_FINALLY, Opcode::OP_NULL, Opcode::OP_NULL, Opcode::OP_ADD, Opcode::OP_RETURN] {
+        chunk.push_op(op, 1);
+    }
+    let chunk = Chunk::deserialize(&chunk.serialize()).unwrap();
+    assert_eq!(vm.set_verified_chunk(chunk).err(), Some(VerifyError::BadFinally { offset: 4 }));
+
+    // 未经检查的 chunk 中 finally 的完成方式无法识别时报错，而不是继续执行
+    let mut chunk = Chunk::new();
+    for op in [Opcode::OP_TRUE, Opcode::OP_NULL, Opcode::OP_END_FINALLY, Opcode::OP_NULL, Opcode::OP_RETURN] {
+        chunk.push_op(op, 1);
+    }
+    vm.set_chunk(chunk);
+    match vm.run() {
+        InterpretResult::RUNTIME_ERROR(e) => assert_eq!(e.massege, "Invalid instruction."),
+        r => panic!("{:?}", r),
+    }
+
+    // 未经检查的 chunk 向回跳到开头之前时报错
+    let mut chunk = Chunk::new();
+    for op in [Opcode::OP_LOOP(9), Opcode::OP_RETURN] {
+        chunk.push_op(op, 1);
+    }
+    let mut vm = VM::from(chunk);
+    match vm.run() {
+        InterpretResult::RUNTIME_ERROR(e) => assert_eq!(e.massege, "Invalid jump."),
+        r => panic!("{:?}", r),
+    }
+}
+
+#[test]
+fn script_args_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::{chunk::*, value::*}};
+
+    let mut vm = VM::from(Chunk::new());
+    vm.set_args(vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(vm.interpret("let n = arg_count(); let s = arg(0) + arg(1); let m = arg(2);"), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("n"), Some(Value::Double(d)) if *d == 2.0));
+    assert!(matches!(vm.get_global("s"), Some(Value::String(s)) if s == "ab"));
+    assert!(matches!(vm.get_global("m"), Some(Value::Null)));
+
+    assert_eq!(vm.interpret("let p = env(\"PATH\"); let q = env(\"TLANG_TEST_UNSET_VARIABLE\");"), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("p"), Some(Value::String(_))));
+    assert!(matches!(vm.get_global("q"), Some(Value::Null)));
+
+    // exit 之后的语句不再执行
+    assert_eq!(vm.interpret("let r = 1; exit(3); r = 2;"), InterpretResult::EXIT(3));
+    assert!(matches!(vm.get_global("r"), Some(Value::Double(d)) if *d == 1.0));
+
+    assert!(matches!(vm.interpret("exit(\"x\");"), InterpretResult::RUNTIME_ERROR(_)));
+    match vm.interpret("exit(256);") {
+        InterpretResult::RUNTIME_ERROR(e) => assert_eq!(e.massege, "exit expects a code between 0 and 255 but got 256."),
+        r => panic!("{:?}", r),
+    }
+    assert!(matches!(vm.interpret("r(1);"), InterpretResult::RUNTIME_ERROR(_)));
+}
+
+#[test]
+fn native_test() {
+    use tlang::{vm::{vm::*, interpret_result::*, runtime_error::*}, chunk::{chunk::*, value::*}};
+
+    fn add(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+        match (&args[0], &args[1]) {
+            (Value::Double(a), Value::Double(b)) => Ok(Value::Double(a + b)),
+            _ => Err(RuntimeError::new("add expects numbers")),
+        }
+    }
+    fn count(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+        Ok(Value::Double(args.len() as f64))
+    }
+
+    let mut vm = VM::from(Chunk::new());
+    vm.define_native("add", 2, add);
+    vm.define_native_variadic("count", count);
+    assert_eq!(vm.interpret("let a = add(1, add(2, 3)); let c = count() + count(1, 2, 3);"), InterpretResult::Ok);
+    assert_eq!(vm.get_global("a"), Some(&Value::Double(6.0)));
+    assert_eq!(vm.get_global("c"), Some(&Value::Double(3.0)));
+
+    let err = |r: InterpretResult| match r {
+        InterpretResult::RUNTIME_ERROR(e) => e.to_string(),
+        r => panic!("expect runtime error, got {:?}", r),
+    };
+    assert_eq!(err(vm.interpret("print 1;\nadd(1);")), "[line 2] runtime error: add expects 2 arguments but got 1.");
+    assert_eq!(err(vm.interpret("\n\nadd(1, \"x\");")), "[line 3] runtime error: add expects numbers");
+    assert_eq!(err(vm.interpret("env(1);")), "[line 1] runtime error: env expects argument 1 to be string but got number.");
+    assert_eq!(err(vm.interpret("a();")), "[line 1] runtime error: Can only call functions.");
+    assert_eq!(err(vm.interpret("print nope;")), "[line 1] runtime error: Undefined variable 'nope'.");
+    assert_eq!(err(vm.interpret("print -\"s\";")), "[line 1] runtime error: Operand must be a number.");
+}
+
+#[test]
+fn function_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::{chunk::*, value::*}, compiler::compiler::compile};
+
+    let code = "fn fib(n) { if n < 2 { return n; } return fib(n - 1) + fib(n - 2); }\nlet r = fib(15);\nfn down(n) { return down(n + 1); }";
+    let mut vm = VM::from(Chunk::new());
+    assert_eq!(vm.interpret(code), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("r"), Some(Value::Double(d)) if *d == 610.0));
+
+    // 无限递归在进入函数时报告栈溢出
+    match vm.interpret("down(0);") {
+        InterpretResult::RUNTIME_ERROR(e) => assert_eq!(e.to_string(), "[line 3] runtime error: Stack overflow."),
+        r => panic!("{:?}", r),
+    }
+
+    // 函数随字节码一起序列化
+    let bytes = compile(code).unwrap().serialize();
+    let mut vm = VM::from(Chunk::new());
+    vm.set_verified_chunk(Chunk::deserialize(&bytes).unwrap()).unwrap();
+    assert_eq!(vm.run(), InterpretResult::Ok);
+    assert!(matches!(vm.get_global("r"), Some(Value::Double(d)) if *d == 610.0));
+}
+
+#[test]
+fn module_test() {
+    use std::{cell::RefCell, env, fs, io, process, rc::Rc};
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::chunk::*};
+
+    // 收集 print 的输出
+    struct Output(Rc<RefCell<Vec<u8>>>);
+    impl io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let dir = env::temp_dir().join(format!("tlang_module_test_{}", process::id()));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("counter.tl"), "let count = 0;\nlet hidden = 1;\nexport fn next() { count = count + 1; return count; }\nexport let name = \"counter\";\nprint \"loaded\";").unwrap();
+    fs::write(dir.join("lib/shapes.tl"), "import \"../counter.tl\" as counter;\nfn area(w, h) { return w * h; }\nlet unit = counter.next();\nexport area, unit;").unwrap();
+    fs::write(dir.join("a.tl"), "import b;\nexport let x = 1;").unwrap();
+    fs::write(dir.join("b.tl"), "import a;\nexport let y = 2;").unwrap();
+    fs::write(dir.join("bad.tl"), "export nothing;").unwrap();
+
+    let out = Rc::new(RefCell::new(Vec::new()));
+    let mut vm = VM::from(Chunk::new());
+    vm.set_script_path(dir.join("main.tl")).add_search_path(dir.join("lib")).set_output(Box::new(Output(out.clone())));
+    // counter 只执行一次，shapes 与主脚本共享同一个实例；模块的全局变量不影响主脚本
+    let code = "let count = 100;
+import \"counter.tl\" as c;
+import shapes;
+{ import counter as again; print again.next(); }
+print c.name;
+print c.next();
+print shapes.area(2, 3);
+print shapes.unit;
+print count;";
+    assert_eq!(vm.interpret(code), InterpretResult::Ok);
+    assert_eq!(String::from_utf8(out.borrow().clone()).unwrap(), "loaded\n2\ncounter\n3\n6\n1\n100\n");
+    assert!(vm.get_global("hidden").is_none());
+
+    let mut error = |code: &str| match vm.interpret(code) {
+        InterpretResult::RUNTIME_ERROR(e) => e.massege,
+        r => panic!("{:?}", r),
+    };
+    assert_eq!(error("import counter;\ncounter.hidden;"), "Undefined property 'hidden' on map.");
+    assert_eq!(error("import missing;"), "Can't find module 'missing'.");
+    let (a, b) = (dir.join("a.tl").display().to_string(), dir.join("b.tl").display().to_string());
+    assert_eq!(error("import a;"), format!("Error in module '{}' at line 1: Error in module '{}' at line 1: Circular import: {} -> {} -> {}.", a, b, a, b, a));
+    assert_eq!(error("import bad;"), format!("Module '{}' exports undefined name 'nothing'.", dir.join("bad.tl").display()));
+
+    vm.set_io_allowed(false);
+    match vm.interpret("import shapes;") {
+        InterpretResult::RUNTIME_ERROR(e) => assert_eq!(e.massege, "import is not allowed in this sandbox."),
+        r => panic!("{:?}", r),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn exception_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::{chunk::*, value::*}};
+
+    let mut vm = VM::from(Chunk::new());
+    let code = "let log = \"\";
+fn f(x) {
+    try {
+        if x > 1 { return \"try\"; }
+        throw \"boom\";
+    } catch (e) {
+        log = log + e + \",\";
+        return \"catch\";
+    } finally {
+        log = log + \"finally,\";
+    }
+}
+let a = f(1) + f(2);
+fn deep(n) { let pad = n; if n == 0 { throw n; } return deep(n - 1) + pad; }
+let b = null;
+try { deep(5); } catch (e) { b = e; }
+let c = null;
+try { try { throw 1; } finally { log = log + \"inner,\"; } } catch (e) { c = e + 1; }";
+    assert_eq!(vm.interpret(code), InterpretResult::Ok);
+    assert_eq!(vm.get_global("log").unwrap().to_string(), "boom,finally,finally,inner,");
+    assert_eq!(vm.get_global("a").unwrap().to_string(), "catchtry");
+    assert!(matches!(vm.get_global("b"), Some(Value::Double(d)) if *d == 0.0));
+    assert!(matches!(vm.get_global("c"), Some(Value::Double(d)) if *d == 2.0));
+
+    // 内置的运行时错误成为错误对象
+    let code = "let errors = \"\";
+fn record(e) { errors = errors + e.kind + \"@\" + \"{}\".format(e.line) + \": \" + e.message + \";\"; }
+try { 1 / 0; } catch (e) { record(e); }
+try {
+    -\"a\";
+} catch (e) { record(e); }
+try { \"abc\".substring(9); } catch (e) { record(e); }
+try { nope; } catch (e) { record(e); }
+try { print 1; } catch { record(null); }
+try { print \"a\" + 1; } catch (e) { record(e); }
+try { null * null; } catch (e) { record(e); }";
+    assert_eq!(vm.interpret(code), InterpretResult::Ok);
+    assert_eq!(vm.get_global("errors").unwrap().to_string(), "DivisionError@3: Division by zero.;\
+TypeError@5: Operand must be a number.;\
+IndexError@7: string.substring index 9 is out of range for length 3.;\
+Error@8: Undefined variable 'nope'.;\
+TypeError@10: Operands must be numbers.;\
+TypeError@11: Operands must be numbers.;");
+
+    // 未捕获时报告抛出处；再次抛出的错误对象保留原来的行号
+    match vm.interpret("let x = 1;\nthrow \"bad\";") {
+        InterpretResult::RUNTIME_ERROR(e) => assert_eq!((e.line, e.massege.as_str()), (2, "Uncaught exception: bad")),
+        r => panic!("{:?}", r),
+    }
+    match vm.interpret("try {\n1 / 0;\n} catch (e) {\nthrow e;\n}") {
+        InterpretResult::RUNTIME_ERROR(e) => assert_eq!((e.line, e.massege.as_str()), (2, "Division by zero.")),
+        r => panic!("{:?}", r),
+    }
+    // 出错后处理器被清空，不会影响下一次执行
+    assert_eq!(vm.interpret("let ok = 1;"), InterpretResult::Ok);
+}
+
+#[test]
+fn for_loop_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::{chunk::*, value::*}};
+
+    let mut vm = VM::from(Chunk::new());
+    vm.set_global("text", Value::String("{\"b\": 2, \"a\": 1}".to_string()));
+    let mut run = |code: &str| {
+        assert_eq!(vm.interpret(code), InterpretResult::Ok, "{}", code);
+        vm.take_result().to_string()
+    };
+    assert_eq!(run("let s = \"\"; for x in \"a,b,c\".split(\",\") { s = s + x; } s"), "abc");
+    assert_eq!(run("let s = \"\"; for i, c in \"中文\" { s = s + \"{}{}\".format(i, c); } s"), "0中1文");
+    assert_eq!(run("let s = \"\"; let m = json.parse(text); for k in m { s = s + k; } for k, v in m { s = s + \"{}\".format(v); } s"), "ab12");
+    assert_eq!(run("let n = 0; for i in 0..10 { n = n + i; } n"), "45");
+    assert_eq!(run("let n = 0; for i in 1..=10 { n = n + i; } n"), "55");
+    assert_eq!(run("let s = \"\"; for i in (10..0).step(-3) { s = s + \"{},\".format(i); } s"), "10,7,4,1,");
+    assert_eq!(run("let s = \"\"; let n = 3; for i in 0..n + 1 { s = s + \"{}\".format(i); } s"), "0123");
+
+    // 循环变量与循环体中的局部变量在每次迭代后弹出，break 与 continue 弹出同样多的值
+    let code = "fn f() {
+    let total = 0;
+    for i in 0..100 {
+        let half = i / 2;
+        if i > 9 { let a = 1; let b = 2; break; }
+        for j in 0..=i { if j == 2 { continue; } total = total + 1; }
+        while true { let x = i; break; }
+    }
+    let k = 0;
+    while k < 5 { k = k + 1; let t = k; if t == 3 { continue; } total = total + 100; }
+    return total;
+}
+f()";
+    assert_eq!(run(code), "447");
+
+    // break 与 continue 跳出 try 时先执行 finally；catch 中的 break 不再重复移除处理器
+    let code = "let log = \"\";
+for i in 0..4 {
+    try {
+        if i == 1 { continue; }
+        if i == 3 { break; }
+        try { if i == 2 { throw i; } } finally { log = log + \"in\"; }
+    } catch (e) {
+        log = log + \"catch\";
+        break;
+    } finally {
+        log = log + \"{},\".format(i);
+    }
+}
+try { throw \"after\"; } catch (e) { log = log + e; }
+log";
+    assert_eq!(run(code), "in0,1,incatch2,after");
+
+    // 在循环中返回时不受迭代器的影响
+    assert_eq!(run("fn first(l) { for x in l { if x != \"\" { return x; } } return null; }\nfirst(\",,b,c\".split(\",\"))"), "b");
+
+    let mut error = |code: &str| match vm.interpret(code) {
+        InterpretResult::RUNTIME_ERROR(e) => (e.line, e.kind.name(), e.massege),
+        r => panic!("{:?}", r),
+    };
+    assert_eq!(error("let x = 1;\nfor i in x { }"), (2, "TypeError", "Can't iterate over number.".to_string()));
+    assert_eq!(error("for i in 0..\"a\" { }"), (1, "TypeError", "Range bounds must be numbers.".to_string()));
+    assert_eq!(error("(0..1).step(0);"), (1, "Error", "range.step expects a non-zero step but got 0.".to_string()));
+}
+
+#[test]
+fn iterator_protocol_test() {
+    use std::{env, fs, process};
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::chunk::*};
+
+    // 模块导出的映射可以作为对象：带有 next 的对象是迭代器，带有 iter 的对象返回迭代器
+    let dir = env::temp_dir().join(format!("tlang_iterator_test_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("countdown.tl"), "let n = 3;\nexport fn next() { if n == 0 { return null; } n = n - 1; return n + 1; }").unwrap();
+    fs::write(dir.join("bag.tl"), "import countdown;\nexport fn iter() { return countdown; }").unwrap();
+    fs::write(dir.join("broken.tl"), "let n = 0;\nexport fn next() { n = n + 1; if n == 2 {\nthrow \"broken\"; } return n; }").unwrap();
+    fs::write(dir.join("bad_iter.tl"), "export fn iter() { return 1; }").unwrap();
+
+    let mut vm = VM::from(Chunk::new());
+    vm.set_script_path(dir.join("main.tl"));
+    let code = "import bag;
+let s = \"\";
+for i, x in bag { s = s + \"{}:{},\".format(i, x); }
+for x in bag { s = s + \"again\"; }
+s";
+    assert_eq!(vm.interpret(code), InterpretResult::Ok);
+    assert_eq!(vm.take_result().to_string(), "0:3,1:2,2:1,");
+
+    // next 中抛出的异常交给循环外的处理器
+    let code = "import broken;
+let s = \"\";
+try { for x in broken { s = s + \"{}\".format(x); } } catch (e) { s = s + e; }
+s";
+    assert_eq!(vm.interpret(code), InterpretResult::Ok);
+    assert_eq!(vm.take_result().to_string(), "1broken");
+
+    match vm.interpret("import bad_iter;\nfor x in bad_iter { }") {
+        InterpretResult::RUNTIME_ERROR(e) => assert_eq!((e.line, e.massege.as_str()), (2, "iter() must return an object with a next method.")),
+        r => panic!("{:?}", r),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn labeled_loop_test() {
+    use tlang::{vm::{vm::*, interpret_result::*}, chunk::chunk::*, lexical_analysis::keywords::*};
+
+    let mut vm = VM::from(Chunk::new());
+    let mut run = |code: &str| {
+        assert_eq!(vm.interpret(code), InterpretResult::Ok, "{}", code);
+        vm.take_result().to_string()
+    };
+    // 带标签的 break 与 continue 弹出内层循环的迭代器与局部变量，并执行途经的 finally
+    let code = "let log = \"\";
+'rows: for i in 0..4 {
+    let row = i;
+    'cols: for j in 0..4 {
+        let cell = row * 10 + j;
+        if j > i { continue 'rows; }
+        try {
+            if cell == 31 { break 'rows; }
+        } finally {
+            log = log + \"{},\".format(cell);
+        }
+        while true { if j == 1 { continue 'cols; } break; }
+        log = log + \"+\";
+    }
+}
+log";
+    assert_eq!(run(code), "0,+10,+11,20,+21,22,+30,+31,");
+
+    // 标签循环中的函数有自己的循环
+    let code = "fn count(n) { let c = 0; 'a: while true { c = c + 1; if c == n { break 'a; } } return c; }
+let total = 0;
+'a: for i in 1..=3 { let x = count(i); total = total + x; if i == 2 { break 'a; } }
+total";
+    assert_eq!(run(code), "3");
+
+    let mut vm = VM::from(Chunk::new());
+    vm.set_keywords(KeywordSet::chinese());
+    assert_eq!(vm.interpret("令 和 = 0; '外层: 对于 i 在 0..10 { 如果 i == 5 { 跳出 '外层; } 如果 i == 1 { 继续; } 和 = 和 + i; }\n和"), InterpretResult::Ok);
+    assert_eq!(vm.take_result().to_string(), "9");
+}